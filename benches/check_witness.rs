@@ -0,0 +1,65 @@
+//! Serial against parallel witness checking, on a satisfied system of 2^18 constraints
+//!
+//! Run with `cargo bench --bench check_witness --features parallel`.
+use ark_bn254::Fr;
+use ark_circom::R1CS;
+use criterion::{criterion_group, criterion_main, Criterion};
+
+const NUM_CONSTRAINTS: usize = 1 << 18;
+
+/// Constraint `i` multiplies two wires picked from `i` into a third, which holds
+/// their product in [`witness`]
+fn r1cs() -> R1CS<Fr> {
+    let constraints = (0..NUM_CONSTRAINTS)
+        .map(|i| {
+            let (x, y, z) = wires(i);
+            (
+                vec![(x, Fr::from(1u64))],
+                vec![(y, Fr::from(1u64))],
+                vec![(z, Fr::from(1u64))],
+            )
+        })
+        .collect();
+    R1CS {
+        num_inputs: 1,
+        num_aux: 3 * NUM_CONSTRAINTS,
+        num_variables: 1 + 3 * NUM_CONSTRAINTS,
+        num_pub_out: 0,
+        num_pub_in: 0,
+        outputs_last: false,
+        custom_gates: false,
+        version: 1,
+        constraints,
+    }
+}
+
+fn wires(i: usize) -> (usize, usize, usize) {
+    (1 + 3 * i, 2 + 3 * i, 3 + 3 * i)
+}
+
+fn witness() -> Vec<Fr> {
+    let mut witness = vec![Fr::from(1u64)];
+    for i in 0..NUM_CONSTRAINTS {
+        let x = Fr::from(i as u64 + 2);
+        let y = Fr::from(i as u64 * 7 + 3);
+        witness.extend([x, y, x * y]);
+    }
+    witness
+}
+
+fn check_witness(c: &mut Criterion) {
+    let r1cs = r1cs();
+    let witness = witness();
+    let mut group = c.benchmark_group("check_witness");
+    group.sample_size(20);
+    group.bench_function("serial", |b| {
+        b.iter(|| r1cs.check_witness(&witness).unwrap())
+    });
+    group.bench_function("parallel", |b| {
+        b.iter(|| r1cs.check_witness_parallel(&witness).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, check_witness);
+criterion_main!(benches);
@@ -0,0 +1,109 @@
+//! Witness checking, synthesis and memory use of the nested [`R1CS`] against
+//! [`R1CSFlat`], on a satisfied system of 2^18 constraints with a few terms in each
+//! vector.
+//!
+//! Run with `cargo bench --bench flat`. The heap use of both forms is printed first.
+use ark_bn254::Fr;
+use ark_circom::{CircomCircuit, FlatCircuit, R1CSFlat, R1CS};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+use std::convert::TryFrom;
+use std::mem::size_of;
+
+const NUM_CONSTRAINTS: usize = 1 << 18;
+const NUM_WIRES: usize = 1 << 16;
+
+/// Constraint `i` states `(x + y + z) * 1 = x + y + z` over three wires picked from
+/// `i`, so any witness satisfies the system
+fn r1cs() -> R1CS<Fr> {
+    let constraints = (0..NUM_CONSTRAINTS)
+        .map(|i| {
+            let lc: Vec<_> = (1..4)
+                .map(|k| ((i * 7919 * k) % (NUM_WIRES - 1) + 1, Fr::from(k as u64)))
+                .collect();
+            (lc.clone(), vec![(0, Fr::from(1u64))], lc)
+        })
+        .collect();
+    R1CS {
+        num_inputs: 1,
+        num_aux: NUM_WIRES - 1,
+        num_variables: NUM_WIRES,
+        num_pub_out: 0,
+        num_pub_in: 0,
+        outputs_last: false,
+        custom_gates: false,
+        version: 1,
+        constraints,
+    }
+}
+
+fn nested_heap_bytes(r1cs: &R1CS<Fr>) -> usize {
+    let term = size_of::<(usize, Fr)>();
+    r1cs.constraints.capacity()
+        * size_of::<(Vec<(usize, Fr)>, Vec<(usize, Fr)>, Vec<(usize, Fr)>)>()
+        + r1cs
+            .constraints
+            .iter()
+            .map(|(a, b, c)| (a.capacity() + b.capacity() + c.capacity()) * term)
+            .sum::<usize>()
+}
+
+fn flat_heap_bytes(flat: &R1CSFlat<Fr>) -> usize {
+    flat.offsets.capacity() * size_of::<u32>()
+        + flat.indices.capacity() * size_of::<u32>()
+        + flat.coeffs.capacity() * size_of::<Fr>()
+}
+
+/// Wire 0 is the constant one
+fn witness() -> Vec<Fr> {
+    (0..NUM_WIRES as u64).map(|i| Fr::from(i.max(1))).collect()
+}
+
+fn check_witness(c: &mut Criterion) {
+    let r1cs = r1cs();
+    let flat = R1CSFlat::try_from(&r1cs).unwrap();
+    let witness = witness();
+    println!(
+        "heap: nested {} MiB, flat {} MiB",
+        nested_heap_bytes(&r1cs) >> 20,
+        flat_heap_bytes(&flat) >> 20
+    );
+
+    let mut group = c.benchmark_group("check_witness");
+    group.sample_size(20);
+    group.bench_function("nested", |b| {
+        b.iter(|| r1cs.check_witness(&witness).unwrap())
+    });
+    group.bench_function("flat", |b| b.iter(|| flat.check_witness(&witness).unwrap()));
+    group.finish();
+}
+
+fn synthesis(c: &mut Criterion) {
+    let nested = CircomCircuit::new(r1cs(), Some(witness()));
+    let flat = FlatCircuit {
+        r1cs: R1CSFlat::try_from(&nested.r1cs).unwrap(),
+        witness: Some(witness()),
+    };
+
+    let mut group = c.benchmark_group("synthesis");
+    group.sample_size(10);
+    group.bench_function("nested", |b| {
+        b.iter_batched(
+            || (nested.clone(), ConstraintSystem::<Fr>::new_ref()),
+            |(circuit, cs)| circuit.generate_constraints(cs).unwrap(),
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("flat", |b| {
+        b.iter_batched(
+            || (flat.clone(), ConstraintSystem::<Fr>::new_ref()),
+            |(circuit, cs)| circuit.generate_constraints(cs).unwrap(),
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, check_witness, synthesis);
+criterion_main!(benches);
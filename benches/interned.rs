@@ -0,0 +1,89 @@
+//! Memory use of [`R1CSInterned`] against the plain [`R1CS`], on a circuit of 2^18
+//! constraints whose A and B vectors repeat a few selector patterns, and on one whose
+//! vectors are all distinct. Also times the conversion.
+//!
+//! Run with `cargo bench --bench interned`. The heap use of both forms is printed first.
+use ark_bn254::Fr;
+use ark_circom::{R1CSInterned, R1CS};
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use std::mem::size_of;
+
+const NUM_CONSTRAINTS: usize = 1 << 18;
+const NUM_WIRES: usize = 1 << 20;
+
+type Vector = Vec<(usize, Fr)>;
+
+/// `pattern(i)` picks the A vector of constraint `i`, B is a two-term vector derived
+/// from the same pattern and C is wire `i`
+fn r1cs(pattern: impl Fn(usize) -> usize) -> R1CS<Fr> {
+    let vector = |p: usize, len: usize| -> Vector {
+        (0..len)
+            .map(|k| {
+                (
+                    (p * 37 + k * 7) % (NUM_WIRES - 1) + 1,
+                    Fr::from(k as u64 + 1),
+                )
+            })
+            .collect()
+    };
+    let constraints = (0..NUM_CONSTRAINTS)
+        .map(|i| {
+            let p = pattern(i);
+            (
+                vector(p, 4),
+                vector(p + 1, 2),
+                vec![(i % NUM_WIRES, Fr::from(1u64))],
+            )
+        })
+        .collect();
+    R1CS {
+        num_inputs: 1,
+        num_aux: NUM_WIRES - 1,
+        num_variables: NUM_WIRES,
+        num_pub_out: 0,
+        num_pub_in: 0,
+        outputs_last: false,
+        custom_gates: false,
+        version: 1,
+        constraints,
+    }
+}
+
+fn vector_bytes(lc: &Vector) -> usize {
+    size_of::<Vector>() + lc.capacity() * size_of::<(usize, Fr)>()
+}
+
+fn plain_heap_bytes(r1cs: &R1CS<Fr>) -> usize {
+    r1cs.constraints
+        .iter()
+        .map(|(a, b, c)| vector_bytes(a) + vector_bytes(b) + vector_bytes(c))
+        .sum()
+}
+
+fn interned_heap_bytes(interned: &R1CSInterned<Fr>) -> usize {
+    interned.vectors.iter().map(vector_bytes).sum::<usize>()
+        + interned.constraints.capacity() * size_of::<(u32, u32, u32)>()
+}
+
+fn interning(c: &mut Criterion) {
+    let mut group = c.benchmark_group("intern");
+    group.sample_size(10);
+    for (name, r1cs) in [
+        ("repeated", r1cs(|i| i % 16)),
+        ("distinct", r1cs(|i| i * 2)),
+    ] {
+        let interned = R1CSInterned::from(&r1cs);
+        println!(
+            "{}: plain {} MiB, interned {} MiB",
+            name,
+            plain_heap_bytes(&r1cs) >> 20,
+            interned_heap_bytes(&interned) >> 20
+        );
+        group.bench_function(name, |b| b.iter(|| R1CSInterned::from(&r1cs)));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, interning);
+criterion_main!(benches);
@@ -0,0 +1,80 @@
+//! Variable allocation during synthesis, on a circuit with a large witness and no
+//! constraints, against allocating each variable with a lookup of its value source.
+//!
+//! Run with `cargo bench --bench synthesis`.
+use ark_bn254::Fr;
+use ark_circom::{CircomCircuit, R1CS};
+use ark_relations::r1cs::{ConstraintSystem, ConstraintSystemRef, SynthesisError};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+const NUM_INPUTS: usize = 1 << 10;
+const NUM_AUX: usize = 1 << 20;
+
+fn circuit() -> CircomCircuit<Fr> {
+    let r1cs = R1CS {
+        num_inputs: NUM_INPUTS,
+        num_aux: NUM_AUX,
+        num_variables: NUM_INPUTS + NUM_AUX,
+        num_pub_out: 0,
+        num_pub_in: NUM_INPUTS - 1,
+        outputs_last: false,
+        custom_gates: false,
+        version: 1,
+        constraints: Vec::new(),
+    };
+    let witness = (0..NUM_INPUTS + NUM_AUX)
+        .map(|i| Fr::from(i as u64))
+        .collect();
+    CircomCircuit::new(r1cs, Some(witness))
+}
+
+/// Allocation as `generate_constraints` did it before the value source was resolved
+/// up front: every closure matches on the witness again
+fn allocate_per_variable(
+    circuit: &CircomCircuit<Fr>,
+    cs: &ConstraintSystemRef<Fr>,
+) -> Result<(), SynthesisError> {
+    let witness = &circuit.witness;
+    let num_inputs = circuit.r1cs.num_inputs;
+    for i in 1..num_inputs {
+        cs.new_input_variable(|| {
+            Ok(match witness {
+                Some(w) => w[i],
+                None => Fr::from(1u32),
+            })
+        })?;
+    }
+    for i in 0..circuit.r1cs.num_aux {
+        cs.new_witness_variable(|| {
+            Ok(match witness {
+                Some(w) => w[i + num_inputs],
+                None => Fr::from(1u32),
+            })
+        })?;
+    }
+    Ok(())
+}
+
+fn allocation(c: &mut Criterion) {
+    let circuit = circuit();
+    let mut group = c.benchmark_group("allocation");
+    group.sample_size(20);
+    group.bench_function("resolved", |b| {
+        b.iter_batched(
+            ConstraintSystem::<Fr>::new_ref,
+            |cs| circuit.synthesize_timed(cs).unwrap(),
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("per_variable", |b| {
+        b.iter_batched(
+            ConstraintSystem::<Fr>::new_ref,
+            |cs| allocate_per_variable(&circuit, &cs).unwrap(),
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, allocation);
+criterion_main!(benches);
@@ -0,0 +1,105 @@
+//! Prints the size, coefficient statistics and leading constraints of a `.r1cs` file
+//!
+//! ```text
+//! r1cs-info <circuit.r1cs> [--field <bn254|m31>] [--sym <circuit.sym>] [--constraints <n>] [--raw]
+//! ```
+//!
+//! The field defaults to M31. With `--raw`, constraint coefficients are printed as their
+//! canonical values rather than as negative numbers above `p / 2`.
+use ark_circom::format::format_constraint_with;
+use ark_circom::{AnyR1CS, FieldKind, MatrixStats, R1CSError, SymFile, R1CS};
+use ark_ff::PrimeField;
+
+use std::fs::File;
+use std::io::BufReader;
+use std::process;
+
+struct Args {
+    r1cs: String,
+    field: FieldKind,
+    sym: Option<String>,
+    constraints: usize,
+    raw: bool,
+}
+
+const USAGE: &str = "usage: r1cs-info <circuit.r1cs> [--field <bn254|m31>] [--sym <circuit.sym>] [--constraints <n>] [--raw]";
+
+fn parse_args() -> Option<Args> {
+    let mut args = std::env::args().skip(1);
+    let mut r1cs = None;
+    let mut field = FieldKind::M31;
+    let mut sym = None;
+    let mut constraints = 0;
+    let mut raw = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--field" => field = args.next()?.parse().ok()?,
+            "--sym" => sym = Some(args.next()?),
+            "--constraints" => constraints = args.next()?.parse().ok()?,
+            "--raw" => raw = true,
+            _ if r1cs.is_none() => r1cs = Some(arg),
+            _ => return None,
+        }
+    }
+    Some(Args {
+        r1cs: r1cs?,
+        field,
+        sym,
+        constraints,
+        raw,
+    })
+}
+
+fn print_stats<F: PrimeField>(name: &str, stats: &MatrixStats<F>) {
+    println!(
+        "{}: {} terms, {} unique, {} ones, {} minus ones",
+        name, stats.num_terms, stats.unique, stats.ones, stats.minus_ones
+    );
+}
+
+fn run(args: Args) -> Result<(), R1CSError> {
+    let r1cs = AnyR1CS::new(args.field, BufReader::new(File::open(&args.r1cs)?))?;
+    let sym = match &args.sym {
+        Some(path) => Some(SymFile::new(File::open(path)?)?),
+        None => None,
+    };
+
+    match &r1cs {
+        AnyR1CS::Bn254(r1cs) => print_info(r1cs, sym.as_ref(), args.constraints, args.raw),
+        AnyR1CS::M31(r1cs) => print_info(r1cs, sym.as_ref(), args.constraints, args.raw),
+    }
+}
+
+fn print_info<F: PrimeField>(
+    r1cs: &R1CS<F>,
+    sym: Option<&SymFile>,
+    constraints: usize,
+    raw: bool,
+) -> Result<(), R1CSError> {
+    println!("{}", r1cs.summary());
+
+    let stats = r1cs.coefficient_stats();
+    print_stats("A", &stats.a);
+    print_stats("B", &stats.b);
+    print_stats("C", &stats.c);
+
+    let shown = constraints.min(r1cs.constraints.len());
+    for (i, constraint) in r1cs.constraint_range(0, shown)?.iter().enumerate() {
+        println!("{}: {}", i, format_constraint_with(constraint, sym, raw));
+    }
+    Ok(())
+}
+
+fn main() {
+    let args = match parse_args() {
+        Some(args) => args,
+        None => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+    if let Err(err) = run(args) {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
+}
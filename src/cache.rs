@@ -0,0 +1,174 @@
+//! On-disk cache of computed witnesses
+//!
+//! Witnesses are stored ark-serialized, one file per input assignment, named after a
+//! SHA-256 digest of the inputs and the field modulus. The cache does not know which
+//! circuit a witness belongs to, so each circuit needs its own directory.
+use ark_ff::{BigInteger, PrimeField};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use num_bigint::BigInt;
+use sha2::{Digest, Sha256};
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use crate::error::Result;
+
+pub struct WitnessCache {
+    dir: PathBuf,
+}
+
+impl WitnessCache {
+    /// Opens the cache in `dir`, creating the directory if needed
+    pub fn new<P: AsRef<Path>>(dir: P) -> Result<WitnessCache> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(WitnessCache {
+            dir: dir.as_ref().to_path_buf(),
+        })
+    }
+
+    /// Hex digest identifying `inputs` for the field `F`. Input names are hashed in
+    /// sorted order, so the key does not depend on the order of a JSON object.
+    pub fn key<F: PrimeField>(inputs: &HashMap<String, Vec<BigInt>>) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(F::MODULUS.to_bytes_le());
+
+        let mut names: Vec<&String> = inputs.keys().collect();
+        names.sort();
+        for name in names {
+            hasher.update((name.len() as u64).to_le_bytes());
+            hasher.update(name.as_bytes());
+            let values = &inputs[name];
+            hasher.update((values.len() as u64).to_le_bytes());
+            for value in values {
+                let bytes = value.to_signed_bytes_le();
+                hasher.update((bytes.len() as u64).to_le_bytes());
+                hasher.update(&bytes);
+            }
+        }
+
+        hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    fn path<F: PrimeField>(&self, inputs: &HashMap<String, Vec<BigInt>>) -> PathBuf {
+        self.dir
+            .join(format!("{}.wtns.bin", Self::key::<F>(inputs)))
+    }
+
+    /// Returns the cached witness for `inputs`, if any
+    pub fn load<F: PrimeField>(
+        &self,
+        inputs: &HashMap<String, Vec<BigInt>>,
+    ) -> Result<Option<Vec<F>>> {
+        let file = match File::open(self.path::<F>(inputs)) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Some(Vec::deserialize_compressed(BufReader::new(file))?))
+    }
+
+    /// Stores `witness` as the witness for `inputs`, replacing any previous entry
+    pub fn store<F: PrimeField>(
+        &self,
+        inputs: &HashMap<String, Vec<BigInt>>,
+        witness: &[F],
+    ) -> Result<()> {
+        let path = self.path::<F>(inputs);
+        // Write to a temporary file first so readers never see a partial entry
+        let tmp = path.with_extension("tmp");
+        let mut writer = BufWriter::new(File::create(&tmp)?);
+        witness.serialize_compressed(&mut writer)?;
+        writer.into_inner().map_err(|err| err.into_error())?;
+        fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    /// Returns the cached witness for `inputs`, or computes and stores it on a miss
+    pub fn get_or_compute<F, C>(
+        &self,
+        inputs: &HashMap<String, Vec<BigInt>>,
+        compute: C,
+    ) -> Result<Vec<F>>
+    where
+        F: PrimeField,
+        C: FnOnce() -> Result<Vec<F>>,
+    {
+        if let Some(witness) = self.load(inputs)? {
+            return Ok(witness);
+        }
+        let witness = compute()?;
+        self.store(inputs, &witness)?;
+        Ok(witness)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use std::cell::Cell;
+
+    fn inputs(a: i64, b: i64) -> HashMap<String, Vec<BigInt>> {
+        [("a", a), ("b", b)]
+            .iter()
+            .map(|(name, value)| (name.to_string(), vec![BigInt::from(*value)]))
+            .collect()
+    }
+
+    #[test]
+    fn second_call_with_the_same_inputs_hits_the_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = WitnessCache::new(dir.path().join("mycircuit")).unwrap();
+        let calls = Cell::new(0);
+        let compute = || {
+            calls.set(calls.get() + 1);
+            Ok([1u64, 33, 3, 11].map(Fr::from).to_vec())
+        };
+
+        let first = cache.get_or_compute(&inputs(3, 11), compute).unwrap();
+        let second = cache.get_or_compute(&inputs(3, 11), compute).unwrap();
+        assert_eq!(calls.get(), 1);
+        assert_eq!(first, second);
+
+        cache.get_or_compute(&inputs(3, 12), compute).unwrap();
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn load_misses_until_stored() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = WitnessCache::new(dir.path()).unwrap();
+        assert_eq!(cache.load::<Fr>(&inputs(3, 11)).unwrap(), None);
+
+        let witness = [1u64, 33, 3, 11].map(Fr::from).to_vec();
+        cache.store(&inputs(3, 11), &witness).unwrap();
+        assert_eq!(cache.load::<Fr>(&inputs(3, 11)).unwrap(), Some(witness));
+        // another field never sees the entry
+        assert_eq!(
+            cache.load::<ark_bls12_381::Fr>(&inputs(3, 11)).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn keys_depend_on_values_not_on_insertion_order() {
+        let mut reversed = HashMap::new();
+        reversed.insert("b".to_string(), vec![BigInt::from(11)]);
+        reversed.insert("a".to_string(), vec![BigInt::from(3)]);
+        assert_eq!(
+            WitnessCache::key::<Fr>(&inputs(3, 11)),
+            WitnessCache::key::<Fr>(&reversed)
+        );
+        assert_ne!(
+            WitnessCache::key::<Fr>(&inputs(3, 11)),
+            WitnessCache::key::<Fr>(&inputs(11, 3))
+        );
+        assert_eq!(WitnessCache::key::<Fr>(&inputs(3, 11)).len(), 64);
+    }
+}
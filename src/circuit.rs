@@ -1,13 +1,39 @@
 use ark_relations::r1cs::{
-    ConstraintSynthesizer, ConstraintSystemRef, LinearCombination, SynthesisError, Variable,
+    ConstraintMatrices, ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef,
+    LinearCombination, SynthesisError, Variable,
 };
 
 use ark_ff::PrimeField;
 
+use ark_std::io::{Read, Seek};
+use std::convert::TryFrom;
+use std::time::{Duration, Instant};
+#[cfg(feature = "tar")]
+use std::{
+    fs::File,
+    io::{BufReader, Cursor},
+    path::Path,
+};
+
 use super::R1CS;
+use crate::error::{R1CSError, Result};
+use crate::eval::checked_evaluate_constraint;
+use crate::format::format_constraint;
+use crate::witness::{same_prime, WitnessFile};
+use crate::{R1CSFile, SymFile};
 
-use color_eyre::Result;
+/// Time spent in each phase of synthesis, see [`CircomCircuit::synthesize_timed`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SynthesisTiming {
+    /// Allocating the instance and witness variables
+    pub allocation: Duration,
+    /// Enforcing the constraints
+    pub enforcement: Duration,
+}
 
+/// A circom circuit ready for synthesis: its constraints and, for proving, the value of
+/// every wire in circom's order. Without a witness, synthesis allocates placeholder
+/// values, which is all key generation needs.
 #[derive(Clone, Debug)]
 pub struct CircomCircuit<F: PrimeField> {
     pub r1cs: R1CS<F>,
@@ -15,59 +41,1052 @@ pub struct CircomCircuit<F: PrimeField> {
 }
 
 impl<F: PrimeField> CircomCircuit<F> {
+    pub fn new(r1cs: R1CS<F>, witness: Option<Vec<F>>) -> Self {
+        CircomCircuit { r1cs, witness }
+    }
+
+    /// Creates the verifier side of a circuit, which only knows the public inputs. See
+    /// [`VerifierCircuit::new`].
+    pub fn verifier_only(r1cs: R1CS<F>, public_inputs: Vec<F>) -> Result<VerifierCircuit<F>> {
+        VerifierCircuit::new(r1cs, public_inputs)
+    }
+
+    /// Wraps the circuit to allocate the constant one wire explicitly, see
+    /// [`ExplicitOne`]
+    pub fn with_explicit_one(self) -> ExplicitOne<F> {
+        ExplicitOne(self)
+    }
+
     pub fn get_public_inputs(&self) -> Option<Vec<F>> {
-        match &self.witness {
-            None => None,
-            Some(w) => Some(w[1..self.r1cs.num_inputs].to_vec()),
+        Some(self.instance_assignment()?.to_vec())
+    }
+
+    /// Public part of the witness, wires `1..num_inputs`, without the constant one.
+    /// `None` without a witness, or if the witness is too short.
+    pub fn instance_assignment(&self) -> Option<&[F]> {
+        self.witness.as_ref()?.get(1..self.r1cs.num_inputs)
+    }
+
+    /// Private part of the witness, every wire from `num_inputs` on
+    pub fn witness_assignment(&self) -> Option<&[F]> {
+        self.witness.as_ref()?.get(self.r1cs.num_inputs..)
+    }
+
+    /// Number of instance variables, including arkworks' `One`, witness variables and
+    /// constraints that synthesizing the circuit produces, without synthesizing it
+    pub fn dry_run_counts(&self) -> (usize, usize, usize) {
+        (
+            self.r1cs.num_inputs,
+            self.r1cs.num_aux,
+            self.r1cs.constraints.len(),
+        )
+    }
+
+    /// Fails unless the circuit has a witness with one value per wire
+    fn check_witness_len(&self) -> Result<()> {
+        let witness = self.witness.as_ref().ok_or(R1CSError::MissingWitness)?;
+        if witness.len() != self.r1cs.expected_witness_len() {
+            return Err(R1CSError::WitnessLengthMismatch {
+                expected: self.r1cs.expected_witness_len(),
+                actual: witness.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Synthesizes the circuit into a fresh constraint system and checks it with
+    /// arkworks' own `is_satisfied`
+    pub fn synthesize_and_check(&self) -> Result<bool> {
+        self.check_witness_len()?;
+        let cs = ConstraintSystem::<F>::new_ref();
+        self.clone().generate_constraints(cs.clone())?;
+        Ok(cs.is_satisfied()?)
+    }
+
+    /// Synthesizes the circuit and returns its constraint matrices together with the
+    /// instance assignment, starting with the constant one, and the witness assignment
+    pub fn into_prover_inputs(self) -> Result<(ConstraintMatrices<F>, Vec<F>, Vec<F>)> {
+        self.check_witness_len()?;
+        let cs = ConstraintSystem::<F>::new_ref();
+        self.generate_constraints(cs.clone())?;
+        cs.finalize();
+
+        let matrices = cs.to_matrices().ok_or(SynthesisError::AssignmentMissing)?;
+        let cs = cs.borrow().ok_or(SynthesisError::MissingCS)?;
+        Ok((
+            matrices,
+            cs.instance_assignment.clone(),
+            cs.witness_assignment.clone(),
+        ))
+    }
+
+    /// Synthesizes the circuit into `cs` and returns the value assigned to every
+    /// variable, instance variables first, starting with `Variable::One`, then witness
+    /// variables. The values are the circom witness itself.
+    pub fn synthesize_with_assignments(
+        self,
+        cs: ConstraintSystemRef<F>,
+    ) -> Result<Vec<(Variable, F)>> {
+        self.synthesize_with_assignments_as(false, cs)
+    }
+
+    fn synthesize_with_assignments_as(
+        self,
+        explicit_one: bool,
+        cs: ConstraintSystemRef<F>,
+    ) -> Result<Vec<(Variable, F)>> {
+        self.check_witness_len()?;
+        self.generate_constraints_as(explicit_one, cs.clone())?;
+
+        let cs = cs.borrow().ok_or(SynthesisError::MissingCS)?;
+        let instance = cs.instance_assignment.iter().enumerate().map(|(i, value)| {
+            let variable = if i == 0 {
+                Variable::One
+            } else {
+                Variable::Instance(i)
+            };
+            (variable, *value)
+        });
+        let witness = cs
+            .witness_assignment
+            .iter()
+            .enumerate()
+            .map(|(i, value)| (Variable::Witness(i), *value));
+        Ok(instance.chain(witness).collect())
+    }
+
+    /// Like `generate_constraints`, but leaves the circuit in place and reports how long
+    /// each phase took
+    pub fn synthesize_timed(&self, cs: ConstraintSystemRef<F>) -> Result<SynthesisTiming> {
+        let start = Instant::now();
+        self.allocate_variables(false, &cs)?;
+        let allocation = start.elapsed();
+
+        let start = Instant::now();
+        enforce_constraints(&self.r1cs, 0, &cs)?;
+        let enforcement = start.elapsed();
+
+        Ok(SynthesisTiming {
+            allocation,
+            enforcement,
+        })
+    }
+
+    /// Describes the first constraint the witness does not satisfy, using signal names
+    /// from `sym` and the computed values of each side. A constraint that references a
+    /// wire past the end of the witness is reported with that wire instead of values.
+    pub fn explain_failure(&self, sym: &SymFile) -> Option<String> {
+        let witness = self.witness.as_ref()?;
+        let index = self.r1cs.check_witness(witness).err()?;
+        let constraint = &self.r1cs.constraints[index];
+        let description = format!(
+            "constraint {} is not satisfied: {}",
+            index,
+            format_constraint(constraint, Some(sym))
+        );
+        Some(match checked_evaluate_constraint(constraint, witness) {
+            Ok((a, b, c)) => format!(
+                "{}\n  A = {}, B = {}, A * B = {}, C = {}",
+                description,
+                a,
+                b,
+                a * b,
+                c
+            ),
+            Err(err) => format!("{}\n  {}", description, err),
+        })
+    }
+}
+
+/// Reads an `.r1cs` and a `.wtns` file for the same circuit.
+///
+/// Fails if the two files were produced for different primes or if the witness
+/// length does not match the number of wires.
+pub fn load_circuit<F: PrimeField, R: Read + Seek, W: Read + Seek>(
+    r1cs_reader: R,
+    witness_reader: W,
+) -> Result<CircomCircuit<F>> {
+    let file = R1CSFile::<F>::new(r1cs_reader)?;
+    let witness_file = WitnessFile::<F>::new(witness_reader)?;
+
+    if !same_prime(&file.header.prime_size, &witness_file.header.prime) {
+        return Err(R1CSError::WitnessPrimeMismatch);
+    }
+
+    let witness = Vec::try_from(witness_file)?;
+    let r1cs = R1CS::from(file);
+    if witness.len() != r1cs.expected_witness_len() {
+        return Err(R1CSError::WitnessLengthMismatch {
+            expected: r1cs.expected_witness_len(),
+            actual: witness.len(),
+        });
+    }
+
+    Ok(CircomCircuit::new(r1cs, Some(witness)))
+}
+
+/// Reads a tar archive holding the `.r1cs` and `.wtns` files of a circuit, each found
+/// by its extension, and checks them against each other as [`load_circuit`] does.
+/// Other members are ignored, but the archive must contain exactly one of each, else
+/// this fails with [`R1CSError::ArchiveMember`].
+#[cfg(feature = "tar")]
+pub fn load_from_tar<F: PrimeField, P: AsRef<Path>>(path: P) -> Result<CircomCircuit<F>> {
+    let mut r1cs = None;
+    let mut witness = None;
+    let mut archive = tar::Archive::new(BufReader::new(File::open(path)?));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let (ext, slot) = match entry.path()?.extension().and_then(|ext| ext.to_str()) {
+            Some("r1cs") => ("r1cs", &mut r1cs),
+            Some("wtns") => ("wtns", &mut witness),
+            _ => continue,
+        };
+        // Stop at the second member rather than counting them all
+        if slot.is_some() {
+            return Err(R1CSError::ArchiveMember { ext, count: 2 });
         }
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data)?;
+        *slot = Some(data);
+    }
+
+    let missing = |ext| R1CSError::ArchiveMember { ext, count: 0 };
+    let r1cs = r1cs.ok_or_else(|| missing("r1cs"))?;
+    let witness = witness.ok_or_else(|| missing("wtns"))?;
+    load_circuit(Cursor::new(r1cs), Cursor::new(witness))
+}
+
+/// Allocates the instance and witness variables of `r1cs` with the values in `inputs`,
+/// excluding the constant one, and `aux`, or with placeholders for the side that is
+/// `None`. The constant wire is allocated as an input first if `explicit_one` is set.
+fn allocate_variables<F: PrimeField>(
+    r1cs: &R1CS<F>,
+    inputs: Option<&[F]>,
+    aux: Option<&[F]>,
+    explicit_one: bool,
+    cs: &ConstraintSystemRef<F>,
+) -> Result<(), SynthesisError> {
+    // Start from 1 because Arkworks implicitly allocates One for the first input,
+    // unless the constant is allocated explicitly
+    if explicit_one {
+        cs.new_input_variable(|| Ok(F::one()))?;
+    }
+    match inputs {
+        Some(values) => {
+            for value in values {
+                cs.new_input_variable(|| Ok(*value))?;
+            }
+        }
+        None => {
+            for _ in 1..r1cs.num_inputs {
+                cs.new_input_variable(|| Ok(F::one()))?;
+            }
+        }
+    }
+
+    match aux {
+        Some(values) => {
+            for value in values {
+                cs.new_witness_variable(|| Ok(*value))?;
+            }
+        }
+        None => {
+            for _ in 0..r1cs.num_aux {
+                cs.new_witness_variable(|| Ok(F::one()))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl<F: PrimeField> CircomCircuit<F> {
+    fn allocate_variables(
+        &self,
+        explicit_one: bool,
+        cs: &ConstraintSystemRef<F>,
+    ) -> Result<(), SynthesisError> {
+        let num_inputs = self.r1cs.num_inputs;
+        let num_aux = self.r1cs.num_aux;
+        // Resolve where the values come from once, rather than per variable, which
+        // benches/synthesis.rs measures against the per-variable lookup. A witness too
+        // short for the circuit is reported instead of indexed past.
+        let (inputs, aux) = match &self.witness {
+            Some(w) => match (
+                w.get(1..num_inputs),
+                w.get(num_inputs..num_inputs + num_aux),
+            ) {
+                (Some(inputs), Some(aux)) => (Some(inputs), Some(aux)),
+                _ => return Err(SynthesisError::AssignmentMissing),
+            },
+            None => (None, None),
+        };
+        allocate_variables(&self.r1cs, inputs, aux, explicit_one, cs)
+    }
+
+    fn generate_constraints_as(
+        self,
+        explicit_one: bool,
+        cs: ConstraintSystemRef<F>,
+    ) -> Result<(), SynthesisError> {
+        self.allocate_variables(explicit_one, &cs)?;
+        enforce_constraints(&self.r1cs, explicit_one as usize, &cs)
     }
 }
 
 impl<F: PrimeField> ConstraintSynthesizer<F> for CircomCircuit<F> {
     fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
-        let witness = &self.witness;
-
-        // Start from 1 because Arkworks implicitly allocates One for the first input
-        for i in 1..self.r1cs.num_inputs {
-            cs.new_input_variable(|| {
-                Ok(match witness {
-                    None => F::from(1u32),
-                    Some(w) => w[i],
+        self.generate_constraints_as(false, cs)
+    }
+}
+
+/// A circom circuit that allocates its constant one wire as an ordinary input variable
+/// instead of mapping it onto arkworks' implicit `One`. Every instance wire moves up by
+/// one, see [`wire_to_variable`], and the public inputs start with the constant.
+#[derive(Clone, Debug)]
+pub struct ExplicitOne<F: PrimeField>(pub CircomCircuit<F>);
+
+impl<F: PrimeField> ExplicitOne<F> {
+    pub fn get_public_inputs(&self) -> Option<Vec<F>> {
+        let inputs = self.0.instance_assignment()?;
+        Some(
+            std::iter::once(F::one())
+                .chain(inputs.iter().copied())
+                .collect(),
+        )
+    }
+
+    /// Like [`CircomCircuit::dry_run_counts`], with the extra instance variable
+    pub fn dry_run_counts(&self) -> (usize, usize, usize) {
+        let (instance, witness, constraints) = self.0.dry_run_counts();
+        (instance + 1, witness, constraints)
+    }
+
+    /// Like [`CircomCircuit::synthesize_with_assignments`]. The constant is bound twice,
+    /// to `Variable::One` and to the first instance variable.
+    pub fn synthesize_with_assignments(
+        self,
+        cs: ConstraintSystemRef<F>,
+    ) -> Result<Vec<(Variable, F)>> {
+        self.0.synthesize_with_assignments_as(true, cs)
+    }
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for ExplicitOne<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        self.0.generate_constraints_as(true, cs)
+    }
+}
+
+/// The verifier's view of a circom circuit: the constraints and the public inputs,
+/// without the private part of the witness. Synthesis allocates the public inputs and
+/// placeholders for the private wires.
+#[derive(Clone, Debug)]
+pub struct VerifierCircuit<F: PrimeField> {
+    pub r1cs: R1CS<F>,
+    /// One value per public signal, `num_inputs - 1` of them without the constant one
+    pub public_inputs: Vec<F>,
+}
+
+impl<F: PrimeField> VerifierCircuit<F> {
+    /// Fails unless there is one input per public signal
+    pub fn new(r1cs: R1CS<F>, public_inputs: Vec<F>) -> Result<Self> {
+        let expected = match r1cs.num_inputs.checked_sub(1) {
+            Some(expected) => expected,
+            None => {
+                return Err(R1CSError::InconsistentCounts {
+                    num_inputs: r1cs.num_inputs,
+                    num_aux: r1cs.num_aux,
+                    num_variables: r1cs.num_variables,
                 })
-            })?;
+            }
+        };
+        if public_inputs.len() != expected {
+            return Err(R1CSError::PublicInputCountMismatch {
+                expected,
+                actual: public_inputs.len(),
+            });
         }
+        Ok(VerifierCircuit {
+            r1cs,
+            public_inputs,
+        })
+    }
 
-        for i in 0..self.r1cs.num_aux {
-            cs.new_witness_variable(|| {
-                Ok(match witness {
-                    None => F::from(1u32),
-                    Some(w) => w[i + self.r1cs.num_inputs],
-                })
-            })?;
+    pub fn get_public_inputs(&self) -> &[F] {
+        &self.public_inputs
+    }
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for VerifierCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let inputs = self
+            .public_inputs
+            .get(..self.r1cs.num_inputs.saturating_sub(1))
+            .ok_or(SynthesisError::AssignmentMissing)?;
+        allocate_variables(&self.r1cs, Some(inputs), None, false, &cs)?;
+        enforce_constraints(&self.r1cs, 0, &cs)
+    }
+}
+
+/// Variable that circom wire `wire` is allocated as, for a system with `num_inputs`
+/// instance wires whose instance variables are moved up by `shift`. Without a shift the
+/// constant wire 0 maps to `Instance(0)`, which arkworks treats as `One`. An
+/// [`ExplicitOne`] circuit allocates with a shift of 1.
+pub fn wire_to_variable(wire: usize, num_inputs: usize, shift: usize) -> Variable {
+    if wire < num_inputs {
+        Variable::Instance(wire + shift)
+    } else {
+        Variable::Witness(wire - num_inputs)
+    }
+}
+
+/// Circom wire of a variable allocated by [`wire_to_variable`] with the same `shift`, or
+/// `None` for variables that do not stand for a wire, such as symbolic linear
+/// combinations or arkworks' own `One` when the constant wire is shifted away from it
+pub fn variable_to_wire(variable: Variable, num_inputs: usize, shift: usize) -> Option<usize> {
+    match variable {
+        Variable::One if shift == 0 => Some(0),
+        Variable::Instance(i) => i.checked_sub(shift),
+        Variable::Witness(j) => Some(num_inputs + j),
+        _ => None,
+    }
+}
+
+/// Enforces the constraints of `r1cs` over variables already allocated in circom's
+/// wire order, with instance wires moved up by `shift`
+fn enforce_constraints<F: PrimeField>(
+    r1cs: &R1CS<F>,
+    shift: usize,
+    cs: &ConstraintSystemRef<F>,
+) -> Result<(), SynthesisError> {
+    let make_index = |index| wire_to_variable(index, r1cs.num_inputs, shift);
+    let make_lc = |lc_data: &[(usize, F)]| {
+        lc_data.iter().fold(
+            LinearCombination::<F>::zero(),
+            |lc: LinearCombination<F>, (index, coeff)| lc + (*coeff, make_index(*index)),
+        )
+    };
+
+    for constraint in &r1cs.constraints {
+        cs.enforce_constraint(
+            make_lc(&constraint.0),
+            make_lc(&constraint.1),
+            make_lc(&constraint.2),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Circuit for key generation, which needs the constraints but no values. Every variable
+/// is allocated as zero, so unlike a [`CircomCircuit`] without a witness there is no
+/// source of values to pick per variable.
+#[derive(Clone, Debug)]
+pub struct SetupCircuit<F: PrimeField>(pub R1CS<F>);
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for SetupCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        for _ in 1..self.0.num_inputs {
+            cs.new_input_variable(|| Ok(F::zero()))?;
+        }
+        for _ in 0..self.0.num_aux {
+            cs.new_witness_variable(|| Ok(F::zero()))?;
         }
+        enforce_constraints(&self.0, 0, &cs)
+    }
+}
 
-        let make_index = |index| {
-            if index < self.r1cs.num_inputs {
-                Variable::Instance(index)
-            } else {
-                Variable::Witness(index - self.r1cs.num_inputs)
-            }
-        };
-        let make_lc = |lc_data: &[(usize, F)]| {
-            lc_data.iter().fold(
-                LinearCombination::<F>::zero(),
-                |lc: LinearCombination<F>, (index, coeff)| lc + (*coeff, make_index(*index)),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+
+    /// `in * secret = out`, over wires `[1, out, in, secret]`
+    fn product() -> R1CS<Fr> {
+        R1CS::for_test(
+            1,
+            1,
+            1,
+            vec![(
+                vec![(2, Fr::from(1u64))],
+                vec![(3, Fr::from(1u64))],
+                vec![(1, Fr::from(1u64))],
+            )],
+        )
+    }
+
+    fn values(values: &[u64]) -> Vec<Fr> {
+        values.iter().map(|v| Fr::from(*v)).collect()
+    }
+
+    #[test]
+    fn verifier_only_yields_its_public_inputs() {
+        let circuit = CircomCircuit::verifier_only(product(), values(&[42, 6])).unwrap();
+        assert_eq!(circuit.get_public_inputs(), &values(&[42, 6])[..]);
+    }
+
+    #[test]
+    fn verifier_inputs_cannot_go_stale() {
+        let mut circuit = CircomCircuit::new(product(), None);
+        assert_eq!(circuit.get_public_inputs(), None);
+        // public inputs always follow the witness once it is set
+        circuit.witness = Some(values(&[1, 42, 6, 7]));
+        assert_eq!(circuit.get_public_inputs(), Some(values(&[42, 6])));
+    }
+
+    #[test]
+    fn a_shortened_verifier_input_list_is_missing_assignments() {
+        let mut circuit = VerifierCircuit::new(product(), values(&[42, 6])).unwrap();
+        circuit.public_inputs.pop();
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        assert!(matches!(
+            circuit.generate_constraints(cs),
+            Err(SynthesisError::AssignmentMissing)
+        ));
+    }
+
+    #[test]
+    fn verifier_only_allocates_the_public_inputs() {
+        let circuit = CircomCircuit::verifier_only(product(), values(&[42, 6])).unwrap();
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        assert_eq!(
+            cs.borrow().unwrap().instance_assignment,
+            values(&[1, 42, 6])
+        );
+        assert_eq!(cs.num_witness_variables(), 1);
+        assert_eq!(cs.num_constraints(), 1);
+    }
+
+    #[test]
+    fn verifier_only_checks_the_input_count() {
+        assert!(matches!(
+            CircomCircuit::verifier_only(product(), values(&[42])),
+            Err(R1CSError::PublicInputCountMismatch {
+                expected: 2,
+                actual: 1
+            })
+        ));
+
+        let mut r1cs = product();
+        r1cs.num_inputs = 0;
+        assert!(matches!(
+            CircomCircuit::verifier_only(r1cs, Vec::new()),
+            Err(R1CSError::InconsistentCounts { num_inputs: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn public_inputs_come_from_the_witness_otherwise() {
+        let circuit = CircomCircuit::new(product(), Some(values(&[1, 42, 6, 7])));
+        assert_eq!(circuit.get_public_inputs(), Some(values(&[42, 6])));
+        assert_eq!(
+            CircomCircuit::new(product(), None).get_public_inputs(),
+            None
+        );
+        assert_eq!(
+            CircomCircuit::new(product(), Some(values(&[1]))).get_public_inputs(),
+            None
+        );
+    }
+
+    fn product_names() -> SymFile {
+        SymFile::new("1,1,0,main.out\n2,2,0,main.in\n3,3,0,main.secret\n".as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn explains_a_wrong_witness_value() {
+        let circuit = CircomCircuit::new(product(), Some(values(&[1, 41, 6, 7])));
+        assert_eq!(
+            circuit.explain_failure(&product_names()).unwrap(),
+            "constraint 0 is not satisfied: (main.in) * (main.secret) = (main.out)\n  \
+             A = 6, B = 7, A * B = 42, C = 41"
+        );
+    }
+
+    #[test]
+    fn nothing_to_explain_for_a_valid_or_missing_witness() {
+        let valid = CircomCircuit::new(product(), Some(values(&[1, 42, 6, 7])));
+        assert_eq!(valid.explain_failure(&product_names()), None);
+        let missing = CircomCircuit::new(product(), None);
+        assert_eq!(missing.explain_failure(&product_names()), None);
+    }
+
+    #[test]
+    fn explains_a_short_witness_without_panicking() {
+        let circuit = CircomCircuit::new(product(), Some(values(&[1, 42, 6])));
+        let explanation = circuit.explain_failure(&product_names()).unwrap();
+        assert!(explanation.starts_with("constraint 0 is not satisfied"));
+        assert!(explanation.ends_with("witness index 3 out of range for 3 values"));
+    }
+
+    fn mycircuit() -> std::fs::File {
+        std::fs::File::open("./test-vectors/mycircuit.r1cs").unwrap()
+    }
+
+    fn wtns<G: PrimeField>(values: &[u64]) -> ark_std::io::Cursor<Vec<u8>> {
+        let values: Vec<G> = values.iter().map(|v| G::from(*v)).collect();
+        ark_std::io::Cursor::new(crate::write_witness(Vec::new(), &values).unwrap())
+    }
+
+    #[test]
+    fn loads_a_matching_circuit_and_witness() {
+        let circuit = load_circuit::<Fr, _, _>(mycircuit(), wtns::<Fr>(&[1, 33, 3, 11])).unwrap();
+        assert_eq!(circuit.witness, Some(values(&[1, 33, 3, 11])));
+        assert_eq!(circuit.r1cs.check_witness(&values(&[1, 33, 3, 11])), Ok(()));
+    }
+
+    #[test]
+    fn rejects_a_witness_for_another_prime() {
+        let witness = wtns::<ark_bls12_381::Fr>(&[1, 33, 3, 11]);
+        assert!(matches!(
+            load_circuit::<Fr, _, _>(mycircuit(), witness),
+            Err(R1CSError::WitnessPrimeMismatch)
+        ));
+    }
+
+    #[test]
+    fn rejects_a_witness_of_the_wrong_length() {
+        assert!(matches!(
+            load_circuit::<Fr, _, _>(mycircuit(), wtns::<Fr>(&[1, 33, 3])),
+            Err(R1CSError::WitnessLengthMismatch {
+                expected: 4,
+                actual: 3
+            })
+        ));
+    }
+
+    /// Instance and witness assignments after synthesizing `circuit`
+    fn assignments<C: ConstraintSynthesizer<Fr>>(circuit: C) -> (Vec<Fr>, Vec<Fr>) {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        let cs = cs.borrow().unwrap();
+        (
+            cs.instance_assignment.clone(),
+            cs.witness_assignment.clone(),
+        )
+    }
+
+    #[test]
+    fn allocates_variables_in_wire_order() {
+        let circuit = CircomCircuit::new(product(), Some(values(&[1, 42, 6, 7])));
+        assert_eq!(
+            assignments(circuit.clone()),
+            (values(&[1, 42, 6]), values(&[7]))
+        );
+        assert_eq!(
+            assignments(circuit.with_explicit_one()),
+            (values(&[1, 1, 42, 6]), values(&[7]))
+        );
+        assert_eq!(
+            assignments(CircomCircuit::new(product(), None)),
+            (values(&[1, 1, 1]), values(&[1]))
+        );
+    }
+
+    #[test]
+    fn allocation_rejects_a_short_witness() {
+        let circuit = CircomCircuit::new(product(), Some(values(&[1, 42, 6])));
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        assert!(matches!(
+            circuit.generate_constraints(cs),
+            Err(SynthesisError::AssignmentMissing)
+        ));
+    }
+
+    #[test]
+    fn synthesize_and_check_agrees_with_check_witness() {
+        for witness in [
+            values(&[1, 42, 6, 7]),
+            values(&[1, 41, 6, 7]),
+            values(&[1, 0, 0, 9]),
+            values(&[1, 42, 7, 6]),
+        ] {
+            let expected = product().check_witness(&witness).is_ok();
+            let circuit = CircomCircuit::new(product(), Some(witness));
+            assert_eq!(circuit.synthesize_and_check().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn synthesize_and_check_needs_a_full_witness() {
+        assert!(matches!(
+            CircomCircuit::new(product(), None).synthesize_and_check(),
+            Err(R1CSError::MissingWitness)
+        ));
+        assert!(matches!(
+            CircomCircuit::new(product(), Some(values(&[1, 42, 6, 7, 0]))).synthesize_and_check(),
+            Err(R1CSError::WitnessLengthMismatch {
+                expected: 4,
+                actual: 5
+            })
+        ));
+    }
+
+    #[test]
+    fn instance_count_matches_arkworks() {
+        let circuit = CircomCircuit::new(product(), Some(values(&[1, 42, 6, 7])));
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.clone().generate_constraints(cs.clone()).unwrap();
+        assert_eq!(
+            cs.num_instance_variables(),
+            circuit.r1cs.num_instance_variables()
+        );
+        assert_eq!(
+            circuit.get_public_inputs().unwrap().len(),
+            circuit.r1cs.num_public_signals()
+        );
+    }
+
+    /// `in * secret = out - 1`, so the constant wire appears in a constraint
+    fn product_minus_one() -> R1CS<Fr> {
+        let mut r1cs = product();
+        r1cs.constraints[0].2.push((0, -Fr::from(1u64)));
+        r1cs
+    }
+
+    fn is_satisfied<C: ConstraintSynthesizer<Fr>>(circuit: C) -> bool {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    #[test]
+    fn satisfiable_with_and_without_an_explicit_one() {
+        let valid = CircomCircuit::new(product_minus_one(), Some(values(&[1, 43, 6, 7])));
+        assert!(is_satisfied(valid.clone()));
+        assert!(is_satisfied(valid.with_explicit_one()));
+
+        let invalid = CircomCircuit::new(product_minus_one(), Some(values(&[1, 42, 6, 7])));
+        assert!(!is_satisfied(invalid.clone()));
+        assert!(!is_satisfied(invalid.with_explicit_one()));
+    }
+
+    #[test]
+    fn explicit_one_adds_an_instance_variable() {
+        let circuit = CircomCircuit::new(product(), Some(values(&[1, 42, 6, 7])));
+        assert_eq!(circuit.dry_run_counts(), (3, 1, 1));
+        let explicit = circuit.with_explicit_one();
+        assert_eq!(explicit.dry_run_counts(), (4, 1, 1));
+        assert_eq!(explicit.get_public_inputs(), Some(values(&[1, 42, 6])));
+        assert_eq!(wire_to_variable(0, 3, 1), Variable::Instance(1));
+        assert_eq!(wire_to_variable(3, 3, 1), Variable::Witness(0));
+    }
+
+    #[test]
+    fn prover_inputs_reconstruct_the_system() {
+        let witness = values(&[1, 42, 6, 7]);
+        let circuit = CircomCircuit::new(product(), Some(witness.clone()));
+        let (matrices, instance, aux) = circuit.into_prover_inputs().unwrap();
+        assert_eq!(instance, values(&[1, 42, 6]));
+        assert_eq!(aux, values(&[7]));
+
+        let one = Fr::from(1u64);
+        assert_eq!(matrices.num_instance_variables, 3);
+        assert_eq!(matrices.num_witness_variables, 1);
+        assert_eq!(matrices.a, vec![vec![(one, 2)]]);
+        assert_eq!(matrices.b, vec![vec![(one, 3)]]);
+        assert_eq!(matrices.c, vec![vec![(one, 1)]]);
+
+        // columns index the instance assignment followed by the witness assignment
+        let z: Vec<Fr> = instance.into_iter().chain(aux).collect();
+        assert_eq!(z, witness);
+        let row = |m: &Vec<(Fr, usize)>| m.iter().map(|(coeff, i)| *coeff * z[*i]).sum::<Fr>();
+        assert_eq!(
+            row(&matrices.a[0]) * row(&matrices.b[0]),
+            row(&matrices.c[0])
+        );
+    }
+
+    #[test]
+    fn prover_inputs_need_a_witness() {
+        assert!(matches!(
+            CircomCircuit::new(product(), None).into_prover_inputs(),
+            Err(R1CSError::MissingWitness)
+        ));
+    }
+
+    #[test]
+    fn bindings_match_the_witness() {
+        let witness = values(&[1, 42, 6, 7]);
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let bindings = CircomCircuit::new(product(), Some(witness.clone()))
+            .synthesize_with_assignments(cs.clone())
+            .unwrap();
+        assert_eq!(
+            bindings.iter().map(|(_, value)| *value).collect::<Vec<_>>(),
+            witness
+        );
+        assert_eq!(
+            bindings
+                .iter()
+                .map(|(variable, _)| *variable)
+                .collect::<Vec<_>>(),
+            vec![
+                Variable::One,
+                Variable::Instance(1),
+                Variable::Instance(2),
+                Variable::Witness(0)
+            ]
+        );
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn bindings_with_an_explicit_one_bind_the_constant_twice() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let bindings = CircomCircuit::new(product(), Some(values(&[1, 42, 6, 7])))
+            .with_explicit_one()
+            .synthesize_with_assignments(cs)
+            .unwrap();
+        assert_eq!(bindings.len(), 5);
+        assert_eq!(bindings[1], (Variable::Instance(1), Fr::from(1u64)));
+        assert_eq!(bindings[2], (Variable::Instance(2), Fr::from(42u64)));
+    }
+
+    #[test]
+    fn bindings_need_a_full_witness() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        assert!(matches!(
+            CircomCircuit::new(product(), Some(values(&[1, 42, 6])))
+                .synthesize_with_assignments(cs),
+            Err(R1CSError::WitnessLengthMismatch {
+                expected: 4,
+                actual: 3
+            })
+        ));
+    }
+
+    /// Writes a tar archive of `members`, each a name and the fixture it holds
+    #[cfg(feature = "tar")]
+    fn archive(dir: &Path, members: &[(&str, &str)]) -> std::path::PathBuf {
+        let path = dir.join("circuit.tar");
+        let mut builder = tar::Builder::new(File::create(&path).unwrap());
+        for (name, fixture) in members {
+            builder
+                .append_path_with_name(format!("./test-vectors/{}", fixture), name)
+                .unwrap();
+        }
+        builder.finish().unwrap();
+        path
+    }
+
+    #[cfg(feature = "tar")]
+    #[test]
+    fn loads_a_circuit_and_witness_from_a_tar() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = archive(
+            dir.path(),
+            &[
+                ("mycircuit.circom", "mycircuit.circom"),
+                ("circuit.r1cs", "mycircuit.r1cs"),
+                ("witness.wtns", "mycircuit.wtns"),
+            ],
+        );
+        let circuit = load_from_tar::<Fr, _>(path).unwrap();
+        assert_eq!(circuit.witness, Some(values(&[1, 33, 3, 11])));
+        assert_eq!(circuit.get_public_inputs(), Some(values(&[33])));
+    }
+
+    #[cfg(feature = "tar")]
+    #[test]
+    fn a_tar_needs_exactly_one_of_each_member() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = archive(dir.path(), &[("circuit.r1cs", "mycircuit.r1cs")]);
+        assert!(matches!(
+            load_from_tar::<Fr, _>(missing),
+            Err(R1CSError::ArchiveMember {
+                ext: "wtns",
+                count: 0
+            })
+        ));
+
+        let twice = archive(
+            dir.path(),
+            &[
+                ("a.r1cs", "mycircuit.r1cs"),
+                ("b.r1cs", "mycircuit.r1cs"),
+                ("witness.wtns", "mycircuit.wtns"),
+            ],
+        );
+        assert!(matches!(
+            load_from_tar::<Fr, _>(twice),
+            Err(R1CSError::ArchiveMember {
+                ext: "r1cs",
+                count: 2
+            })
+        ));
+    }
+
+    #[cfg(feature = "tar")]
+    #[test]
+    fn a_tar_is_cross_validated() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = archive(
+            dir.path(),
+            &[
+                ("circuit.r1cs", "passthrough.r1cs"),
+                ("witness.wtns", "mycircuit.wtns"),
+            ],
+        );
+        assert!(matches!(
+            load_from_tar::<Fr, _>(path),
+            Err(R1CSError::WitnessLengthMismatch {
+                expected: 3,
+                actual: 4
+            })
+        ));
+    }
+
+    #[test]
+    fn assignment_views_match_manual_indexing() {
+        let witness = values(&[1, 42, 6, 7]);
+        let circuit = CircomCircuit::new(product(), Some(witness.clone()));
+        assert_eq!(circuit.instance_assignment(), Some(&witness[1..3]));
+        assert_eq!(circuit.witness_assignment(), Some(&witness[3..]));
+        // the views borrow the witness rather than copy it
+        assert!(std::ptr::eq(
+            circuit.witness_assignment().unwrap(),
+            &circuit.witness.as_ref().unwrap()[3..]
+        ));
+    }
+
+    #[test]
+    fn assignment_views_need_enough_witness() {
+        assert_eq!(
+            CircomCircuit::new(product(), None).instance_assignment(),
+            None
+        );
+        assert_eq!(
+            CircomCircuit::new(product(), None).witness_assignment(),
+            None
+        );
+
+        let short = CircomCircuit::new(product(), Some(values(&[1, 42])));
+        assert_eq!(short.instance_assignment(), None);
+        assert_eq!(short.witness_assignment(), None);
+
+        let public_only = CircomCircuit::new(product(), Some(values(&[1, 42, 6])));
+        assert_eq!(
+            public_only.instance_assignment(),
+            Some(&values(&[42, 6])[..])
+        );
+        assert_eq!(public_only.witness_assignment(), Some(&[][..]));
+    }
+
+    #[test]
+    fn timed_synthesis_matches_generate_constraints() {
+        let circuit = CircomCircuit::new(product(), Some(values(&[1, 42, 6, 7])));
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.synthesize_timed(cs.clone()).unwrap();
+        assert_eq!(cs.num_instance_variables(), 3);
+        assert_eq!(cs.num_witness_variables(), 1);
+        assert_eq!(cs.num_constraints(), 1);
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    /// `w[i + 1] = w[i] * w[i]` for `n` squarings of the input on wire 1
+    fn squarings(n: usize) -> (R1CS<Fr>, Vec<Fr>) {
+        let one = Fr::from(1u64);
+        let r1cs = R1CS::for_test(
+            0,
+            1,
+            n,
+            (1..=n)
+                .map(|i| (vec![(i, one)], vec![(i, one)], vec![(i + 1, one)]))
+                .collect(),
+        );
+        let mut witness = vec![one, Fr::from(3u64)];
+        for i in 1..=n {
+            witness.push(witness[i] * witness[i]);
+        }
+        (r1cs, witness)
+    }
+
+    #[test]
+    fn times_both_phases_of_a_large_circuit() {
+        let (r1cs, witness) = squarings(10_000);
+        let circuit = CircomCircuit::new(r1cs, Some(witness));
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let timing = circuit.synthesize_timed(cs.clone()).unwrap();
+        assert!(timing.allocation > Duration::ZERO);
+        assert!(timing.enforcement > Duration::ZERO);
+        assert_eq!(cs.num_constraints(), 10_000);
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn dry_run_counts_match_a_real_synthesis() {
+        let (chain, witness) = squarings(20);
+        let circuits = [
+            CircomCircuit::new(product(), Some(values(&[1, 42, 6, 7]))),
+            CircomCircuit::new(chain, Some(witness)),
+        ];
+        let synthesized = |circuit: &dyn Fn(ConstraintSystemRef<Fr>)| {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            circuit(cs.clone());
+            (
+                cs.num_instance_variables(),
+                cs.num_witness_variables(),
+                cs.num_constraints(),
             )
         };
+        for circuit in circuits {
+            let explicit = circuit.clone().with_explicit_one();
+            assert_eq!(
+                circuit.dry_run_counts(),
+                synthesized(&|cs| circuit.clone().generate_constraints(cs).unwrap())
+            );
+            assert_eq!(
+                explicit.dry_run_counts(),
+                synthesized(&|cs| explicit.clone().generate_constraints(cs).unwrap())
+            );
+        }
+    }
+
+    #[test]
+    fn setup_circuit_allocates_zeros_without_a_witness() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        SetupCircuit(product())
+            .generate_constraints(cs.clone())
+            .unwrap();
+        let circuit = CircomCircuit::new(product(), None);
+        assert_eq!(
+            (
+                cs.num_instance_variables(),
+                cs.num_witness_variables(),
+                cs.num_constraints()
+            ),
+            circuit.dry_run_counts()
+        );
+        // zero satisfies `in * secret = out`
+        assert!(cs.is_satisfied().unwrap());
+    }
 
-        for constraint in &self.r1cs.constraints {
-            cs.enforce_constraint(
-                make_lc(&constraint.0),
-                make_lc(&constraint.1),
-                make_lc(&constraint.2),
-            )?;
+    #[test]
+    fn variables_map_back_to_their_wires() {
+        // wires [1, out, in] are instance wires, 3 and 4 witness wires
+        for shift in [0, 1] {
+            for wire in 0..5 {
+                let variable = wire_to_variable(wire, 3, shift);
+                assert_eq!(variable_to_wire(variable, 3, shift), Some(wire));
+            }
         }
+        assert_eq!(variable_to_wire(Variable::Instance(2), 3, 0), Some(2));
+        assert_eq!(variable_to_wire(Variable::Witness(1), 3, 0), Some(4));
+    }
 
-        Ok(())
+    #[test]
+    fn variables_without_a_wire_have_no_index() {
+        assert_eq!(variable_to_wire(Variable::One, 3, 0), Some(0));
+        // with a shift, arkworks' own `One` is not the constant wire
+        assert_eq!(variable_to_wire(Variable::One, 3, 1), None);
+        assert_eq!(variable_to_wire(Variable::Instance(0), 3, 1), None);
+        assert_eq!(variable_to_wire(Variable::Zero, 3, 0), None);
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let symbolic = cs.new_lc(LinearCombination::zero()).unwrap();
+        assert!(matches!(symbolic, Variable::SymbolicLc(_)));
+        assert_eq!(variable_to_wire(symbolic, 3, 0), None);
     }
 }
@@ -0,0 +1,252 @@
+//! Structural comparison of two R1CS systems
+//!
+//! Rows are compared in [canonical form](crate::transform::canonicalize_constraint), so
+//! a row whose terms were only reordered or split is not reported. Changed rows are
+//! reported as stored.
+//!
+//! Rows shared at the start and at the end of both systems are matched first, so a
+//! single inserted or removed row is reported as such rather than shifting every row
+//! after it. The rows left in between are compared by position: pairs are reported as
+//! changed and the excess of the longer side as added or removed. Indices of removed
+//! rows refer to `self`, those of added rows to the other system.
+use ark_ff::PrimeField;
+
+use crate::transform::canonicalize_constraint;
+use crate::{Constraints, R1CS};
+
+/// Maximum number of constraint rows reported by [`R1CS::diff`]
+pub const DEFAULT_DIFF_LIMIT: usize = 100;
+
+/// A single row-level difference between two constraint systems
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConstraintChange<F> {
+    /// Row only present in the other system
+    Added(usize, Constraints<F>),
+    /// Row only present in this system
+    Removed(usize, Constraints<F>),
+    /// Row present in both systems with different terms
+    Changed {
+        index: usize,
+        old: Constraints<F>,
+        new: Constraints<F>,
+    },
+}
+
+/// Differences between two R1CS systems.
+///
+/// Count fields hold `Some((self, other))` when the values differ.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct R1CSDiff<F> {
+    pub num_inputs: Option<(usize, usize)>,
+    pub num_aux: Option<(usize, usize)>,
+    pub num_variables: Option<(usize, usize)>,
+    pub num_constraints: Option<(usize, usize)>,
+    pub changes: Vec<ConstraintChange<F>>,
+    /// Set when more rows differ than the configured limit
+    pub truncated: bool,
+}
+
+impl<F> R1CSDiff<F> {
+    pub fn is_empty(&self) -> bool {
+        self.num_inputs.is_none()
+            && self.num_aux.is_none()
+            && self.num_variables.is_none()
+            && self.num_constraints.is_none()
+            && self.changes.is_empty()
+            && !self.truncated
+    }
+}
+
+fn count_diff(old: usize, new: usize) -> Option<(usize, usize)> {
+    if old == new {
+        None
+    } else {
+        Some((old, new))
+    }
+}
+
+impl<F: PrimeField> R1CS<F> {
+    /// Compares `self` against `other`, reporting at most [`DEFAULT_DIFF_LIMIT`] rows
+    pub fn diff(&self, other: &R1CS<F>) -> R1CSDiff<F> {
+        self.diff_with_limit(other, DEFAULT_DIFF_LIMIT)
+    }
+
+    /// Compares `self` against `other` row by row, reporting at most `limit` rows
+    pub fn diff_with_limit(&self, other: &R1CS<F>, limit: usize) -> R1CSDiff<F> {
+        let old_rows: Vec<_> = self
+            .constraints
+            .iter()
+            .map(canonicalize_constraint)
+            .collect();
+        let new_rows: Vec<_> = other
+            .constraints
+            .iter()
+            .map(canonicalize_constraint)
+            .collect();
+
+        let prefix = old_rows
+            .iter()
+            .zip(&new_rows)
+            .take_while(|(old, new)| old == new)
+            .count();
+        let suffix = old_rows[prefix..]
+            .iter()
+            .rev()
+            .zip(new_rows[prefix..].iter().rev())
+            .take_while(|(old, new)| old == new)
+            .count();
+        let old_end = old_rows.len() - suffix;
+        let new_end = new_rows.len() - suffix;
+
+        let mut changes = Vec::new();
+        let mut truncated = false;
+        for index in prefix..old_end.max(new_end) {
+            let old = self.constraints.get(index).filter(|_| index < old_end);
+            let new = other.constraints.get(index).filter(|_| index < new_end);
+            let change = match (old, new) {
+                (Some(_), Some(_)) if old_rows[index] == new_rows[index] => continue,
+                (Some(old), Some(new)) => ConstraintChange::Changed {
+                    index,
+                    old: old.clone(),
+                    new: new.clone(),
+                },
+                (Some(old), None) => ConstraintChange::Removed(index, old.clone()),
+                (None, Some(new)) => ConstraintChange::Added(index, new.clone()),
+                (None, None) => unreachable!(),
+            };
+            if changes.len() == limit {
+                truncated = true;
+                break;
+            }
+            changes.push(change);
+        }
+
+        R1CSDiff {
+            num_inputs: count_diff(self.num_inputs, other.num_inputs),
+            num_aux: count_diff(self.num_aux, other.num_aux),
+            num_variables: count_diff(self.num_variables, other.num_variables),
+            num_constraints: count_diff(self.constraints.len(), other.constraints.len()),
+            changes,
+            truncated,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+
+    /// `w[a] * w[b] = w[c]`
+    fn mul(a: usize, b: usize, c: usize) -> Constraints<Fr> {
+        (
+            vec![(a, Fr::from(1u64))],
+            vec![(b, Fr::from(1u64))],
+            vec![(c, Fr::from(1u64))],
+        )
+    }
+
+    /// A chain of multiplications over one public input and four private wires
+    fn chain() -> R1CS<Fr> {
+        R1CS::for_test(
+            0,
+            1,
+            4,
+            vec![mul(1, 1, 2), mul(2, 1, 3), mul(3, 1, 4), mul(4, 1, 5)],
+        )
+    }
+
+    #[test]
+    fn diff_against_itself_is_empty() {
+        let r1cs = chain();
+        let diff = r1cs.diff(&r1cs);
+        assert!(diff.is_empty());
+        assert_eq!(diff.changes, vec![]);
+    }
+
+    #[test]
+    fn reordered_terms_are_not_reported() {
+        let mut r1cs = chain();
+        r1cs.constraints[0].0 = vec![(1, Fr::from(2u64)), (0, Fr::from(0u64))];
+        let mut other = r1cs.clone();
+        other.constraints[0].0 = vec![(1, Fr::from(1u64)), (1, Fr::from(1u64))];
+
+        assert!(r1cs.diff(&other).is_empty());
+    }
+
+    #[test]
+    fn reports_one_modified_constraint() {
+        let r1cs = chain();
+        let mut other = chain();
+        other.constraints[2] = mul(3, 2, 4);
+
+        let diff = r1cs.diff(&other);
+        assert!(!diff.is_empty());
+        assert_eq!(diff.num_constraints, None);
+        assert_eq!(
+            diff.changes,
+            vec![ConstraintChange::Changed {
+                index: 2,
+                old: mul(3, 1, 4),
+                new: mul(3, 2, 4),
+            }]
+        );
+    }
+
+    #[test]
+    fn reports_an_inserted_row_once() {
+        let r1cs = chain();
+        let mut other = chain();
+        other.constraints.insert(1, mul(5, 5, 5));
+
+        let diff = r1cs.diff(&other);
+        assert_eq!(diff.num_constraints, Some((4, 5)));
+        assert_eq!(diff.changes, vec![ConstraintChange::Added(1, mul(5, 5, 5))]);
+    }
+
+    #[test]
+    fn reports_a_removed_row_once() {
+        let r1cs = chain();
+        let mut other = chain();
+        other.constraints.remove(2);
+
+        let diff = r1cs.diff(&other);
+        assert_eq!(diff.num_constraints, Some((4, 3)));
+        assert_eq!(
+            diff.changes,
+            vec![ConstraintChange::Removed(2, mul(3, 1, 4))]
+        );
+    }
+
+    #[test]
+    fn reports_header_counts() {
+        let r1cs = chain();
+        let mut other = chain();
+        other.num_aux = 5;
+        other.num_variables = 7;
+
+        let diff = r1cs.diff(&other);
+        assert_eq!(diff.num_inputs, None);
+        assert_eq!(diff.num_aux, Some((4, 5)));
+        assert_eq!(diff.num_variables, Some((6, 7)));
+        assert!(diff.changes.is_empty());
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn truncates_at_the_limit() {
+        let r1cs = chain();
+        let mut other = chain();
+        for constraint in other.constraints.iter_mut() {
+            constraint.1 = vec![(2, Fr::from(1u64))];
+        }
+
+        let diff = r1cs.diff_with_limit(&other, 2);
+        assert_eq!(diff.changes.len(), 2);
+        assert!(diff.truncated);
+
+        let diff = r1cs.diff_with_limit(&other, 0);
+        assert!(diff.changes.is_empty());
+        assert!(!diff.is_empty());
+    }
+}
@@ -0,0 +1,200 @@
+//! Errors returned by the reader, the circuit and the helpers built on them
+use ark_relations::r1cs::SynthesisError;
+use ark_serialize::SerializationError;
+use std::io;
+use thiserror::Error;
+
+use crate::r1cs_reader::Matrix;
+
+pub type Result<T, E = R1CSError> = std::result::Result<T, E>;
+
+#[derive(Debug, Error)]
+pub enum R1CSError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Serialization(#[from] SerializationError),
+    #[error(transparent)]
+    Synthesis(#[from] SynthesisError),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("invalid magic number {0:?}")]
+    InvalidMagic([u8; 4]),
+    #[error("unsupported version {0}")]
+    UnsupportedVersion(u32),
+    #[error(
+        "the circuit uses custom gates, which Groth16 cannot prove and the writer cannot preserve"
+    )]
+    CustomGates,
+    #[error("no section of type {0} found")]
+    MissingSection(u32),
+    #[error("invalid size {size} for section of type {section_type}")]
+    InvalidSectionSize { section_type: u32, size: u64 },
+    #[error("file is {len} bytes long but its sections extend to byte {end}")]
+    SectionOutOfBounds { end: u64, len: u64 },
+    #[error("file ends inside entry {section_index} of the section table")]
+    TruncatedFile { section_index: u32 },
+    #[error("file declares {value} {what}, more than the limit of {limit}")]
+    LimitExceeded {
+        what: &'static str,
+        value: u64,
+        limit: u64,
+    },
+    #[error("{0} unexpected bytes after the last section")]
+    TrailingBytes(u64),
+    #[error("field size {0} cannot hold the field modulus")]
+    InvalidFieldSize(u32),
+    #[error("field size of {field_size} bytes truncates the field modulus, which needs {needed}")]
+    FieldSizeMismatch { field_size: u32, needed: u32 },
+    #[error("prime {found} in header does not match the field modulus {expected}")]
+    PrimeMismatch { found: String, expected: String },
+    #[error(
+        "wire to label map references label {label} but the header declares {n_labels} labels"
+    )]
+    LabelOutOfRange { label: u64, n_labels: u64 },
+    #[error("inconsistent wire counts: {num_inputs} inputs and {num_aux} auxiliary wires for {num_variables} variables")]
+    InconsistentCounts {
+        num_inputs: usize,
+        num_aux: usize,
+        num_variables: usize,
+    },
+    #[error("{num_pub_out} public outputs and {num_pub_in} public inputs do not match {num_inputs} inputs")]
+    InconsistentPublicCounts {
+        num_inputs: usize,
+        num_pub_out: usize,
+        num_pub_in: usize,
+    },
+    #[error("header declares {n_wires} wires, fewer than the constant wire, {n_pub_out} public outputs, {n_pub_in} public inputs and {n_prv_in} private inputs")]
+    InconsistentWireCounts {
+        n_wires: u32,
+        n_pub_out: u32,
+        n_pub_in: u32,
+        n_prv_in: u32,
+    },
+    #[error("constraint {constraint} references wire {wire} in {matrix:?} but there are {n_wires} wires")]
+    WireOutOfRange {
+        constraint: usize,
+        matrix: Matrix,
+        wire: usize,
+        n_wires: usize,
+    },
+    #[error("wire index {0} does not fit in 32 bits")]
+    WireIndexTooLarge(usize),
+    #[error("{0} terms are too many for 32-bit offsets")]
+    TooManyTerms(usize),
+    #[error("constraint {constraint} has {len} terms in {matrix:?} but there are {n_wires} wires")]
+    ConstraintVecTooLong {
+        constraint: usize,
+        matrix: Matrix,
+        len: usize,
+        n_wires: usize,
+    },
+    #[error("constraint section ends inside constraint {constraint}")]
+    TruncatedConstraint { constraint: usize },
+    #[error("coefficient of wire {wire} in {matrix:?} of constraint {constraint} does not fit in the target field")]
+    CoefficientOutOfRange {
+        constraint: usize,
+        matrix: Matrix,
+        wire: usize,
+    },
+    #[error("constraint range {start}..{end} is out of bounds for {len} constraints")]
+    ConstraintRangeOutOfBounds {
+        start: usize,
+        end: usize,
+        len: usize,
+    },
+    #[error("header declares {expected} constraints but {actual} were read")]
+    ConstraintCountMismatch { expected: usize, actual: usize },
+    #[error("invalid symbol line: {0}")]
+    InvalidSymbol(String),
+    #[error("witness was generated for a different prime than the R1CS")]
+    WitnessPrimeMismatch,
+    #[error("expected a witness of length {expected} but found {actual}")]
+    WitnessLengthMismatch { expected: usize, actual: usize },
+    #[error("witness index {index} out of range for {len} values")]
+    WitnessIndexOutOfRange { index: usize, len: usize },
+    #[error("limbs of witness element {0} do not encode a field element")]
+    InvalidLimbs(usize),
+    #[error("first witness value is not the constant one")]
+    InvalidConstantWire,
+    #[error("circuit has no witness")]
+    MissingWitness,
+    #[error("archive has {count} .{ext} members instead of one")]
+    ArchiveMember { ext: &'static str, count: usize },
+    #[error("{0}")]
+    InvalidInput(String),
+    #[error("R1CS writer called out of order: {0}")]
+    WriterState(&'static str),
+    #[error("no value given for public signal {0}")]
+    MissingPublicInput(String),
+    #[error("invalid public input {0}")]
+    InvalidPublicInput(String),
+    #[error("verifying key expects {expected} public inputs but {actual} were provided")]
+    PublicInputCountMismatch { expected: usize, actual: usize },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+    use std::io::Cursor;
+
+    use crate::R1CSFile;
+
+    fn read_past_the_end() -> Result<u8> {
+        let mut byte = [0u8; 1];
+        io::Read::read_exact(&mut Cursor::new(Vec::new()), &mut byte)?;
+        Ok(byte[0])
+    }
+
+    #[test]
+    fn wrapped_errors_convert_with_the_question_mark() {
+        let err = read_past_the_end().unwrap_err();
+        assert!(matches!(&err, R1CSError::Io(err) if err.kind() == io::ErrorKind::UnexpectedEof));
+
+        let err = R1CSError::from(SynthesisError::AssignmentMissing);
+        assert_eq!(
+            err.to_string(),
+            SynthesisError::AssignmentMissing.to_string()
+        );
+        // usable wherever a boxed standard error is expected
+        let boxed: Box<dyn Error + Send + Sync> = Box::new(err);
+        assert!(boxed.downcast_ref::<R1CSError>().is_some());
+    }
+
+    #[test]
+    fn typed_variants_describe_themselves() {
+        assert_eq!(
+            R1CSError::InvalidMagic(*b"wtns").to_string(),
+            "invalid magic number [119, 116, 110, 115]"
+        );
+        assert_eq!(
+            R1CSError::WireOutOfRange {
+                constraint: 2,
+                matrix: Matrix::B,
+                wire: 9,
+                n_wires: 4
+            }
+            .to_string(),
+            "constraint 2 references wire 9 in B but there are 4 wires"
+        );
+    }
+
+    #[test]
+    fn the_reader_returns_typed_errors() {
+        let mut bytes = b"wtns".to_vec();
+        bytes.extend([1, 0, 0, 0, 0, 0, 0, 0]);
+        let err = R1CSFile::<ark_bn254::Fr>::new(Cursor::new(bytes))
+            .err()
+            .unwrap();
+        assert!(matches!(err, R1CSError::InvalidMagic(magic) if &magic == b"wtns"));
+
+        let err = R1CSFile::<ark_bn254::Fr>::new(Cursor::new(b"r1"))
+            .err()
+            .unwrap();
+        match err {
+            R1CSError::Io(err) => assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof),
+            err => panic!("unexpected error {}", err),
+        }
+    }
+}
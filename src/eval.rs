@@ -0,0 +1,409 @@
+//! Evaluation of constraints against a witness
+use ark_ff::PrimeField;
+use ark_std::io::{Read, Seek};
+use std::convert::TryFrom;
+
+use crate::error::{R1CSError, Result};
+use crate::r1cs_reader::ConstraintStream;
+use crate::witness::WitnessReader;
+use crate::{ConstraintVec, Constraints, R1CSFlat, R1CS};
+
+/// Evaluates the linear combination `lc` at `witness`, the sum of `coeff * witness[index]`.
+///
+/// # Panics
+///
+/// Panics if `lc` references a wire past the end of `witness`, see [`checked_dot`].
+pub fn dot<F: PrimeField>(lc: &ConstraintVec<F>, witness: &[F]) -> F {
+    lc.iter()
+        .map(|(index, coeff)| *coeff * witness[*index])
+        .sum()
+}
+
+/// Like [`dot`], but fails instead of panicking on a wire past the end of `witness`
+pub fn checked_dot<F: PrimeField>(lc: &ConstraintVec<F>, witness: &[F]) -> Result<F> {
+    lc.iter()
+        .map(|(index, coeff)| match witness.get(*index) {
+            Some(value) => Ok(*coeff * value),
+            None => Err(R1CSError::WitnessIndexOutOfRange {
+                index: *index,
+                len: witness.len(),
+            }),
+        })
+        .sum()
+}
+
+/// Evaluates the A, B and C sides of a constraint
+pub(crate) fn evaluate_constraint<F: PrimeField>(
+    constraint: &Constraints<F>,
+    witness: &[F],
+) -> (F, F, F) {
+    (
+        dot(&constraint.0, witness),
+        dot(&constraint.1, witness),
+        dot(&constraint.2, witness),
+    )
+}
+
+/// Like [`evaluate_constraint`], but fails on a wire past the end of `witness`
+pub(crate) fn checked_evaluate_constraint<F: PrimeField>(
+    constraint: &Constraints<F>,
+    witness: &[F],
+) -> Result<(F, F, F)> {
+    Ok((
+        checked_dot(&constraint.0, witness)?,
+        checked_dot(&constraint.1, witness)?,
+        checked_dot(&constraint.2, witness)?,
+    ))
+}
+
+impl<F: PrimeField> R1CS<F> {
+    /// Returns the index of the first constraint not satisfied by `witness`. A
+    /// constraint referencing a wire past the end of `witness` is not satisfied.
+    pub fn check_witness(&self, witness: &[F]) -> Result<(), usize> {
+        for (i, constraint) in self.constraints.iter().enumerate() {
+            match checked_evaluate_constraint(constraint, witness) {
+                Ok((a, b, c)) if a * b == c => {}
+                _ => return Err(i),
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns `<A_i, w> * <B_i, w> - <C_i, w>` for every constraint `i`, all zero when
+    /// `witness` satisfies the system
+    ///
+    /// # Panics
+    ///
+    /// Panics if a constraint references a wire past the end of `witness`.
+    pub fn qap_evaluations(&self, witness: &[F]) -> Vec<F> {
+        self.constraints
+            .iter()
+            .map(|constraint| {
+                let (a, b, c) = evaluate_constraint(constraint, witness);
+                a * b - c
+            })
+            .collect()
+    }
+
+    /// Like [`Self::check_witness`], but evaluates the constraints on the rayon thread
+    /// pool. Still reports the first unsatisfied constraint in order. On a single core
+    /// this is slower than the serial check, see `benches/check_witness.rs`.
+    #[cfg(feature = "parallel")]
+    pub fn check_witness_parallel(&self, witness: &[F]) -> Result<(), usize> {
+        use rayon::prelude::*;
+
+        match self
+            .constraints
+            .par_iter()
+            .enumerate()
+            .find_first(|(_, constraint)| {
+                !matches!(checked_evaluate_constraint(constraint, witness),
+                    Ok((a, b, c)) if a * b == c)
+            }) {
+            Some((i, _)) => Err(i),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks several witnesses against the same constraints, converting them to the
+    /// flat representation once for the whole batch
+    pub fn check_witnesses(&self, witnesses: &[Vec<F>]) -> Vec<Result<(), usize>> {
+        match R1CSFlat::try_from(self) {
+            Ok(flat) => witnesses
+                .iter()
+                .map(|witness| flat.check_witness(witness))
+                .collect(),
+            // Systems too large for the flat form are checked as they are
+            Err(_) => witnesses
+                .iter()
+                .map(|witness| self.check_witness(witness))
+                .collect(),
+        }
+    }
+}
+
+impl<R: Read, F: PrimeField> ConstraintStream<R, F> {
+    /// Checks `witness` against each constraint as it is read, stopping at the first
+    /// unsatisfied one. The inner result carries the index of that constraint in the
+    /// file, as with [`R1CS::check_witness`], also on a partly consumed stream.
+    pub fn check_witness(self, witness: &[F]) -> Result<Result<(), usize>> {
+        let start = self.header().n_constraints as usize - self.size_hint().0;
+        for (offset, constraint) in self.enumerate() {
+            match checked_evaluate_constraint(&constraint?, witness) {
+                Ok((a, b, c)) if a * b == c => {}
+                _ => return Ok(Err(start + offset)),
+            }
+        }
+        Ok(Ok(()))
+    }
+
+    /// Like [`ConstraintStream::check_witness`], but fetches witness values from disk as
+    /// the constraints need them, so that neither side has to fit in memory. A constraint
+    /// on a wire past the end of the witness is unsatisfied, as in memory; only errors
+    /// reading either file fail the check.
+    pub fn check_witness_reader<W: Read + Seek>(
+        self,
+        witness: &mut WitnessReader<W, F>,
+    ) -> Result<Result<(), usize>> {
+        let mut evaluate = |(a, b, c): &Constraints<F>| -> Result<(F, F, F)> {
+            let mut sums = [F::zero(); 3];
+            for (sum, lc) in sums.iter_mut().zip([a, b, c]) {
+                for (index, coeff) in lc {
+                    *sum += *coeff * witness.get(*index)?;
+                }
+            }
+            Ok((sums[0], sums[1], sums[2]))
+        };
+        let start = self.header().n_constraints as usize - self.size_hint().0;
+        for (offset, constraint) in self.enumerate() {
+            match evaluate(&constraint?) {
+                Ok((a, b, c)) if a * b == c => {}
+                Ok(_) | Err(R1CSError::WitnessIndexOutOfRange { .. }) => {
+                    return Ok(Err(start + offset))
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use ark_ff::BigInteger;
+    use ark_std::io::{Cursor, Seek};
+
+    use crate::r1cs_reader::Header;
+    use crate::{R1CSFile, R1CSWriter};
+
+    /// Repeated squaring `w[i + 1] * w[i + 1] = w[i + 2]` over `n + 2` wires, written
+    /// as a `.r1cs` file
+    fn squarings(n: u32) -> Vec<u8> {
+        let header = Header {
+            field_size: 32,
+            prime_size: Fr::MODULUS.to_bytes_le(),
+            n_wires: n + 2,
+            n_pub_out: 0,
+            n_pub_in: 1,
+            n_prv_in: 0,
+            n_labels: 0,
+            n_constraints: 0,
+        };
+        let mut writer = R1CSWriter::<_, Fr>::new(Cursor::new(Vec::new()));
+        writer.write_header(&header).unwrap();
+        for i in 0..n as usize {
+            let x = vec![(i + 1, Fr::from(1u64))];
+            writer
+                .push_constraint(&x, &x, &vec![(i + 2, Fr::from(1u64))])
+                .unwrap();
+        }
+        writer.finalize().unwrap().into_inner()
+    }
+
+    /// `x, x^2, x^4, ...` after the constant one
+    fn powers(x: u64, n: usize) -> Vec<Fr> {
+        let mut witness = vec![Fr::from(1u64), Fr::from(x)];
+        for _ in 0..n {
+            let last = *witness.last().unwrap();
+            witness.push(last * last);
+        }
+        witness
+    }
+
+    fn stream(bytes: &[u8]) -> ConstraintStream<Cursor<&[u8]>, Fr> {
+        ConstraintStream::new(Cursor::new(bytes)).unwrap()
+    }
+
+    #[test]
+    fn streamed_check_accepts_a_valid_witness() {
+        let bytes = squarings(4);
+        assert_eq!(stream(&bytes).check_witness(&powers(3, 4)).unwrap(), Ok(()));
+    }
+
+    #[test]
+    fn streamed_check_stops_at_the_first_failure() {
+        let mut bytes = squarings(4);
+        // corrupt the length of the last C vector, which a full read rejects
+        let len = bytes.len();
+        bytes[len - 40..len - 36].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(R1CSFile::<Fr>::new(Cursor::new(&bytes)).is_err());
+
+        let mut witness = powers(3, 4);
+        witness[3] += Fr::from(1u64);
+        assert_eq!(stream(&bytes).check_witness(&witness).unwrap(), Err(1));
+        // a witness that gets that far reaches the corrupt constraint
+        assert!(matches!(
+            stream(&bytes).check_witness(&powers(3, 4)),
+            Err(R1CSError::ConstraintVecTooLong { constraint: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn streamed_check_fails_a_short_witness() {
+        let bytes = squarings(4);
+        assert_eq!(
+            stream(&bytes).check_witness(&powers(3, 4)[..4]).unwrap(),
+            Err(2)
+        );
+    }
+
+    #[test]
+    fn checks_a_batch_of_witnesses() {
+        let r1cs = R1CS::from(R1CSFile::<Fr>::new(Cursor::new(squarings(3))).unwrap());
+        let mut wrong_last = powers(2, 3);
+        wrong_last[4] = Fr::from(255u64);
+        let mut wrong_first = powers(5, 3);
+        wrong_first[2] = Fr::from(24u64);
+
+        let batch = vec![
+            powers(2, 3),
+            wrong_last,
+            powers(7, 3),
+            wrong_first,
+            powers(5, 3)[..3].to_vec(),
+        ];
+        let results = r1cs.check_witnesses(&batch);
+        assert_eq!(results, vec![Ok(()), Err(2), Ok(()), Err(0), Err(1)]);
+        for (witness, result) in batch.iter().zip(&results) {
+            assert_eq!(r1cs.check_witness(witness), *result);
+        }
+        assert!(r1cs.check_witnesses(&[]).is_empty());
+    }
+
+    /// `witness` written to a `.wtns` file on disk, positioned at its start
+    fn on_disk(witness: &[Fr]) -> std::fs::File {
+        let mut file = crate::write_witness(tempfile::tempfile().unwrap(), witness).unwrap();
+        file.rewind().unwrap();
+        file
+    }
+
+    #[test]
+    fn streamed_check_against_an_on_disk_witness() {
+        let bytes = squarings(4);
+        let mut witness =
+            WitnessReader::<_, Fr>::with_cache_capacity(on_disk(&powers(3, 4)), 2).unwrap();
+        assert_eq!(
+            stream(&bytes).check_witness_reader(&mut witness).unwrap(),
+            Ok(())
+        );
+
+        let mut wrong = powers(3, 4);
+        wrong[4] += Fr::from(1u64);
+        let mut witness = WitnessReader::<_, Fr>::new(on_disk(&wrong)).unwrap();
+        assert_eq!(
+            stream(&bytes).check_witness_reader(&mut witness).unwrap(),
+            Err(2)
+        );
+    }
+
+    #[test]
+    fn a_truncated_witness_fails_the_same_constraint_on_disk() {
+        let bytes = squarings(4);
+        for len in 2..6 {
+            let truncated = &powers(3, 4)[..len];
+            let mut witness = WitnessReader::<_, Fr>::new(on_disk(truncated)).unwrap();
+            let expected = stream(&bytes).check_witness(truncated).unwrap();
+            assert_eq!(expected, Err(len - 2));
+            assert_eq!(
+                stream(&bytes).check_witness_reader(&mut witness).unwrap(),
+                expected
+            );
+        }
+    }
+
+    fn squarings_r1cs(n: u32) -> R1CS<Fr> {
+        R1CSFile::<Fr>::new(Cursor::new(squarings(n)))
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn qap_evaluations_vanish_for_a_satisfying_witness() {
+        let evaluations = squarings_r1cs(3).qap_evaluations(&powers(3, 3));
+        assert_eq!(evaluations, vec![Fr::from(0u64); 3]);
+    }
+
+    #[test]
+    fn qap_evaluations_are_a_times_b_minus_c() {
+        let mut witness = powers(3, 3);
+        witness[3] += Fr::from(1u64);
+        assert_eq!(
+            squarings_r1cs(3).qap_evaluations(&witness),
+            vec![Fr::from(0u64), -Fr::from(1u64), Fr::from(82 * 82 - 6561u64)]
+        );
+    }
+
+    #[test]
+    fn dot_sums_coefficients_times_values() {
+        // 3 * w0 - w2 + 2 * w2 = 3 + 7
+        let lc = vec![
+            (0, Fr::from(3u64)),
+            (2, -Fr::from(1u64)),
+            (2, Fr::from(2u64)),
+        ];
+        let witness = [1u64, 5, 7].map(Fr::from);
+        assert_eq!(dot(&lc, &witness), Fr::from(10u64));
+        assert_eq!(checked_dot(&lc, &witness).unwrap(), Fr::from(10u64));
+        assert_eq!(dot(&Vec::new(), &witness), Fr::from(0u64));
+    }
+
+    #[test]
+    fn checked_dot_rejects_a_wire_past_the_witness() {
+        let lc = vec![(0, Fr::from(1u64)), (3, Fr::from(1u64))];
+        assert!(matches!(
+            checked_dot(&lc, &[1u64, 5, 7].map(Fr::from)),
+            Err(R1CSError::WitnessIndexOutOfRange { index: 3, len: 3 })
+        ));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_check_agrees_with_the_serial_one() {
+        let r1cs = squarings_r1cs(64);
+        let valid = powers(3, 64);
+        assert_eq!(r1cs.check_witness_parallel(&valid), Ok(()));
+
+        for wire in [1, 2, 17, 40, 65] {
+            let mut witness = valid.clone();
+            witness[wire] += Fr::from(1u64);
+            // corrupting wire i breaks constraint i - 2, if any, and constraint i - 1
+            let expected = r1cs.check_witness(&witness);
+            assert_eq!(expected, Err(wire.saturating_sub(2)));
+            assert_eq!(r1cs.check_witness_parallel(&witness), expected);
+        }
+
+        // the earliest of several failures wins, however the work is split
+        let mut witness = valid.clone();
+        for wire in [50, 30, 60] {
+            witness[wire] = Fr::from(0u64);
+        }
+        assert_eq!(r1cs.check_witness_parallel(&witness), Err(28));
+        assert_eq!(r1cs.check_witness(&witness), Err(28));
+
+        // a short witness fails at the first constraint past its end
+        assert_eq!(r1cs.check_witness_parallel(&valid[..20]), Err(18));
+        assert_eq!(r1cs.check_witness(&valid[..20]), Err(18));
+    }
+
+    #[test]
+    fn a_partly_consumed_stream_reports_file_indices() {
+        let bytes = squarings(4);
+        let mut witness = powers(3, 4);
+        witness[5] += Fr::from(1u64);
+
+        let mut constraints = stream(&bytes);
+        constraints.next().unwrap().unwrap();
+        constraints.next().unwrap().unwrap();
+        assert_eq!(constraints.check_witness(&witness).unwrap(), Err(3));
+
+        let mut on_disk = WitnessReader::<_, Fr>::new(on_disk(&witness)).unwrap();
+        let mut constraints = stream(&bytes);
+        constraints.next().unwrap().unwrap();
+        assert_eq!(
+            constraints.check_witness_reader(&mut on_disk).unwrap(),
+            Err(3)
+        );
+    }
+}
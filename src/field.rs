@@ -0,0 +1,138 @@
+//! Runtime selection of the prime field
+//!
+//! The reader is generic over `F`, so the field has to be known at compile time. Tools
+//! that take the field as an argument parse a [`FieldKind`] and load an [`AnyR1CS`],
+//! which holds the system monomorphized for the chosen field.
+use ark_ff::{Fp64, MontBackend, MontConfig};
+use ark_std::io::{Read, Seek};
+
+use std::convert::TryInto;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{R1CSError, Result};
+use crate::{R1CSFile, R1CSSummary, R1CS};
+
+#[derive(MontConfig)]
+#[modulus = "2147483647"]
+#[generator = "7"]
+pub struct M31Config;
+
+/// The Mersenne prime field `2^31 - 1`
+pub type M31 = Fp64<MontBackend<M31Config, 1>>;
+
+pub type Bn254 = ark_bn254::Fr;
+
+/// A prime field supported by [`AnyR1CS`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FieldKind {
+    /// The BN254 scalar field, circom's default `bn128` prime
+    Bn254,
+    M31,
+}
+
+impl FromStr for FieldKind {
+    type Err = R1CSError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "bn254" | "bn128" => Ok(FieldKind::Bn254),
+            "m31" => Ok(FieldKind::M31),
+            _ => Err(R1CSError::InvalidInput(format!("unknown field {}", s))),
+        }
+    }
+}
+
+impl fmt::Display for FieldKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldKind::Bn254 => write!(f, "bn254"),
+            FieldKind::M31 => write!(f, "m31"),
+        }
+    }
+}
+
+/// An R1CS over one of the fields of [`FieldKind`]
+#[derive(Clone, Debug)]
+pub enum AnyR1CS {
+    Bn254(R1CS<Bn254>),
+    M31(R1CS<M31>),
+}
+
+impl AnyR1CS {
+    /// Reads an `.r1cs` file, which must have been compiled for the prime of `kind`
+    pub fn new<R: Read + Seek>(kind: FieldKind, reader: R) -> Result<AnyR1CS> {
+        Ok(match kind {
+            FieldKind::Bn254 => AnyR1CS::Bn254(R1CSFile::new(reader)?.into()),
+            FieldKind::M31 => AnyR1CS::M31(R1CSFile::new(reader)?.into()),
+        })
+    }
+
+    pub fn kind(&self) -> FieldKind {
+        match self {
+            AnyR1CS::Bn254(_) => FieldKind::Bn254,
+            AnyR1CS::M31(_) => FieldKind::M31,
+        }
+    }
+
+    pub fn num_constraints(&self) -> usize {
+        match self {
+            AnyR1CS::Bn254(r1cs) => r1cs.constraints.len(),
+            AnyR1CS::M31(r1cs) => r1cs.constraints.len(),
+        }
+    }
+
+    pub fn summary(&self) -> R1CSSummary {
+        match self {
+            AnyR1CS::Bn254(r1cs) => r1cs.summary(),
+            AnyR1CS::M31(r1cs) => r1cs.summary(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    fn prime_file(prime: &str) -> File {
+        File::open(format!("./test-vectors/primes/{}.r1cs", prime)).unwrap()
+    }
+
+    #[test]
+    fn parses_field_names() {
+        assert_eq!("bn254".parse::<FieldKind>().unwrap(), FieldKind::Bn254);
+        assert_eq!("BN128".parse::<FieldKind>().unwrap(), FieldKind::Bn254);
+        assert_eq!("m31".parse::<FieldKind>().unwrap(), FieldKind::M31);
+        assert!(matches!(
+            "goldilocks".parse::<FieldKind>(),
+            Err(R1CSError::InvalidInput(_))
+        ));
+        for kind in [FieldKind::Bn254, FieldKind::M31] {
+            assert_eq!(kind.to_string().parse::<FieldKind>().unwrap(), kind);
+        }
+    }
+
+    #[test]
+    fn loads_the_fixture_of_each_field() {
+        for (name, prime) in [("bn254", "bn128"), ("m31", "m31")] {
+            let kind = name.parse().unwrap();
+            let r1cs = AnyR1CS::new(kind, prime_file(prime)).unwrap();
+            assert_eq!(r1cs.kind(), kind);
+            assert_eq!(r1cs.num_constraints(), 1);
+
+            let summary = r1cs.summary();
+            assert_eq!(summary.num_constraints, 1);
+            assert_eq!(summary.num_pub_out, 1);
+            assert_eq!(summary.num_terms, (1, 1, 1));
+        }
+    }
+
+    #[test]
+    fn rejects_a_fixture_for_another_field() {
+        assert!(matches!(
+            AnyR1CS::new(FieldKind::M31, prime_file("bn128")),
+            Err(R1CSError::PrimeMismatch { .. })
+        ));
+    }
+}
@@ -0,0 +1,366 @@
+//! Flat (CSR-style) representation of an R1CS
+//!
+//! The wire indices and coefficients of every term live in two contiguous arrays, one
+//! constraint after another and A, B then C within each, with a `u32` offset marking
+//! where each vector starts. This replaces the three small allocations per constraint
+//! of [`R1CS`] with three for the whole system, and wire indices take 4 bytes instead
+//! of 8. [`FlatCircuit`] synthesizes the system straight from this form.
+use ark_ff::PrimeField;
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystemRef, LinearCombination, SynthesisError,
+};
+use std::convert::TryFrom;
+
+use crate::error::{R1CSError, Result};
+use crate::{wire_to_variable, ConstraintVec, Matrix, R1CSMetadata, R1CS};
+
+/// An R1CS whose terms are stored in a single index array and a single coefficient
+/// array
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct R1CSFlat<F> {
+    pub metadata: R1CSMetadata,
+    /// Wire index of every term
+    pub indices: Vec<u32>,
+    /// Coefficient of every term, in the order of `indices`
+    pub coeffs: Vec<F>,
+    /// Vector `m` of constraint `i`, with `m` 0, 1 and 2 for A, B and C, spans
+    /// `offsets[3 * i + m]..offsets[3 * i + m + 1]` of `indices` and `coeffs`
+    pub offsets: Vec<u32>,
+}
+
+impl<F: PrimeField> R1CSFlat<F> {
+    pub fn num_constraints(&self) -> usize {
+        self.offsets.len().saturating_sub(1) / 3
+    }
+
+    /// Returns the `(index, coeff)` terms of `matrix` in constraint `i`
+    pub fn terms(&self, i: usize, matrix: Matrix) -> impl Iterator<Item = (usize, &F)> {
+        let vector = 3 * i + matrix as usize;
+        let range = self.offsets[vector] as usize..self.offsets[vector + 1] as usize;
+        self.indices[range.clone()]
+            .iter()
+            .map(|index| *index as usize)
+            .zip(&self.coeffs[range])
+    }
+
+    fn to_constraint_vec(&self, i: usize, matrix: Matrix) -> ConstraintVec<F> {
+        self.terms(i, matrix)
+            .map(|(index, coeff)| (index, *coeff))
+            .collect()
+    }
+
+    /// Evaluates `matrix` of constraint `i` at `witness`, or `None` if it references a
+    /// wire past the end
+    fn evaluate(&self, i: usize, matrix: Matrix, witness: &[F]) -> Option<F> {
+        self.terms(i, matrix)
+            .map(|(index, coeff)| Some(*coeff * witness.get(index)?))
+            .sum()
+    }
+
+    /// Returns the index of the first constraint not satisfied by `witness`. A
+    /// constraint referencing a wire past the end of `witness` is not satisfied.
+    pub fn check_witness(&self, witness: &[F]) -> Result<(), usize> {
+        for i in 0..self.num_constraints() {
+            let a = self.evaluate(i, Matrix::A, witness);
+            let b = self.evaluate(i, Matrix::B, witness);
+            let c = self.evaluate(i, Matrix::C, witness);
+            match (a, b, c) {
+                (Some(a), Some(b), Some(c)) if a * b == c => {}
+                _ => return Err(i),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Fails with [`R1CSError::WireIndexTooLarge`] if a wire index does not fit in 32 bits,
+/// or [`R1CSError::TooManyTerms`] if the system has `2^32` terms or more
+impl<F: PrimeField> TryFrom<&R1CS<F>> for R1CSFlat<F> {
+    type Error = R1CSError;
+
+    fn try_from(r1cs: &R1CS<F>) -> Result<Self> {
+        let terms: usize = r1cs
+            .constraints
+            .iter()
+            .map(|(a, b, c)| a.len() + b.len() + c.len())
+            .sum();
+        if u32::try_from(terms).is_err() {
+            return Err(R1CSError::TooManyTerms(terms));
+        }
+
+        let mut indices = Vec::with_capacity(terms);
+        let mut coeffs = Vec::with_capacity(terms);
+        let mut offsets = Vec::with_capacity(3 * r1cs.constraints.len() + 1);
+        offsets.push(0);
+        for (a, b, c) in &r1cs.constraints {
+            for vector in [a, b, c] {
+                for (index, coeff) in vector {
+                    let index =
+                        u32::try_from(*index).map_err(|_| R1CSError::WireIndexTooLarge(*index))?;
+                    indices.push(index);
+                    coeffs.push(*coeff);
+                }
+                // cannot overflow, as the total was checked above
+                offsets.push(indices.len() as u32);
+            }
+        }
+        Ok(R1CSFlat {
+            metadata: r1cs.metadata(),
+            indices,
+            coeffs,
+            offsets,
+        })
+    }
+}
+
+impl<F: PrimeField> TryFrom<R1CS<F>> for R1CSFlat<F> {
+    type Error = R1CSError;
+
+    fn try_from(r1cs: R1CS<F>) -> Result<Self> {
+        Self::try_from(&r1cs)
+    }
+}
+
+impl<F: PrimeField> From<&R1CSFlat<F>> for R1CS<F> {
+    fn from(flat: &R1CSFlat<F>) -> Self {
+        let constraints = (0..flat.num_constraints())
+            .map(|i| {
+                (
+                    flat.to_constraint_vec(i, Matrix::A),
+                    flat.to_constraint_vec(i, Matrix::B),
+                    flat.to_constraint_vec(i, Matrix::C),
+                )
+            })
+            .collect();
+        flat.metadata.with_constraints(constraints)
+    }
+}
+
+impl<F: PrimeField> From<R1CSFlat<F>> for R1CS<F> {
+    fn from(flat: R1CSFlat<F>) -> Self {
+        Self::from(&flat)
+    }
+}
+
+/// A circom circuit over an [`R1CSFlat`]. It synthesizes the same variables and
+/// constraints as a [`CircomCircuit`](crate::CircomCircuit) over the nested form, with
+/// placeholder values when there is no witness.
+#[derive(Clone, Debug)]
+pub struct FlatCircuit<F> {
+    pub r1cs: R1CSFlat<F>,
+    pub witness: Option<Vec<F>>,
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for FlatCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let num_inputs = self.r1cs.metadata.num_inputs;
+        let num_aux = self.r1cs.metadata.num_aux;
+        match &self.witness {
+            Some(w) => {
+                let inputs = w.get(1..num_inputs);
+                let aux = w.get(num_inputs..num_inputs + num_aux);
+                let (inputs, aux) = inputs.zip(aux).ok_or(SynthesisError::AssignmentMissing)?;
+                for value in inputs {
+                    cs.new_input_variable(|| Ok(*value))?;
+                }
+                for value in aux {
+                    cs.new_witness_variable(|| Ok(*value))?;
+                }
+            }
+            None => {
+                for _ in 1..num_inputs {
+                    cs.new_input_variable(|| Ok(F::one()))?;
+                }
+                for _ in 0..num_aux {
+                    cs.new_witness_variable(|| Ok(F::one()))?;
+                }
+            }
+        }
+
+        // Each combination is collected at its final size and sorted once, which gives
+        // the same combination as adding the terms one at a time
+        let make_lc = |i, matrix| {
+            let mut lc = LinearCombination(
+                self.r1cs
+                    .terms(i, matrix)
+                    .map(|(index, coeff)| (*coeff, wire_to_variable(index, num_inputs, 0)))
+                    .collect(),
+            );
+            lc.compactify();
+            lc
+        };
+        for i in 0..self.r1cs.num_constraints() {
+            cs.enforce_constraint(
+                make_lc(i, Matrix::A),
+                make_lc(i, Matrix::B),
+                make_lc(i, Matrix::C),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    use crate::CircomCircuit;
+
+    /// `x * x = y` and `(y + 1) * 1 = out`, over wires `[1, out, x, y]`
+    fn square_plus_one() -> R1CS<Fr> {
+        let one = Fr::from(1u64);
+        R1CS::for_test(
+            1,
+            0,
+            2,
+            vec![
+                (vec![(2, one)], vec![(2, one)], vec![(3, one)]),
+                (vec![(3, one), (0, one)], vec![(0, one)], vec![(1, one)]),
+            ],
+        )
+    }
+
+    fn witness(x: u64) -> Vec<Fr> {
+        [1, x * x + 1, x, x * x]
+            .iter()
+            .map(|v| Fr::from(*v))
+            .collect()
+    }
+
+    fn flat(r1cs: &R1CS<Fr>) -> R1CSFlat<Fr> {
+        R1CSFlat::try_from(r1cs).unwrap()
+    }
+
+    #[test]
+    fn stores_every_term_in_one_slab() {
+        let flat = flat(&square_plus_one());
+        assert_eq!(flat.num_constraints(), 2);
+        assert_eq!(flat.indices, vec![2, 2, 3, 3, 0, 0, 1]);
+        assert_eq!(flat.coeffs.len(), 7);
+        assert_eq!(flat.offsets, vec![0, 1, 2, 3, 5, 6, 7]);
+        assert_eq!(
+            flat.terms(1, Matrix::A).collect::<Vec<_>>(),
+            vec![(3, &Fr::from(1u64)), (0, &Fr::from(1u64))]
+        );
+        assert_eq!(
+            flat.terms(1, Matrix::C).collect::<Vec<_>>(),
+            vec![(1, &Fr::from(1u64))]
+        );
+    }
+
+    #[test]
+    fn round_trips_through_the_nested_form() {
+        let r1cs = square_plus_one();
+        let back = R1CS::from(flat(&r1cs));
+        assert_eq!(back.constraints, r1cs.constraints);
+        assert_eq!(back.metadata(), r1cs.metadata());
+    }
+
+    #[test]
+    fn rejects_wires_past_32_bits() {
+        let mut r1cs = square_plus_one();
+        r1cs.constraints[1].2.push((1 << 32, Fr::from(1u64)));
+        assert!(matches!(
+            R1CSFlat::try_from(r1cs),
+            Err(R1CSError::WireIndexTooLarge(wire)) if wire == 1 << 32
+        ));
+    }
+
+    #[test]
+    fn checks_witnesses_like_the_nested_form() {
+        let flat = flat(&square_plus_one());
+        assert_eq!(flat.check_witness(&witness(5)), Ok(()));
+
+        let mut wrong = witness(5);
+        wrong[1] = Fr::from(25u64);
+        assert_eq!(flat.check_witness(&wrong), Err(1));
+        wrong[3] = Fr::from(24u64);
+        assert_eq!(flat.check_witness(&wrong), Err(0));
+    }
+
+    #[test]
+    fn short_witness_fails_the_first_row_past_it() {
+        let flat = flat(&square_plus_one());
+        assert_eq!(flat.check_witness(&witness(5)[..3]), Err(0));
+    }
+
+    #[test]
+    fn empty_system_has_no_rows() {
+        let r1cs = square_plus_one().with_constraints::<Fr>(Vec::new());
+        let flat = flat(&r1cs);
+        assert_eq!(flat.num_constraints(), 0);
+        assert_eq!(flat.check_witness(&[]), Ok(()));
+        assert!(R1CS::from(flat).constraints.is_empty());
+    }
+
+    #[test]
+    fn synthesizes_the_same_system_as_the_nested_circuit() {
+        let mut r1cs = square_plus_one();
+        // repeated and unsorted terms are merged and sorted the same way
+        r1cs.constraints[1].0.push((3, Fr::from(2u64)));
+        r1cs.constraints[1].2.insert(0, (3, Fr::from(3u64)));
+        // `out = 3 * y + 1 - 3 * y` for x = 10
+        let witness: Vec<Fr> = [1, 1, 10, 100].map(|v: u64| Fr::from(v)).to_vec();
+
+        let nested = ConstraintSystem::<Fr>::new_ref();
+        CircomCircuit::new(r1cs.clone(), Some(witness.clone()))
+            .generate_constraints(nested.clone())
+            .unwrap();
+        let from_flat = ConstraintSystem::<Fr>::new_ref();
+        FlatCircuit {
+            r1cs: flat(&r1cs),
+            witness: Some(witness),
+        }
+        .generate_constraints(from_flat.clone())
+        .unwrap();
+
+        assert_eq!(
+            from_flat.num_instance_variables(),
+            nested.num_instance_variables()
+        );
+        assert_eq!(
+            from_flat.num_witness_variables(),
+            nested.num_witness_variables()
+        );
+        assert!(nested.is_satisfied().unwrap());
+        assert!(from_flat.is_satisfied().unwrap());
+        nested.finalize();
+        from_flat.finalize();
+        let (nested, from_flat) = (
+            nested.to_matrices().unwrap(),
+            from_flat.to_matrices().unwrap(),
+        );
+        assert_eq!(from_flat.a, nested.a);
+        assert_eq!(from_flat.b, nested.b);
+        assert_eq!(from_flat.c, nested.c);
+    }
+
+    #[test]
+    fn a_short_witness_is_missing_assignments() {
+        let circuit = FlatCircuit {
+            r1cs: flat(&square_plus_one()),
+            witness: Some(witness(5)[..3].to_vec()),
+        };
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        assert!(matches!(
+            circuit.generate_constraints(cs),
+            Err(SynthesisError::AssignmentMissing)
+        ));
+    }
+
+    #[test]
+    fn synthesizes_placeholders_without_a_witness() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        FlatCircuit {
+            r1cs: flat(&square_plus_one()),
+            witness: None,
+        }
+        .generate_constraints(cs.clone())
+        .unwrap();
+        assert_eq!(cs.num_instance_variables(), 2);
+        assert_eq!(cs.num_witness_variables(), 2);
+        assert_eq!(cs.num_constraints(), 2);
+    }
+}
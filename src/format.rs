@@ -0,0 +1,219 @@
+//! Human-readable rendering of constraints
+//!
+//! Wires are shown by their signal name when a [`SymFile`] is available and as `w<index>`
+//! otherwise. The constant one wire is rendered as a bare coefficient.
+//!
+//! Coefficients above `p / 2` are shown as the small negative numbers they stand for,
+//! so that `p - 1` reads `-1`, unless raw values are asked for.
+use ark_ff::PrimeField;
+
+use crate::error::Result;
+use crate::{ConstraintVec, Constraints, SymFile, R1CS};
+
+fn format_wire(wire: usize, sym: Option<&SymFile>) -> String {
+    match sym.and_then(|sym| sym.name(wire)) {
+        Some(name) => name.to_string(),
+        None => format!("w{}", wire),
+    }
+}
+
+/// Renders `coeff` in decimal, as a negative number above `p / 2` unless `raw` is set
+pub fn format_coefficient<F: PrimeField>(coeff: &F, raw: bool) -> String {
+    let (negative, magnitude) = signed_magnitude(coeff, raw);
+    if negative {
+        format!("-{}", magnitude)
+    } else {
+        magnitude.to_string()
+    }
+}
+
+/// Whether `coeff` is shown as negative, and the value shown without its sign
+fn signed_magnitude<F: PrimeField>(coeff: &F, raw: bool) -> (bool, F) {
+    if !raw && coeff.into_bigint() > F::MODULUS_MINUS_ONE_DIV_TWO {
+        (true, -*coeff)
+    } else {
+        (false, *coeff)
+    }
+}
+
+/// Renders a linear combination such as `3*main.a - main.b + 1`
+pub fn format_linear_combination<F: PrimeField>(
+    lc: &ConstraintVec<F>,
+    sym: Option<&SymFile>,
+) -> String {
+    format_linear_combination_with(lc, sym, false)
+}
+
+/// Like [`format_linear_combination`], but with `raw` set, every coefficient is shown
+/// as its canonical value in `[0, p)` and terms are always joined with `+`
+pub fn format_linear_combination_with<F: PrimeField>(
+    lc: &ConstraintVec<F>,
+    sym: Option<&SymFile>,
+    raw: bool,
+) -> String {
+    let mut out = String::new();
+    for (i, (wire, coeff)) in lc.iter().enumerate() {
+        let (negative, magnitude) = signed_magnitude(coeff, raw);
+        out.push_str(match (i, negative) {
+            (0, false) => "",
+            (0, true) => "-",
+            (_, false) => " + ",
+            (_, true) => " - ",
+        });
+        if *wire == 0 {
+            out.push_str(&magnitude.to_string());
+        } else if magnitude.is_one() {
+            out.push_str(&format_wire(*wire, sym));
+        } else {
+            out.push_str(&format!("{}*{}", magnitude, format_wire(*wire, sym)));
+        }
+    }
+    if out.is_empty() {
+        out.push('0');
+    }
+    out
+}
+
+/// Renders a constraint as `(A) * (B) = (C)`
+pub fn format_constraint<F: PrimeField>(
+    constraint: &Constraints<F>,
+    sym: Option<&SymFile>,
+) -> String {
+    format_constraint_with(constraint, sym, false)
+}
+
+/// Like [`format_constraint`], showing raw coefficients if `raw` is set. See
+/// [`format_linear_combination_with`].
+pub fn format_constraint_with<F: PrimeField>(
+    constraint: &Constraints<F>,
+    sym: Option<&SymFile>,
+    raw: bool,
+) -> String {
+    format!(
+        "({}) * ({}) = ({})",
+        format_linear_combination_with(&constraint.0, sym, raw),
+        format_linear_combination_with(&constraint.1, sym, raw),
+        format_linear_combination_with(&constraint.2, sym, raw),
+    )
+}
+
+impl<F: PrimeField> R1CS<F> {
+    /// Prints `len` constraints starting at `start`, one per line
+    pub fn print_range(&self, start: usize, len: usize, sym: Option<&SymFile>) -> Result<()> {
+        for (i, constraint) in self.constraint_range(start, len)?.iter().enumerate() {
+            println!("{}: {}", start + i, format_constraint(constraint, sym));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+
+    use crate::error::R1CSError;
+    use crate::Symbol;
+
+    /// `(a + 2) * (b) = (out)`, over wires `[1, out, a, b]`
+    fn shifted_product() -> R1CS<Fr> {
+        R1CS::for_test(
+            1,
+            0,
+            2,
+            vec![(
+                vec![(2, Fr::from(1u64)), (0, Fr::from(2u64))],
+                vec![(3, Fr::from(1u64))],
+                vec![(1, Fr::from(1u64))],
+            )],
+        )
+    }
+
+    fn names() -> SymFile {
+        let symbol = |wire, name: &str| Symbol {
+            label: wire as u64,
+            wire: Some(wire),
+            component: 0,
+            name: name.to_string(),
+        };
+        SymFile::from_symbols(vec![
+            symbol(1, "main.out"),
+            symbol(2, "main.a"),
+            symbol(3, "main.b"),
+        ])
+    }
+
+    #[test]
+    fn renders_constraints_with_and_without_names() {
+        let r1cs = shifted_product();
+        assert_eq!(
+            format_constraint(&r1cs[0], Some(&names())),
+            "(main.a + 2) * (main.b) = (main.out)"
+        );
+        assert_eq!(format_constraint(&r1cs[0], None), "(w2 + 2) * (w3) = (w1)");
+    }
+
+    #[test]
+    fn print_range_rejects_a_window_past_the_end() {
+        let r1cs = shifted_product();
+        assert!(r1cs.print_range(0, 1, Some(&names())).is_ok());
+        assert!(matches!(
+            r1cs.print_range(1, 1, None),
+            Err(R1CSError::ConstraintRangeOutOfBounds {
+                start: 1,
+                end: 2,
+                len: 1
+            })
+        ));
+    }
+
+    fn modulus_minus(n: u8) -> Fr {
+        Fr::from(num_bigint::BigUint::from(Fr::MODULUS) - n)
+    }
+
+    #[test]
+    fn the_modulus_minus_one_prints_as_minus_one() {
+        assert_eq!(modulus_minus(1), -Fr::from(1u64));
+        assert_eq!(format_coefficient(&modulus_minus(1), false), "-1");
+        assert_eq!(format_coefficient(&modulus_minus(7), false), "-7");
+        assert_eq!(
+            format_coefficient(&modulus_minus(1), true),
+            (num_bigint::BigUint::from(Fr::MODULUS) - 1u8).to_string()
+        );
+    }
+
+    #[test]
+    fn only_values_above_half_the_modulus_are_negative() {
+        let half = Fr::from(Fr::MODULUS_MINUS_ONE_DIV_TWO);
+        assert_eq!(format_coefficient(&half, false), half.to_string());
+        assert_eq!(
+            format_coefficient(&(half + Fr::from(1u64)), false),
+            format!("-{}", half)
+        );
+        assert_eq!(format_coefficient(&Fr::from(0u64), false), "0");
+    }
+
+    #[test]
+    fn negative_terms_are_subtracted() {
+        let lc = vec![
+            (2, -Fr::from(1u64)),
+            (3, -Fr::from(2u64)),
+            (0, modulus_minus(3)),
+        ];
+        assert_eq!(
+            format_linear_combination(&lc, Some(&names())),
+            "-main.a - 2*main.b - 3"
+        );
+        let raw = format_linear_combination_with(&lc, None, true);
+        assert_eq!(
+            raw,
+            format!(
+                "{}*w2 + {}*w3 + {}",
+                modulus_minus(1),
+                modulus_minus(2),
+                modulus_minus(3)
+            )
+        );
+        assert!(!raw.contains('-'));
+    }
+}
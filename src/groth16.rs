@@ -0,0 +1,264 @@
+//! Groth16 setup, proving and verification for circom circuits
+//!
+//! Keys and proofs are ark-serialized in compressed form. Public inputs are read from
+//! a JSON array of decimal strings, as written by `snarkjs` to `public.json`, or taken
+//! from a `.wtns` file.
+use ark_ec::pairing::Pairing;
+use ark_ff::PrimeField;
+use ark_groth16::{prepare_verifying_key, Groth16, Proof, ProvingKey, VerifyingKey};
+use ark_serialize::CanonicalDeserialize;
+use ark_std::rand::Rng;
+
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+use std::path::Path;
+
+use crate::error::{R1CSError, Result};
+use crate::{CircomCircuit, WitnessFile};
+
+/// Runs a circuit-specific trusted setup
+pub fn setup<E: Pairing, R: Rng>(
+    circuit: CircomCircuit<E::ScalarField>,
+    rng: &mut R,
+) -> Result<ProvingKey<E>> {
+    if !circuit.r1cs.is_pure_r1cs() {
+        return Err(R1CSError::CustomGates);
+    }
+    Ok(Groth16::<E>::generate_random_parameters_with_reduction(
+        circuit, rng,
+    )?)
+}
+
+/// Proves a circuit whose witness has been populated
+pub fn prove<E: Pairing, R: Rng>(
+    pk: &ProvingKey<E>,
+    circuit: CircomCircuit<E::ScalarField>,
+    rng: &mut R,
+) -> Result<Proof<E>> {
+    if !circuit.r1cs.is_pure_r1cs() {
+        return Err(R1CSError::CustomGates);
+    }
+    if circuit.witness.is_none() {
+        return Err(R1CSError::MissingWitness);
+    }
+    Ok(Groth16::<E>::create_random_proof_with_reduction(
+        circuit, pk, rng,
+    )?)
+}
+
+/// Number of public inputs, excluding the constant one, that `vk` verifies against
+pub fn expected_public_inputs<E: Pairing>(vk: &VerifyingKey<E>) -> usize {
+    vk.gamma_abc_g1.len().saturating_sub(1)
+}
+
+/// Verifies `proof` against the public inputs, excluding the constant one
+pub fn verify<E: Pairing>(
+    vk: &VerifyingKey<E>,
+    public_inputs: &[E::ScalarField],
+    proof: &Proof<E>,
+) -> Result<bool> {
+    let expected = expected_public_inputs(vk);
+    if public_inputs.len() != expected {
+        return Err(R1CSError::PublicInputCountMismatch {
+            expected,
+            actual: public_inputs.len(),
+        });
+    }
+    let pvk = prepare_verifying_key(vk);
+    Ok(Groth16::<E>::verify_proof(&pvk, proof, public_inputs)?)
+}
+
+/// Loads a verifying key, a proof and a `public.json` from disk and verifies them
+pub fn verify_from_files<E: Pairing>(
+    vk_path: impl AsRef<Path>,
+    proof_path: impl AsRef<Path>,
+    public_path: impl AsRef<Path>,
+) -> Result<bool> {
+    let vk = VerifyingKey::<E>::deserialize_compressed(BufReader::new(File::open(vk_path)?))?;
+    let proof = Proof::<E>::deserialize_compressed(BufReader::new(File::open(proof_path)?))?;
+    let public_inputs = read_public_inputs(BufReader::new(File::open(public_path)?))?;
+    verify(&vk, &public_inputs, &proof)
+}
+
+/// Verifies `proof` against the public signals of a `.wtns` file, taken in circom's
+/// wire order right after the constant one
+pub fn verify_with_witness<E: Pairing, R: Read + Seek>(
+    vk: &VerifyingKey<E>,
+    witness_reader: R,
+    proof: &Proof<E>,
+) -> Result<bool> {
+    let witness = Vec::try_from(WitnessFile::<E::ScalarField>::new(witness_reader)?)?;
+    let expected = expected_public_inputs(vk);
+    let public_inputs = witness
+        .get(1..1 + expected)
+        .ok_or(R1CSError::WitnessLengthMismatch {
+            expected: 1 + expected,
+            actual: witness.len(),
+        })?;
+    verify(vk, public_inputs, proof)
+}
+
+/// Parses a JSON array of decimal strings into field elements
+pub fn read_public_inputs<F: PrimeField, R: Read>(reader: R) -> Result<Vec<F>> {
+    let values: Vec<String> = serde_json::from_reader(reader)?;
+    values
+        .iter()
+        .map(|v| F::from_str(v).map_err(|_| R1CSError::InvalidPublicInput(v.clone())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::{Bn254, Fr};
+    use ark_serialize::CanonicalSerialize;
+    use std::fs;
+
+    use crate::R1CS;
+
+    /// The verifying key and a proof of circom's multiplier for `a = 3, b = 11`
+    fn prove_multiplier() -> (VerifyingKey<Bn254>, Proof<Bn254>) {
+        let file = File::open("./test-vectors/mycircuit.r1cs").unwrap();
+        let r1cs = R1CS::from(crate::R1CSFile::<Fr>::new(file).unwrap());
+        let witness = [1u64, 33, 3, 11].iter().map(|v| Fr::from(*v)).collect();
+        let circuit = CircomCircuit::new(r1cs, Some(witness));
+
+        let mut rng = ark_std::test_rng();
+        let pk = setup::<Bn254, _>(circuit.clone(), &mut rng).unwrap();
+        let proof = prove(&pk, circuit, &mut rng).unwrap();
+        (pk.vk, proof)
+    }
+
+    fn write_multiplier_proof(dir: &Path) {
+        let (vk, proof) = prove_multiplier();
+        let mut bytes = Vec::new();
+        vk.serialize_compressed(&mut bytes).unwrap();
+        fs::write(dir.join("vk.bin"), &bytes).unwrap();
+        bytes.clear();
+        proof.serialize_compressed(&mut bytes).unwrap();
+        fs::write(dir.join("proof.bin"), &bytes).unwrap();
+    }
+
+    fn verify_public(dir: &Path, public: &str) -> Result<bool> {
+        fs::write(dir.join("public.json"), public).unwrap();
+        verify_from_files::<Bn254>(
+            dir.join("vk.bin"),
+            dir.join("proof.bin"),
+            dir.join("public.json"),
+        )
+    }
+
+    #[test]
+    fn verifies_a_proof_from_files() {
+        let dir = tempfile::tempdir().unwrap();
+        write_multiplier_proof(dir.path());
+
+        assert!(verify_public(dir.path(), r#"["33"]"#).unwrap());
+        assert!(!verify_public(dir.path(), r#"["34"]"#).unwrap());
+        assert!(matches!(
+            verify_public(dir.path(), r#"["33", "1"]"#),
+            Err(R1CSError::PublicInputCountMismatch {
+                expected: 1,
+                actual: 2
+            })
+        ));
+        assert!(matches!(
+            verify_public(dir.path(), r#"["0x21"]"#),
+            Err(R1CSError::InvalidPublicInput(_))
+        ));
+    }
+
+    #[test]
+    fn missing_files_are_io_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            verify_public(dir.path(), r#"["33"]"#),
+            Err(R1CSError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn verify_checks_the_public_input_count() {
+        let (vk, proof) = prove_multiplier();
+        assert_eq!(expected_public_inputs(&vk), 1);
+        assert!(verify(&vk, &[Fr::from(33u64)], &proof).unwrap());
+
+        let err = verify(&vk, &[], &proof).unwrap_err();
+        assert!(matches!(
+            err,
+            R1CSError::PublicInputCountMismatch {
+                expected: 1,
+                actual: 0
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "verifying key expects 1 public inputs but 0 were provided"
+        );
+    }
+
+    fn wtns(values: &[u64]) -> ark_std::io::Cursor<Vec<u8>> {
+        let values: Vec<Fr> = values.iter().map(|v| Fr::from(*v)).collect();
+        ark_std::io::Cursor::new(crate::write_witness(Vec::new(), &values).unwrap())
+    }
+
+    #[test]
+    fn verifies_against_the_witness_it_was_proven_from() {
+        let (vk, proof) = prove_multiplier();
+        let witness = File::open("./test-vectors/mycircuit.wtns").unwrap();
+        assert!(verify_with_witness(&vk, witness, &proof).unwrap());
+        assert!(!verify_with_witness(&vk, wtns(&[1, 34, 3, 11]), &proof).unwrap());
+    }
+
+    #[test]
+    fn a_witness_without_the_public_signals_is_rejected() {
+        let (vk, proof) = prove_multiplier();
+        assert!(matches!(
+            verify_with_witness(&vk, wtns(&[1]), &proof),
+            Err(R1CSError::WitnessLengthMismatch {
+                expected: 2,
+                actual: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn custom_gates_fail_setup_and_proving() {
+        let file = File::open("./test-vectors/custom-gates.r1cs").unwrap();
+        let r1cs = R1CS::from(crate::R1CSFile::<Fr>::new(file).unwrap());
+        let witness = [1u64, 33, 3, 11].iter().map(|v| Fr::from(*v)).collect();
+        let circuit = CircomCircuit::new(r1cs, Some(witness));
+
+        let mut rng = ark_std::test_rng();
+        assert!(matches!(
+            setup::<Bn254, _>(circuit.clone(), &mut rng),
+            Err(R1CSError::CustomGates)
+        ));
+
+        // keys for the same constraints still do not let the gated circuit be proven
+        let mut pure = circuit.clone();
+        pure.r1cs.custom_gates = false;
+        let pk = setup::<Bn254, _>(pure, &mut rng).unwrap();
+        assert!(matches!(
+            prove(&pk, circuit, &mut rng),
+            Err(R1CSError::CustomGates)
+        ));
+    }
+
+    #[test]
+    fn setup_circuit_keys_match_a_circuit_without_witness() {
+        let file = File::open("./test-vectors/mycircuit.r1cs").unwrap();
+        let r1cs = R1CS::from(crate::R1CSFile::<Fr>::new(file).unwrap());
+
+        let mut rng = ark_std::test_rng();
+        let from_setup = Groth16::<Bn254>::generate_random_parameters_with_reduction(
+            crate::SetupCircuit(r1cs.clone()),
+            &mut rng,
+        )
+        .unwrap();
+        let mut rng = ark_std::test_rng();
+        let without_witness = setup::<Bn254, _>(CircomCircuit::new(r1cs, None), &mut rng).unwrap();
+        assert_eq!(from_setup, without_witness);
+    }
+}
@@ -0,0 +1,180 @@
+//! Hashes of constraint systems and public inputs
+//!
+//! [`R1CS::structural_hash`] covers the wire counts, the field modulus and every
+//! constraint after normalization, with coefficients in their canonical compressed
+//! serialization. It does not cover labels or the order of terms within a vector, so it
+//! only changes when the system itself does. The hasher is SHA-256 and will not change
+//! between releases.
+//!
+//! [`hash_public_inputs`] compresses a public input vector into one field element, as
+//! proof aggregation protocols commit to it.
+use ark_ff::{BigInteger, PrimeField};
+use sha2::{Digest, Sha256};
+
+use crate::transform::canonicalize_constraint;
+use crate::R1CS;
+
+impl<F: PrimeField> R1CS<F> {
+    /// SHA-256 digest of the normalized constraint system
+    pub fn structural_hash(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(F::MODULUS.to_bytes_le());
+        for count in [
+            self.num_inputs,
+            self.num_aux,
+            self.num_variables,
+            self.num_pub_out,
+            self.num_pub_in,
+            self.constraints.len(),
+        ] {
+            hasher.update((count as u64).to_le_bytes());
+        }
+        hasher.update([self.outputs_last as u8, self.custom_gates as u8]);
+
+        let mut bytes = Vec::new();
+        for constraint in &self.constraints {
+            let (a, b, c) = canonicalize_constraint(constraint);
+            for lc in [a, b, c] {
+                hasher.update((lc.len() as u64).to_le_bytes());
+                for (index, coeff) in lc {
+                    hasher.update((index as u64).to_le_bytes());
+                    bytes.clear();
+                    coeff
+                        .serialize_compressed(&mut bytes)
+                        .expect("serializing into a Vec cannot fail");
+                    hasher.update(&bytes);
+                }
+            }
+        }
+        hasher.finalize().into()
+    }
+}
+
+/// Hashes `inputs` into a field element with SHA-256, see [`hash_public_inputs_with`]
+pub fn hash_public_inputs<F: PrimeField>(inputs: &[F]) -> F {
+    hash_public_inputs_with::<Sha256, F>(inputs)
+}
+
+/// Hashes the number of inputs as a little-endian `u64`, followed by the compressed
+/// serialization of each input, with `D`, and reduces the digest modulo the field
+/// prime. The result is slightly biased when the digest is not much longer than the
+/// prime, which is fine for binding the inputs but not for sampling challenges.
+pub fn hash_public_inputs_with<D: Digest, F: PrimeField>(inputs: &[F]) -> F {
+    let mut hasher = D::new();
+    hasher.update((inputs.len() as u64).to_le_bytes());
+    let mut bytes = Vec::new();
+    for input in inputs {
+        bytes.clear();
+        input
+            .serialize_compressed(&mut bytes)
+            .expect("serializing into a Vec cannot fail");
+        hasher.update(&bytes);
+    }
+    F::from_le_bytes_mod_order(&hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+
+    /// `(x + 2) * y = out` over wires `[1, out, x, y]`
+    fn affine_product(a: Vec<(usize, Fr)>) -> R1CS<Fr> {
+        R1CS::for_test(
+            1,
+            0,
+            2,
+            vec![(a, vec![(3, Fr::from(1u64))], vec![(1, Fr::from(1u64))])],
+        )
+    }
+
+    fn x_plus_two() -> Vec<(usize, Fr)> {
+        vec![(2, Fr::from(1u64)), (0, Fr::from(2u64))]
+    }
+
+    #[test]
+    fn reordered_terms_hash_equal() {
+        let hash = affine_product(x_plus_two()).structural_hash();
+        let reordered = vec![(0, Fr::from(2u64)), (2, Fr::from(1u64))];
+        assert_eq!(affine_product(reordered).structural_hash(), hash);
+        let split = vec![
+            (0, Fr::from(1u64)),
+            (2, Fr::from(1u64)),
+            (0, Fr::from(1u64)),
+        ];
+        assert_eq!(affine_product(split).structural_hash(), hash);
+
+        let mut normalized = affine_product(x_plus_two());
+        normalized.normalize();
+        assert_eq!(normalized.structural_hash(), hash);
+    }
+
+    #[test]
+    fn structural_changes_change_the_hash() {
+        let hash = affine_product(x_plus_two()).structural_hash();
+        let other = vec![(2, Fr::from(1u64)), (0, Fr::from(3u64))];
+        assert_ne!(affine_product(other).structural_hash(), hash);
+
+        let mut public = affine_product(x_plus_two());
+        public.num_pub_out = 0;
+        public.num_pub_in = 1;
+        assert_ne!(public.structural_hash(), hash);
+
+        // the version a system was read from is not part of its structure
+        let mut v2 = affine_product(x_plus_two());
+        v2.version = 2;
+        assert_eq!(v2.structural_hash(), hash);
+    }
+
+    #[test]
+    fn the_hash_is_stable() {
+        let hex: String = affine_product(x_plus_two())
+            .structural_hash()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        assert_eq!(
+            hex,
+            "ff74f10b84bc90ed6fa682680badc27df9f80b3553133523f9b995c7ae5e89b3"
+        );
+    }
+
+    #[test]
+    fn public_inputs_hash_to_a_known_digest() {
+        let inputs = [33u64, 3].map(Fr::from);
+        // the count, then each input as 32 little-endian bytes
+        let mut preimage = 2u64.to_le_bytes().to_vec();
+        for value in [33u8, 3] {
+            preimage.push(value);
+            preimage.extend([0u8; 31]);
+        }
+        let expected = Fr::from_le_bytes_mod_order(&Sha256::digest(&preimage));
+        assert_eq!(hash_public_inputs(&inputs), expected);
+        assert_eq!(
+            hash_public_inputs(&inputs).to_string(),
+            "6369864399236518837794149585664995229401128346057969675525356636200493515770"
+        );
+    }
+
+    #[test]
+    fn public_inputs_hash_depends_on_order_and_hasher() {
+        let inputs = [33u64, 3].map(Fr::from);
+        assert_ne!(
+            hash_public_inputs(&inputs),
+            hash_public_inputs(&[3u64, 33].map(Fr::from))
+        );
+        assert_ne!(
+            hash_public_inputs_with::<sha2::Sha512, Fr>(&inputs),
+            hash_public_inputs(&inputs)
+        );
+        assert_eq!(
+            hash_public_inputs_with::<Sha256, Fr>(&inputs),
+            hash_public_inputs(&inputs)
+        );
+        // the count keeps a trailing zero from being dropped
+        assert_ne!(
+            hash_public_inputs(&[Fr::from(0u64)]),
+            hash_public_inputs::<Fr>(&[])
+        );
+    }
+}
@@ -0,0 +1,103 @@
+//! Parsing of circom's JSON input format, e.g. `{"a": "3", "in": ["1", "2"]}`
+//!
+//! Values may be decimal strings or JSON numbers. Arrays of any depth are flattened in
+//! row-major order, matching how circom lays out multi-dimensional signals.
+use ark_std::io::Read;
+use num_bigint::BigInt;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::error::{R1CSError, Result};
+
+pub fn parse_inputs<R: Read>(reader: R) -> Result<HashMap<String, Vec<BigInt>>> {
+    let inputs = match serde_json::from_reader(reader)? {
+        Value::Object(inputs) => inputs,
+        _ => {
+            return Err(R1CSError::InvalidInput(
+                "inputs must be a JSON object".into(),
+            ))
+        }
+    };
+
+    let mut parsed = HashMap::with_capacity(inputs.len());
+    for (name, value) in inputs {
+        let mut values = Vec::new();
+        flatten(&name, &value, &mut values)?;
+        parsed.insert(name, values);
+    }
+    Ok(parsed)
+}
+
+fn flatten(name: &str, value: &Value, out: &mut Vec<BigInt>) -> Result<()> {
+    let invalid = || R1CSError::InvalidInput(format!("invalid value for input {}", name));
+    match value {
+        Value::String(s) => out.push(BigInt::from_str(s).map_err(|_| invalid())?),
+        Value::Number(n) => out.push(BigInt::from_str(&n.to_string()).map_err(|_| invalid())?),
+        Value::Array(values) => {
+            for value in values {
+                flatten(name, value, out)?;
+            }
+        }
+        _ => return Err(invalid()),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ints(values: &[i64]) -> Vec<BigInt> {
+        values.iter().map(|v| BigInt::from(*v)).collect()
+    }
+
+    #[test]
+    fn parses_scalars() {
+        let inputs = parse_inputs(r#"{"a": "3", "b": 11, "c": "-1"}"#.as_bytes()).unwrap();
+        assert_eq!(inputs.len(), 3);
+        assert_eq!(inputs["a"], ints(&[3]));
+        assert_eq!(inputs["b"], ints(&[11]));
+        assert_eq!(inputs["c"], ints(&[-1]));
+    }
+
+    #[test]
+    fn parses_arrays() {
+        let inputs = parse_inputs(r#"{"in": ["1", "2", 3], "empty": []}"#.as_bytes()).unwrap();
+        assert_eq!(inputs["in"], ints(&[1, 2, 3]));
+        assert_eq!(inputs["empty"], ints(&[]));
+    }
+
+    #[test]
+    fn flattens_nested_arrays_row_major() {
+        let inputs =
+            parse_inputs(r#"{"m": [["1", "2", "3"], ["4", "5", "6"]]}"#.as_bytes()).unwrap();
+        assert_eq!(inputs["m"], ints(&[1, 2, 3, 4, 5, 6]));
+    }
+
+    #[test]
+    fn keeps_values_wider_than_64_bits() {
+        let p = "21888242871839275222246405745257275088548364400416034343698204186575808495617";
+        let inputs = parse_inputs(format!(r#"{{"p": "{}"}}"#, p).as_bytes()).unwrap();
+        assert_eq!(inputs["p"], vec![BigInt::from_str(p).unwrap()]);
+    }
+
+    #[test]
+    fn rejects_malformed_inputs() {
+        for json in [
+            r#"["1"]"#,
+            r#"{"a": "x"}"#,
+            r#"{"a": true}"#,
+            r#"{"a": {"b": "1"}}"#,
+        ] {
+            assert!(matches!(
+                parse_inputs(json.as_bytes()),
+                Err(R1CSError::InvalidInput(_))
+            ));
+        }
+        assert!(matches!(
+            parse_inputs("{".as_bytes()),
+            Err(R1CSError::Json(_))
+        ));
+    }
+}
@@ -0,0 +1,143 @@
+//! R1CS with identical constraint vectors stored once
+//!
+//! Every distinct A, B or C vector is kept in a single table and constraints refer to
+//! them by index. This pays off for circuits that repeat the same rows many times, such
+//! as selector patterns or unrolled loops: each repetition then costs 12 bytes instead
+//! of a full vector. For circuits whose vectors are mostly distinct it costs slightly
+//! more than [`R1CS`], and building it hashes every vector once. `benches/interned.rs`
+//! measures both cases.
+use ark_ff::PrimeField;
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use crate::{ConstraintVec, R1CSMetadata, R1CS};
+
+/// An R1CS whose constraint vectors are interned in a shared table
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct R1CSInterned<F> {
+    pub metadata: R1CSMetadata,
+    /// Distinct constraint vectors, in order of first use
+    pub vectors: Vec<ConstraintVec<F>>,
+    /// Indices into `vectors` of the A, B and C sides of each constraint
+    pub constraints: Vec<(u32, u32, u32)>,
+}
+
+impl<F: PrimeField> R1CSInterned<F> {
+    pub fn num_constraints(&self) -> usize {
+        self.constraints.len()
+    }
+
+    /// Returns the A, B and C vectors of constraint `i`
+    pub fn constraint(
+        &self,
+        i: usize,
+    ) -> (&ConstraintVec<F>, &ConstraintVec<F>, &ConstraintVec<F>) {
+        let (a, b, c) = self.constraints[i];
+        (
+            &self.vectors[a as usize],
+            &self.vectors[b as usize],
+            &self.vectors[c as usize],
+        )
+    }
+}
+
+impl<F: PrimeField> From<&R1CS<F>> for R1CSInterned<F> {
+    fn from(r1cs: &R1CS<F>) -> Self {
+        let mut vectors = Vec::new();
+        let mut ids: HashMap<&ConstraintVec<F>, u32> = HashMap::new();
+        let mut intern = |lc| {
+            *ids.entry(lc).or_insert_with(|| {
+                vectors.push(lc.clone());
+                u32::try_from(vectors.len() - 1).expect("too many distinct constraint vectors")
+            })
+        };
+        let constraints = r1cs
+            .constraints
+            .iter()
+            .map(|(a, b, c)| (intern(a), intern(b), intern(c)))
+            .collect();
+
+        R1CSInterned {
+            metadata: r1cs.metadata(),
+            vectors,
+            constraints,
+        }
+    }
+}
+
+impl<F: PrimeField> From<R1CS<F>> for R1CSInterned<F> {
+    fn from(r1cs: R1CS<F>) -> Self {
+        Self::from(&r1cs)
+    }
+}
+
+impl<F: PrimeField> From<&R1CSInterned<F>> for R1CS<F> {
+    fn from(interned: &R1CSInterned<F>) -> Self {
+        let constraints = (0..interned.num_constraints())
+            .map(|i| {
+                let (a, b, c) = interned.constraint(i);
+                (a.clone(), b.clone(), c.clone())
+            })
+            .collect();
+        interned.metadata.with_constraints(constraints)
+    }
+}
+
+impl<F: PrimeField> From<R1CSInterned<F>> for R1CS<F> {
+    fn from(interned: R1CSInterned<F>) -> Self {
+        Self::from(&interned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+
+    /// `w[i] * s = w[i + 1]` for `i` in `3..n + 3`, over wires `[1, out, s, w3, ...]`,
+    /// so every B vector is the selector `s`
+    fn selected(n: usize) -> R1CS<Fr> {
+        let one = Fr::from(1u64);
+        R1CS::for_test(
+            1,
+            0,
+            n + 2,
+            (3..n + 3)
+                .map(|i| (vec![(i, one)], vec![(2, one)], vec![(i + 1, one)]))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn stores_a_repeated_vector_once() {
+        let interned = R1CSInterned::from(&selected(4));
+        assert_eq!(interned.num_constraints(), 4);
+        // the C vector of each row is the A vector of the next
+        assert_eq!(interned.vectors.len(), 1 + 5);
+        assert!(interned.constraints.iter().all(|(_, b, _)| *b == 1));
+        assert_eq!(interned.constraints[0], (0, 1, 2));
+        assert_eq!(interned.constraints[1], (2, 1, 3));
+        assert_eq!(interned.constraint(3).2, &vec![(7, Fr::from(1u64))]);
+    }
+
+    #[test]
+    fn converts_back_to_the_plain_system() {
+        let r1cs = selected(4);
+        let interned = R1CSInterned::from(r1cs.clone());
+        assert_eq!(interned.metadata, r1cs.metadata());
+        let back = R1CS::from(&interned);
+        assert_eq!(back.metadata(), r1cs.metadata());
+        assert_eq!(back.constraints, r1cs.constraints);
+        assert_eq!(R1CS::from(interned).constraints, r1cs.constraints);
+    }
+
+    #[test]
+    fn interns_an_empty_system() {
+        let interned = R1CSInterned::from(selected(0));
+        assert!(interned.vectors.is_empty());
+        let back = R1CS::from(interned);
+        assert!(back.constraints.is_empty());
+        assert_eq!(back.metadata(), selected(0).metadata());
+    }
+}
@@ -2,12 +2,62 @@
 //!
 //! Provides bindings to Circom's R1CS, for Groth16 Proof and Witness generation in Rust.
 
+pub mod cache;
 pub mod circuit;
+pub mod diff;
+pub mod error;
+pub mod eval;
+pub mod field;
+pub mod flat;
+pub mod format;
+#[cfg(feature = "groth16")]
+pub mod groth16;
+pub mod hash;
+pub mod inputs;
+pub mod interned;
+pub mod matrices;
+pub mod mtx;
 pub mod r1cs_reader;
+pub mod r1cs_writer;
+#[cfg(feature = "groth16")]
+pub mod snarkjs;
+pub mod solve;
+pub mod stats;
+pub mod sym;
+#[cfg(test)]
+pub(crate) mod test_fields;
+pub mod transform;
+pub mod visit;
+pub mod witness;
+pub mod witness_writer;
 
-pub use crate::r1cs_reader::{R1CSFile, R1CS};
+pub use crate::r1cs_reader::{
+    ConstraintStream, Matrix, R1CSFile, R1CSMetadata, R1CSReaderOptions, R1CS,
+};
+pub use crate::r1cs_writer::R1CSWriter;
 
-pub use crate::circuit::CircomCircuit;
+pub use crate::cache::WitnessCache;
+#[cfg(feature = "tar")]
+pub use crate::circuit::load_from_tar;
+pub use crate::circuit::{
+    load_circuit, variable_to_wire, wire_to_variable, CircomCircuit, ExplicitOne, SetupCircuit,
+    SynthesisTiming, VerifierCircuit,
+};
+pub use crate::diff::{ConstraintChange, R1CSDiff};
+pub use crate::error::R1CSError;
+pub use crate::field::{AnyR1CS, FieldKind};
+pub use crate::flat::{FlatCircuit, R1CSFlat};
+pub use crate::hash::{hash_public_inputs, hash_public_inputs_with};
+pub use crate::interned::R1CSInterned;
+pub use crate::stats::{CoeffStats, MatrixStats, R1CSSummary};
+pub use crate::sym::{SymFile, Symbol};
+pub use crate::transform::{apply_wire_map, canonicalize_constraint, OptimizeReport};
+pub use crate::visit::ConstraintVisitor;
+pub use crate::witness::{same_public_signals, WitnessFile, WitnessReader, WitnessReaderOptions};
+pub use crate::witness_writer::write_witness;
 
 pub type Constraints<F> = (ConstraintVec<F>, ConstraintVec<F>, ConstraintVec<F>);
 pub type ConstraintVec<F> = Vec<(usize, F)>;
+/// Wire indices paired with the little-endian coefficient bytes as stored on disk
+pub type RawConstraints = (RawConstraintVec, RawConstraintVec, RawConstraintVec);
+pub type RawConstraintVec = Vec<(usize, Vec<u8>)>;
@@ -0,0 +1,170 @@
+//! Conversion of an R1CS into arkworks' `ConstraintMatrices`
+use ark_ff::PrimeField;
+use ark_relations::r1cs::{ConstraintMatrices, Matrix};
+
+use crate::transform::normalize_vec;
+use crate::{ConstraintVec, R1CS};
+
+/// Builds a matrix row the way ark-relations does: sorted by column, with duplicate
+/// columns summed and zero coefficients dropped. Circom wire `i` maps to column `i`,
+/// since instance variables come first and witness variables follow them.
+pub(crate) fn make_row<F: PrimeField>(lc: &ConstraintVec<F>) -> Vec<(F, usize)> {
+    normalize_vec(lc)
+        .into_iter()
+        .map(|(index, coeff)| (coeff, index))
+        .collect()
+}
+
+impl<F: PrimeField> R1CS<F> {
+    /// The constraint matrices `generate_constraints` followed by `to_matrices` produces
+    pub fn matrices(&self) -> ConstraintMatrices<F> {
+        self.matrices_with_one(true)
+    }
+
+    /// Like [`Self::matrices`], but without the constant one column when `include_one`
+    /// is unset. Every other column then moves down by one, and terms on the constant
+    /// wire are left out, see [`Self::constant_terms`] to recover them.
+    pub fn matrices_with_one(&self, include_one: bool) -> ConstraintMatrices<F> {
+        let row = |lc| {
+            let mut row = make_row(lc);
+            if !include_one {
+                row.retain(|(_, index)| *index != 0);
+                row.iter_mut().for_each(|(_, index)| *index -= 1);
+            }
+            row
+        };
+
+        let mut a: Matrix<F> = Vec::with_capacity(self.constraints.len());
+        let mut b: Matrix<F> = Vec::with_capacity(self.constraints.len());
+        let mut c: Matrix<F> = Vec::with_capacity(self.constraints.len());
+        for constraint in &self.constraints {
+            a.push(row(&constraint.0));
+            b.push(row(&constraint.1));
+            c.push(row(&constraint.2));
+        }
+
+        let num_non_zero = |m: &Matrix<F>| m.iter().map(|row| row.len()).sum();
+        ConstraintMatrices {
+            num_instance_variables: self.num_inputs - !include_one as usize,
+            num_witness_variables: self.num_aux,
+            num_constraints: self.constraints.len(),
+            a_num_non_zero: num_non_zero(&a),
+            b_num_non_zero: num_non_zero(&b),
+            c_num_non_zero: num_non_zero(&c),
+            a,
+            b,
+            c,
+        }
+    }
+
+    /// Evaluation domain size used by Groth16 setup and proving: ark-groth16 adds one
+    /// constraint per instance variable, and the radix-2 domain rounds that up to a
+    /// power of two
+    pub fn qap_domain_size(&self) -> usize {
+        (self.constraints.len() + self.num_inputs).next_power_of_two()
+    }
+}
+
+impl<F: PrimeField> From<R1CS<F>> for ConstraintMatrices<F> {
+    fn from(r1cs: R1CS<F>) -> Self {
+        r1cs.matrices()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+
+    use crate::CircomCircuit;
+
+    /// `(2 * in + 2) * s = t` and `t * 1 = out + 3`, over wires `[1, out, in, s, t]`,
+    /// with `in` written as two terms and the constant as the last term
+    fn doubled() -> R1CS<Fr> {
+        let one = Fr::from(1u64);
+        R1CS::for_test(
+            1,
+            1,
+            2,
+            vec![
+                (
+                    vec![(2, one), (2, one), (0, Fr::from(2u64))],
+                    vec![(3, one)],
+                    vec![(4, one)],
+                ),
+                (
+                    vec![(4, one)],
+                    vec![(0, one)],
+                    vec![(1, one), (0, Fr::from(3u64))],
+                ),
+            ],
+        )
+    }
+
+    /// What synthesizing the circuit and asking arkworks for its matrices yields
+    fn synthesized(r1cs: R1CS<Fr>) -> ConstraintMatrices<Fr> {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        CircomCircuit::new(r1cs, None)
+            .generate_constraints(cs.clone())
+            .unwrap();
+        cs.finalize();
+        cs.to_matrices().unwrap()
+    }
+
+    #[test]
+    fn matches_to_matrices() {
+        let matrices = ConstraintMatrices::from(doubled());
+        assert_eq!(matrices, synthesized(doubled()));
+        assert_eq!(matrices.num_instance_variables, 3);
+        assert_eq!(matrices.num_witness_variables, 2);
+        assert_eq!(
+            matrices.a[0],
+            vec![(Fr::from(2u64), 0), (Fr::from(2u64), 2)]
+        );
+    }
+
+    #[test]
+    fn qap_domain_size_counts_constraints_and_instance_variables() {
+        // 2 constraints plus 3 instance variables round up to 8
+        assert_eq!(doubled().qap_domain_size(), 8);
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        CircomCircuit::new(doubled(), None)
+            .generate_constraints(cs.clone())
+            .unwrap();
+        assert_eq!(
+            doubled().qap_domain_size(),
+            (cs.num_constraints() + cs.num_instance_variables()).next_power_of_two()
+        );
+
+        // exactly a power of two is not rounded further
+        let mut r1cs = doubled();
+        r1cs.constraints.truncate(1);
+        assert_eq!(r1cs.qap_domain_size(), 4);
+    }
+
+    #[test]
+    fn matrices_without_the_one_column() {
+        let matrices = doubled().matrices_with_one(false);
+        assert_eq!(matrices.num_instance_variables, 2);
+        assert_eq!(matrices.a[0], vec![(Fr::from(2u64), 1)]);
+        assert_eq!(matrices.b[1], vec![]);
+        assert_eq!(matrices.c[1], vec![(Fr::from(1u64), 0)]);
+        assert_eq!(matrices.b_num_non_zero, 1);
+    }
+
+    #[test]
+    fn matrices_with_the_one_column_are_the_default() {
+        assert_eq!(doubled().matrices_with_one(true), doubled().matrices());
+        let with = doubled().matrices_with_one(true);
+        let without = doubled().matrices_with_one(false);
+        assert_eq!(
+            with.num_instance_variables,
+            without.num_instance_variables + 1
+        );
+        // every column but the constant moves down by one
+        assert_eq!(with.c[1], vec![(Fr::from(3u64), 0), (Fr::from(1u64), 1)]);
+        assert_eq!(without.c[1], vec![(Fr::from(1u64), 0)]);
+    }
+}
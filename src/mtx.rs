@@ -0,0 +1,134 @@
+//! Matrix Market export of the constraint matrices
+//!
+//! Matrices are written in the coordinate format, with one row per constraint, one
+//! column per wire and 1-based indices. Coefficients are the canonical representatives
+//! in `[0, p)` printed in decimal, so they usually exceed 64 bits; readers have to parse
+//! them as arbitrary-precision integers.
+use ark_ff::PrimeField;
+use ark_std::io::Write;
+
+use crate::error::Result;
+use crate::matrices::make_row;
+use crate::{Matrix, R1CS};
+
+impl<F: PrimeField> R1CS<F> {
+    /// Writes the A, B or C matrix in Matrix Market coordinate format. Rows are
+    /// normalized as in [`R1CS::matrices`].
+    pub fn write_matrix_market<W: Write>(&self, which: Matrix, mut writer: W) -> Result<()> {
+        let rows: Vec<_> = self
+            .constraints
+            .iter()
+            .map(|(a, b, c)| match which {
+                Matrix::A => make_row(a),
+                Matrix::B => make_row(b),
+                Matrix::C => make_row(c),
+            })
+            .collect();
+        let num_non_zero: usize = rows.iter().map(|row| row.len()).sum();
+
+        writeln!(writer, "%%MatrixMarket matrix coordinate integer general")?;
+        writeln!(writer, "% {:?} matrix of a circom R1CS", which)?;
+        writeln!(
+            writer,
+            "{} {} {}",
+            rows.len(),
+            self.num_variables,
+            num_non_zero
+        )?;
+        for (i, row) in rows.iter().enumerate() {
+            for (coeff, index) in row {
+                writeln!(writer, "{} {} {}", i + 1, index + 1, coeff.into_bigint())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+
+    /// `(x + x - 1) * y = out` and `y * y = x`, over wires `[1, out, x, y]`
+    fn system() -> R1CS<Fr> {
+        let one = Fr::from(1u64);
+        R1CS::for_test(
+            1,
+            0,
+            2,
+            vec![
+                (
+                    vec![(2, one), (2, one), (0, -one)],
+                    vec![(3, one)],
+                    vec![(1, one)],
+                ),
+                (vec![(3, one)], vec![(3, one)], vec![(2, one)]),
+            ],
+        )
+    }
+
+    fn mtx(which: Matrix) -> String {
+        let mut out = Vec::new();
+        system().write_matrix_market(which, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    /// Row, column and value of a Matrix Market entry
+    type Entry = (usize, usize, String);
+
+    /// Parses coordinate Matrix Market text into its size line and entries
+    fn parse(text: &str) -> ((usize, usize, usize), Vec<Entry>) {
+        let mut lines = text.lines();
+        assert_eq!(
+            lines.next(),
+            Some("%%MatrixMarket matrix coordinate integer general")
+        );
+        let mut lines = lines.filter(|line| !line.starts_with('%'));
+        let size: Vec<usize> = lines
+            .next()
+            .unwrap()
+            .split(' ')
+            .map(|n| n.parse().unwrap())
+            .collect();
+        let entries = lines
+            .map(|line| {
+                let fields: Vec<&str> = line.split(' ').collect();
+                assert_eq!(fields.len(), 3);
+                (
+                    fields[0].parse().unwrap(),
+                    fields[1].parse().unwrap(),
+                    fields[2].to_string(),
+                )
+            })
+            .collect();
+        ((size[0], size[1], size[2]), entries)
+    }
+
+    #[test]
+    fn writes_valid_coordinate_format() {
+        for which in [Matrix::A, Matrix::B, Matrix::C] {
+            let ((rows, cols, nnz), entries) = parse(&mtx(which));
+            assert_eq!((rows, cols), (2, 4));
+            assert_eq!(entries.len(), nnz);
+            assert!(entries
+                .iter()
+                .all(|(i, j, _)| (1..=rows).contains(i) && (1..=cols).contains(j)));
+        }
+    }
+
+    #[test]
+    fn merges_terms_and_prints_canonical_coefficients() {
+        let ((_, _, nnz), entries) = parse(&mtx(Matrix::A));
+        assert_eq!(nnz, 3);
+        let minus_one = (-Fr::from(1u64)).into_bigint().to_string();
+        assert_eq!(
+            entries,
+            vec![
+                (1, 1, minus_one),
+                (1, 3, "2".to_string()),
+                (2, 4, "1".to_string())
+            ]
+        );
+        assert!(mtx(Matrix::A).contains("% A matrix of a circom R1CS"));
+    }
+}
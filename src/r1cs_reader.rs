@@ -1,45 +1,381 @@
 //! R1CS circom file reader
 //! Copied from <https://github.com/poma/zkutil>
 //! Spec: <https://github.com/iden3/r1csfile/blob/master/doc/r1cs_bin_format.md>
-use ark_ff::PrimeField;
+//!
+//! Files compiled for any of circom's built-in primes (`bn128`, `bls12381`, `goldilocks`,
+//! `grumpkin`, `secq256r1`, `pallas`, `vesta`, as well as M31) can be read, as long as
+//! `F` is the matching prime field. The prime declared in the header is checked against
+//! the value of `F::MODULUS`, regardless of zero padding. `field_size` can be any
+//! number of bytes that holds the modulus: coefficients are read as `field_size` byte
+//! strings, without assuming a multiple of 4 or 8. A header whose `field_size` is too
+//! small for the modulus fails with [`R1CSError::FieldSizeMismatch`], and one declaring
+//! another prime with [`R1CSError::PrimeMismatch`].
+//!
+//! Version 2 files are accepted as well. Their custom gate sections are not parsed,
+//! only recorded, see [`R1CS::is_pure_r1cs`].
+//! Headers of very old files, which lack the private input count, are accepted too: every
+//! wire that is neither the constant nor public is then counted as a private input.
+//!
+//! With the `tracing` feature, the section scan, header parse and constraint loop each
+//! run in a `debug` span that reports the counts read; span close events carry timings.
+use ark_ff::{BigInteger, PrimeField};
 use byteorder::{LittleEndian, ReadBytesExt};
-use std::io::{Error, ErrorKind};
+use num_bigint::BigUint;
 
-use ark_serialize::{SerializationError, SerializationError::IoError};
-use ark_std::io::{Read, Seek, SeekFrom};
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, SerializationError, Valid, Validate,
+};
+use ark_std::io::{self, Cursor, Read, Seek, SeekFrom, Take, Write};
 
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::marker::PhantomData;
+use std::ops::Index;
 
-type IoResult<T> = Result<T, SerializationError>;
-
-use crate::{ConstraintVec, Constraints};
+use crate::error::{R1CSError, Result};
+use crate::transform::normalize_constraint;
+use crate::{ConstraintVec, Constraints, RawConstraintVec, RawConstraints};
 
 #[derive(Clone, Debug)]
 pub struct R1CS<F> {
     pub num_inputs: usize,
     pub num_aux: usize,
     pub num_variables: usize,
+    pub num_pub_out: usize,
+    pub num_pub_in: usize,
+    /// Whether public inputs come before public outputs, the reverse of circom's layout
+    pub outputs_last: bool,
+    /// Whether the file declared custom gates, whose constraints are not part of
+    /// `constraints`
+    pub custom_gates: bool,
+    /// Version of the file format the system was read from, 1 or 2
+    pub version: u32,
     pub constraints: Vec<Constraints<F>>,
 }
 
+/// Everything an [`R1CS`] records besides its constraints, shared by the other
+/// representations of a system such as [`crate::R1CSFlat`]
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct R1CSMetadata {
+    pub num_inputs: usize,
+    pub num_aux: usize,
+    pub num_variables: usize,
+    pub num_pub_out: usize,
+    pub num_pub_in: usize,
+    pub outputs_last: bool,
+    pub custom_gates: bool,
+    pub version: u32,
+}
+
+impl R1CSMetadata {
+    /// The system with these counts and `constraints`
+    pub fn with_constraints<F>(&self, constraints: Vec<Constraints<F>>) -> R1CS<F> {
+        R1CS {
+            num_inputs: self.num_inputs,
+            num_aux: self.num_aux,
+            num_variables: self.num_variables,
+            num_pub_out: self.num_pub_out,
+            num_pub_in: self.num_pub_in,
+            outputs_last: self.outputs_last,
+            custom_gates: self.custom_gates,
+            version: self.version,
+            constraints,
+        }
+    }
+}
+
+impl<F> R1CS<F> {
+    /// Every field of `self` except the constraints
+    pub fn metadata(&self) -> R1CSMetadata {
+        R1CSMetadata {
+            num_inputs: self.num_inputs,
+            num_aux: self.num_aux,
+            num_variables: self.num_variables,
+            num_pub_out: self.num_pub_out,
+            num_pub_in: self.num_pub_in,
+            outputs_last: self.outputs_last,
+            custom_gates: self.custom_gates,
+            version: self.version,
+        }
+    }
+
+    /// Copies every field of `self` except the constraints
+    pub(crate) fn with_constraints<G>(&self, constraints: Vec<Constraints<G>>) -> R1CS<G> {
+        self.metadata().with_constraints(constraints)
+    }
+}
+
+#[cfg(test)]
+impl<F> R1CS<F> {
+    /// A version 1 system over circom's wire layout: the constant one, `num_pub_out`
+    /// outputs, `num_pub_in` public inputs and `num_aux` private wires
+    pub(crate) fn for_test(
+        num_pub_out: usize,
+        num_pub_in: usize,
+        num_aux: usize,
+        constraints: Vec<Constraints<F>>,
+    ) -> Self {
+        let num_inputs = 1 + num_pub_out + num_pub_in;
+        R1CSMetadata {
+            num_inputs,
+            num_aux,
+            num_variables: num_inputs + num_aux,
+            num_pub_out,
+            num_pub_in,
+            outputs_last: false,
+            custom_gates: false,
+            version: 1,
+        }
+        .with_constraints(constraints)
+    }
+}
+
+impl<F: PrimeField> R1CS<F> {
+    /// Whether every constraint of the circuit is quadratic, so that it can be proven
+    /// with Groth16. False for files that use custom gates.
+    pub fn is_pure_r1cs(&self) -> bool {
+        !self.custom_gates
+    }
+
+    /// Version of the file format the system was read from. Version 2 files may declare
+    /// custom gates, see [`Self::is_pure_r1cs`].
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn has_public_outputs(&self) -> bool {
+        self.num_pub_out > 0
+    }
+
+    pub fn has_public_inputs(&self) -> bool {
+        self.num_pub_in > 0
+    }
+
+    /// Number of public signals, outputs and inputs, excluding the constant one wire
+    pub fn num_public(&self) -> usize {
+        self.num_pub_out + self.num_pub_in
+    }
+
+    /// Number of instance variables as counted by arkworks, including the constant one
+    /// wire. Same as `num_inputs`.
+    pub fn num_instance_variables(&self) -> usize {
+        self.num_inputs
+    }
+
+    /// Number of public signals as counted by snarkjs, excluding the constant one wire
+    pub fn num_public_signals(&self) -> usize {
+        self.num_inputs.saturating_sub(1)
+    }
+
+    /// Number of wires that are not public, including intermediate signals
+    pub fn num_private(&self) -> usize {
+        self.num_aux
+    }
+
+    /// Length a witness for this system must have, one value per wire
+    pub fn expected_witness_len(&self) -> usize {
+        self.num_variables
+    }
+
+    /// Returns constraint `index`, or `None` if it is out of range
+    pub fn get(&self, index: usize) -> Option<&Constraints<F>> {
+        self.constraints.get(index)
+    }
+
+    /// Largest wire index referenced by any constraint, or 0 if there are no terms.
+    /// Anything not below `num_variables` means the system is corrupt.
+    pub fn max_wire_index(&self) -> usize {
+        self.constraints
+            .iter()
+            .flat_map(|(a, b, c)| a.iter().chain(b).chain(c))
+            .map(|(index, _)| *index)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Indices of the constraints that reference `wire` in A, B or C
+    pub fn constraints_touching(&self, wire: usize) -> Vec<usize> {
+        self.constraints
+            .iter()
+            .enumerate()
+            .filter(|(_, (a, b, c))| a.iter().chain(b).chain(c).any(|(index, _)| *index == wire))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Whether constraint `i`, which must exist, is linear: its B side only has terms on
+    /// the constant one wire, so it states `A * b = C` for a constant `b`
+    pub fn is_linear_constraint(&self, i: usize) -> bool {
+        self.constraints[i].1.iter().all(|(index, _)| *index == 0)
+    }
+
+    /// Indices of the [linear](Self::is_linear_constraint) constraints
+    pub fn linear_constraint_indices(&self) -> Vec<usize> {
+        (0..self.constraints.len())
+            .filter(|i| self.is_linear_constraint(*i))
+            .collect()
+    }
+
+    /// Runs the consistency checks an untrusted system should pass before use: the
+    /// wire counts add up, the public counts match `num_inputs`, and every constraint
+    /// vector is no longer than the number of wires and only references existing wires.
+    /// Returns the first violation found.
+    pub fn validate(&self) -> Result<()> {
+        if self.num_inputs == 0 || self.num_inputs + self.num_aux != self.num_variables {
+            return Err(R1CSError::InconsistentCounts {
+                num_inputs: self.num_inputs,
+                num_aux: self.num_aux,
+                num_variables: self.num_variables,
+            });
+        }
+        if 1 + self.num_pub_out + self.num_pub_in != self.num_inputs {
+            return Err(R1CSError::InconsistentPublicCounts {
+                num_inputs: self.num_inputs,
+                num_pub_out: self.num_pub_out,
+                num_pub_in: self.num_pub_in,
+            });
+        }
+
+        let n_wires = self.num_variables;
+        for (constraint, (a, b, c)) in self.constraints.iter().enumerate() {
+            for (lc, matrix) in [(a, Matrix::A), (b, Matrix::B), (c, Matrix::C)] {
+                if lc.len() > n_wires {
+                    return Err(R1CSError::ConstraintVecTooLong {
+                        constraint,
+                        matrix,
+                        len: lc.len(),
+                        n_wires,
+                    });
+                }
+                if let Some((wire, _)) = lc.iter().find(|(index, _)| *index >= n_wires) {
+                    return Err(R1CSError::WireOutOfRange {
+                        constraint,
+                        matrix,
+                        wire: *wire,
+                        n_wires,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the `len` constraints starting at `start`
+    pub fn constraint_range(&self, start: usize, len: usize) -> Result<&[Constraints<F>]> {
+        match start.checked_add(len) {
+            Some(end) if end <= self.constraints.len() => Ok(&self.constraints[start..end]),
+            _ => Err(R1CSError::ConstraintRangeOutOfBounds {
+                start,
+                end: start.saturating_add(len),
+                len: self.constraints.len(),
+            }),
+        }
+    }
+}
+
+impl<F> Index<usize> for R1CS<F> {
+    type Output = Constraints<F>;
+
+    fn index(&self, index: usize) -> &Constraints<F> {
+        &self.constraints[index]
+    }
+}
+
 impl<F: PrimeField> From<R1CSFile<F>> for R1CS<F> {
     fn from(file: R1CSFile<F>) -> Self {
-        let num_inputs = (1 + file.header.n_pub_in + file.header.n_pub_out) as usize;
+        // Headers read from a file have enough wires, but the fields are public: a larger
+        // input count is left for `validate` to reject instead of underflowing here
+        let num_inputs = 1 + file.header.n_pub_in as usize + file.header.n_pub_out as usize;
         let num_variables = file.header.n_wires as usize;
-        let num_aux = num_variables - num_inputs;
+        let num_aux = num_variables.saturating_sub(num_inputs);
         R1CS {
             num_aux,
             num_inputs,
             num_variables,
+            num_pub_out: file.header.n_pub_out as usize,
+            num_pub_in: file.header.n_pub_in as usize,
+            outputs_last: false,
+            custom_gates: file.has_custom_gates(),
+            version: file.version,
             constraints: file.constraints,
         }
     }
 }
 
+impl<F: PrimeField> CanonicalSerialize for R1CS<F> {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.metadata().serialize_with_mode(&mut writer, compress)?;
+        self.constraints.serialize_with_mode(&mut writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.metadata().serialized_size(compress) + self.constraints.serialized_size(compress)
+    }
+}
+
+impl<F: PrimeField> Valid for R1CS<F> {
+    fn check(&self) -> Result<(), SerializationError> {
+        if self.num_inputs + self.num_aux != self.num_variables {
+            return Err(SerializationError::InvalidData);
+        }
+        self.constraints.check()
+    }
+}
+
+impl<F: PrimeField> CanonicalDeserialize for R1CS<F> {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let metadata = R1CSMetadata::deserialize_with_mode(&mut reader, compress, validate)?;
+        let constraints = Vec::deserialize_with_mode(&mut reader, compress, Validate::No)?;
+        let r1cs = metadata.with_constraints(constraints);
+        if let Validate::Yes = validate {
+            r1cs.check()?;
+        }
+        Ok(r1cs)
+    }
+}
+
 pub struct R1CSFile<F: PrimeField> {
     pub version: u32,
     pub header: Header,
     pub constraints: Vec<Constraints<F>>,
+    /// Label id of each wire, if the file has a wire-to-label map section
+    pub wire_to_label: Option<Vec<u64>>,
+    /// On-disk coefficient bytes, if `retain_coefficient_bytes` was set
+    pub raw_constraints: Option<Vec<RawConstraints>>,
+    section_table: Vec<Section>,
+}
+
+/// Optional checks performed while reading an R1CS file
+#[derive(Clone, Copy, Debug, Default)]
+pub struct R1CSReaderOptions {
+    /// Check that every label in the wire-to-label map is below `n_labels`
+    pub check_labels: bool,
+    /// Keep the `field_size` bytes of every coefficient as read from the file
+    pub retain_coefficient_bytes: bool,
+    /// Sort the terms of every constraint vector by wire, summing repeated wires and
+    /// dropping zero coefficients as [`R1CS::normalize`] does. Retained coefficient
+    /// bytes keep the order of the file.
+    pub sort_terms: bool,
+    /// Length of the file in bytes. Sections must lie within it; when unset, the end
+    /// of the stream is used instead.
+    pub length: Option<u64>,
+    /// Fail if anything follows the last section, up to `length` or the end of the
+    /// stream. Trailing data may mean the file was concatenated with another or
+    /// corrupted.
+    pub reject_trailing_bytes: bool,
+    /// Largest number of constraints the header may declare
+    pub max_constraints: Option<u32>,
+    /// Largest number of wires the header may declare
+    pub max_wires: Option<u32>,
+    /// Largest size of any single section, in bytes
+    pub max_section_bytes: Option<u64>,
 }
 
 impl<F: PrimeField> R1CSFile<F> {
@@ -48,81 +384,555 @@ impl<F: PrimeField> R1CSFile<F> {
     /// ```rust,ignore
     /// let reader = BufReader::new(Cursor::new(&data[..]));
     /// ```
-    pub fn new<R: Read + Seek>(mut reader: R) -> IoResult<R1CSFile<F>> {
-        let mut magic = [0u8; 4];
-        reader.read_exact(&mut magic)?;
-        if magic != [0x72, 0x31, 0x63, 0x73] {
-            return Err(IoError(Error::new(
-                ErrorKind::InvalidData,
-                "Invalid magic number",
-            )));
-        }
-
-        let version = reader.read_u32::<LittleEndian>()?;
-        if version != 1 {
-            return Err(IoError(Error::new(
-                ErrorKind::InvalidData,
-                "Unsupported version",
-            )));
-        }
-
-        let num_sections = reader.read_u32::<LittleEndian>()?;
-
-        // todo: handle sec_size correctly
-        // section type -> file offset
-        let mut sec_offsets = HashMap::<u32, u64>::new();
-        let mut sec_sizes = HashMap::<u32, u64>::new();
-
-        // get file offset of each section
-        for _ in 0..num_sections {
-            let sec_type = reader.read_u32::<LittleEndian>()?;
-            let sec_size = reader.read_u64::<LittleEndian>()?;
-            let offset = reader.stream_position()?;
-            sec_offsets.insert(sec_type, offset);
-            sec_sizes.insert(sec_type, sec_size);
-            reader.seek(SeekFrom::Current(sec_size as i64))?;
-        }
-
-        let header_type = 1;
-        let constraint_type = 2;
-
-        let header_offset = sec_offsets.get(&header_type).ok_or_else(|| {
-            Error::new(
-                ErrorKind::InvalidData,
-                "No section offset for header type found",
-            )
-        });
+    pub fn new<R: Read + Seek>(reader: R) -> Result<R1CSFile<F>> {
+        Self::new_with_options(reader, R1CSReaderOptions::default())
+    }
 
-        reader.seek(SeekFrom::Start(*header_offset?))?;
+    /// Reads from a source that does not implement `Seek`, such as a pipe or socket.
+    ///
+    /// The whole stream is buffered in memory before parsing, so peak memory use is
+    /// the size of the file plus the parsed constraints.
+    pub fn new_buffered<R: Read>(mut reader: R) -> Result<R1CSFile<F>> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Self::new(Cursor::new(data))
+    }
 
-        let header_size = sec_sizes.get(&header_type).ok_or_else(|| {
-            Error::new(
-                ErrorKind::InvalidData,
-                "No section size for header type found",
-            )
-        });
+    /// Reads from a [`bytes::Buf`]. When `buf` is a `Bytes` it is parsed in place,
+    /// other implementations are copied into one contiguous buffer first.
+    #[cfg(feature = "bytes")]
+    pub fn from_buf<B: bytes::Buf>(mut buf: B) -> Result<R1CSFile<F>> {
+        let data = buf.copy_to_bytes(buf.remaining());
+        Self::new(Cursor::new(data))
+    }
 
-        let header = Header::new(&mut reader, *header_size?)?;
+    /// Reads a file of `length` bytes, rejecting files whose sections extend past it
+    pub fn new_with_length<R: Read + Seek>(reader: R, length: u64) -> Result<R1CSFile<F>> {
+        Self::new_with_options(
+            reader,
+            R1CSReaderOptions {
+                length: Some(length),
+                ..Default::default()
+            },
+        )
+    }
 
-        let constraint_offset = sec_offsets.get(&constraint_type).ok_or_else(|| {
-            Error::new(
-                ErrorKind::InvalidData,
-                "No section offset for constraint type found",
-            )
-        });
+    /// Reads a file embedded in a larger stream, starting at byte `start`.
+    ///
+    /// Section offsets are tracked as absolute stream positions, so no further
+    /// adjustment is needed once the reader is positioned.
+    pub fn new_at_offset<R: Read + Seek>(mut reader: R, start: u64) -> Result<R1CSFile<F>> {
+        reader.seek(SeekFrom::Start(start))?;
+        Self::new(reader)
+    }
 
-        reader.seek(SeekFrom::Start(*constraint_offset?))?;
+    /// Reads a file whose constraint section is split across several shards.
+    ///
+    /// The header and the wire-to-label map come from `header_reader`, a regular
+    /// `.r1cs` file whose own constraints, if any, are ignored. Each shard holds whole
+    /// encoded constraints without section framing, and is read from its current
+    /// position to its end. Together the shards must hold exactly `n_constraints`
+    /// constraints.
+    pub fn from_shards<H: Read + Seek, R: Read + Seek>(
+        header_reader: H,
+        constraint_readers: Vec<R>,
+    ) -> Result<R1CSFile<F>> {
+        Self::from_shards_with_options(
+            header_reader,
+            constraint_readers,
+            R1CSReaderOptions::default(),
+        )
+    }
+
+    /// Like [`Self::from_shards`], with `options` applied as [`Self::new_with_options`]
+    /// does. Each shard counts as a section for `max_section_bytes`, while `length` and
+    /// `reject_trailing_bytes` only apply to `header_reader`.
+    pub fn from_shards_with_options<H: Read + Seek, R: Read + Seek>(
+        mut header_reader: H,
+        constraint_readers: Vec<R>,
+        options: R1CSReaderOptions,
+    ) -> Result<R1CSFile<F>> {
+        let (version, section_table, header) = read_prelude::<_, F>(&mut header_reader, &options)?;
+
+        let expected = header.n_constraints as usize;
+        let mut shards = Vec::with_capacity(constraint_readers.len());
+        for mut shard in constraint_readers {
+            let start = shard.stream_position()?;
+            let end = shard.seek(SeekFrom::End(0))?;
+            shard.seek(SeekFrom::Start(start))?;
+            check_limit("bytes in a section", end - start, options.max_section_bytes)?;
+            shards.push(shard.take(end - start));
+        }
+        // Every constraint takes at least 12 bytes, so the shards bound the count
+        let total: u64 = shards.iter().map(Take::limit).sum();
+        let capacity = expected.min((total / 12) as usize);
+        let mut constraints = Vec::with_capacity(capacity);
+        let mut raw_constraints = if options.retain_coefficient_bytes {
+            Some(Vec::with_capacity(capacity))
+        } else {
+            None
+        };
+        for mut shard in shards {
+            while shard.limit() > 0 {
+                let index = constraints.len();
+                let mut raw_constraint =
+                    raw_constraints.as_ref().map(|_| RawConstraints::default());
+                constraints.push(read_constraint(
+                    &mut shard,
+                    &header,
+                    index,
+                    raw_constraint.as_mut(),
+                    &F::from_le_bytes_mod_order,
+                )?);
+                if let (Some(raw), Some(raw_constraint)) =
+                    (raw_constraints.as_mut(), raw_constraint)
+                {
+                    raw.push(raw_constraint);
+                }
+            }
+        }
+        if constraints.len() != expected {
+            return Err(R1CSError::ConstraintCountMismatch {
+                expected,
+                actual: constraints.len(),
+            });
+        }
+        if options.sort_terms {
+            constraints.iter_mut().for_each(normalize_constraint);
+        }
+
+        let wire_to_label =
+            read_wire_to_label_section(&mut header_reader, &section_table, &header)?;
+        if options.check_labels {
+            if let Some(labels) = &wire_to_label {
+                check_labels(labels, &header)?;
+            }
+        }
+        Ok(R1CSFile {
+            version,
+            header,
+            constraints,
+            wire_to_label,
+            raw_constraints,
+            section_table,
+        })
+    }
 
-        let constraints = read_constraints::<&mut R, F>(&mut reader, &header)?;
+    pub fn new_with_options<R: Read + Seek>(
+        reader: R,
+        options: R1CSReaderOptions,
+    ) -> Result<R1CSFile<F>> {
+        Self::new_with_decoder(reader, options, F::from_le_bytes_mod_order)
+    }
+
+    /// Like [`Self::new_with_options`], calling `progress` with the fraction of
+    /// constraints read so far, from 0 to 1, as the constraint section is read
+    pub fn new_with_progress<R: Read + Seek>(
+        reader: R,
+        options: R1CSReaderOptions,
+        progress: &mut dyn FnMut(f64),
+    ) -> Result<R1CSFile<F>> {
+        Self::read(reader, options, F::from_le_bytes_mod_order, Some(progress))
+    }
+
+    /// Reads a file, turning every `field_size` byte coefficient into a field element
+    /// with `decode` rather than as a little-endian integer reduced modulo the prime.
+    /// This is meant for tools that wrote coefficients in another encoding, such as
+    /// big-endian or Montgomery form. The header prime is still checked against `F`.
+    pub fn new_with_decoder<R: Read + Seek, D: Fn(&[u8]) -> F>(
+        reader: R,
+        options: R1CSReaderOptions,
+        decode: D,
+    ) -> Result<R1CSFile<F>> {
+        Self::read(reader, options, decode, None)
+    }
+
+    fn read<R: Read + Seek, D: Fn(&[u8]) -> F>(
+        mut reader: R,
+        options: R1CSReaderOptions,
+        decode: D,
+        progress: Option<&mut dyn FnMut(f64)>,
+    ) -> Result<R1CSFile<F>> {
+        let (version, section_table, header) = read_prelude::<_, F>(&mut reader, &options)?;
+
+        let mut raw_constraints = if options.retain_coefficient_bytes {
+            Some(Vec::new())
+        } else {
+            None
+        };
+        let mut constraints = match find_section(&section_table, CONSTRAINT_TYPE) {
+            Some((offset, size)) => {
+                reader.seek(SeekFrom::Start(offset))?;
+                let mut section = (&mut reader).take(size);
+                let constraints = read_constraints(
+                    &mut section,
+                    &header,
+                    raw_constraints.as_mut(),
+                    progress,
+                    &decode,
+                )?;
+                if section.limit() != 0 {
+                    return Err(R1CSError::InvalidSectionSize {
+                        section_type: CONSTRAINT_TYPE,
+                        size,
+                    });
+                }
+                constraints
+            }
+            // A circuit without constraints may omit the section altogether
+            None if header.n_constraints == 0 => Vec::new(),
+            None => return Err(R1CSError::MissingSection(CONSTRAINT_TYPE)),
+        };
+        if options.sort_terms {
+            constraints.iter_mut().for_each(normalize_constraint);
+        }
+
+        let wire_to_label = read_wire_to_label_section(&mut reader, &section_table, &header)?;
+        if options.check_labels {
+            if let Some(labels) = &wire_to_label {
+                check_labels(labels, &header)?;
+            }
+        }
+
+        Ok(R1CSFile {
+            version,
+            header,
+            constraints,
+            wire_to_label,
+            raw_constraints,
+            section_table,
+        })
+    }
 
+    /// Builds a file from its parts, for systems that were not read from a source. The
+    /// header must declare enough wires for its signals and as many constraints as
+    /// there are, and the wire-to-label map, if any, must hold one label per wire. The
+    /// section table of such a file is empty.
+    pub fn from_parts(
+        version: u32,
+        header: Header,
+        constraints: Vec<Constraints<F>>,
+        wire_to_label: Option<Vec<u64>>,
+    ) -> Result<R1CSFile<F>> {
+        if version != 1 && version != 2 {
+            return Err(R1CSError::UnsupportedVersion(version));
+        }
+        if header.prime_size.len() != header.field_size as usize {
+            return Err(R1CSError::InvalidFieldSize(header.field_size));
+        }
+        header.check_wire_counts()?;
+        if constraints.len() != header.n_constraints as usize {
+            return Err(R1CSError::ConstraintCountMismatch {
+                expected: header.n_constraints as usize,
+                actual: constraints.len(),
+            });
+        }
+        if let Some(labels) = &wire_to_label {
+            if labels.len() != header.n_wires as usize {
+                return Err(R1CSError::InvalidSectionSize {
+                    section_type: WIRE_TO_LABEL_TYPE,
+                    size: labels.len() as u64 * 8,
+                });
+            }
+        }
         Ok(R1CSFile {
             version,
             header,
             constraints,
+            wire_to_label,
+            raw_constraints: None,
+            section_table: Vec::new(),
+        })
+    }
+
+    /// Reads only the constraints at `indices`, returned in that order. The constraint
+    /// section is still scanned from its start up to the largest index, but other
+    /// constraints are skipped without decoding and never held in memory.
+    pub fn read_constraints_subset<R: Read + Seek>(
+        reader: R,
+        indices: &[usize],
+    ) -> Result<Vec<Constraints<F>>> {
+        Self::read_constraints_subset_with_options(reader, indices, R1CSReaderOptions::default())
+    }
+
+    /// Like [`Self::read_constraints_subset`], with the limits, length checks and
+    /// `sort_terms` of `options` applied. Coefficient bytes are not retained and labels
+    /// are not read.
+    pub fn read_constraints_subset_with_options<R: Read + Seek>(
+        mut reader: R,
+        indices: &[usize],
+        options: R1CSReaderOptions,
+    ) -> Result<Vec<Constraints<F>>> {
+        let (_, section_table, header) = read_prelude::<_, F>(&mut reader, &options)?;
+        let len = header.n_constraints as usize;
+        if let Some(index) = indices.iter().find(|index| **index >= len) {
+            return Err(R1CSError::ConstraintRangeOutOfBounds {
+                start: *index,
+                end: *index + 1,
+                len,
+            });
+        }
+
+        let wanted: BTreeSet<usize> = indices.iter().copied().collect();
+        let mut found = HashMap::with_capacity(wanted.len());
+        if let Some(last) = wanted.iter().next_back() {
+            let size = seek_to_section(&mut reader, &section_table, CONSTRAINT_TYPE)?;
+            let mut reader = reader.take(size);
+            for index in 0..=*last {
+                if wanted.contains(&index) {
+                    let mut constraint = read_constraint(
+                        &mut reader,
+                        &header,
+                        index,
+                        None,
+                        &F::from_le_bytes_mod_order,
+                    )?;
+                    if options.sort_terms {
+                        normalize_constraint(&mut constraint);
+                    }
+                    found.insert(index, constraint);
+                } else {
+                    skip_constraint(&mut reader, &header, index)?;
+                }
+            }
+        }
+        Ok(indices.iter().map(|index| found[index].clone()).collect())
+    }
+
+    /// Sections found in the file as `(type, offset, size)`, in file order. Empty for
+    /// files built with [`Self::from_parts`].
+    pub fn section_table(&self) -> &[(u32, u64, u64)] {
+        &self.section_table
+    }
+
+    /// Whether the file has a custom gates list or application section. Those are
+    /// skipped by the reader, so the constraints alone do not describe the circuit.
+    pub fn has_custom_gates(&self) -> bool {
+        self.section_table
+            .iter()
+            .any(|(ty, _, _)| *ty == CUSTOM_GATES_LIST_TYPE || *ty == CUSTOM_GATES_USE_TYPE)
+    }
+}
+
+/// `(type, offset, size)` of a section
+type Section = (u32, u64, u64);
+
+const HEADER_TYPE: u32 = 1;
+const CONSTRAINT_TYPE: u32 = 2;
+const WIRE_TO_LABEL_TYPE: u32 = 3;
+const CUSTOM_GATES_LIST_TYPE: u32 = 4;
+const CUSTOM_GATES_USE_TYPE: u32 = 5;
+
+/// Number of constraints read between two progress reports
+const PROGRESS_INTERVAL: usize = 1024;
+
+/// Reads the magic number, version and section table, leaving the reader after the
+/// last section. Sections are returned as `(type, offset, size)` in file order.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+fn read_section_table<R: Read + Seek>(
+    mut reader: R,
+    options: &R1CSReaderOptions,
+) -> Result<(u32, Vec<Section>)> {
+    let start = reader.stream_position()?;
+    let end = match options.length {
+        // A length that takes the end past `u64::MAX` cannot describe the stream
+        Some(length) => start.checked_add(length).ok_or(R1CSError::LimitExceeded {
+            what: "bytes after the start of the stream",
+            value: length,
+            limit: u64::MAX - start,
+        })?,
+        None => {
+            let end = reader.seek(SeekFrom::End(0))?;
+            reader.seek(SeekFrom::Start(start))?;
+            end
+        }
+    };
+
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != [0x72, 0x31, 0x63, 0x73] {
+        return Err(R1CSError::InvalidMagic(magic));
+    }
+
+    let version = reader.read_u32::<LittleEndian>()?;
+    // Version 2 only adds the custom gate sections
+    if version != 1 && version != 2 {
+        return Err(R1CSError::UnsupportedVersion(version));
+    }
+
+    let num_sections = reader.read_u32::<LittleEndian>()?;
+
+    // get file offset of each section
+    let mut section_table = Vec::new();
+    for section_index in 0..num_sections {
+        let truncated = |err: io::Error| match err.kind() {
+            io::ErrorKind::UnexpectedEof => R1CSError::TruncatedFile { section_index },
+            _ => R1CSError::Io(err),
+        };
+        let sec_type = reader.read_u32::<LittleEndian>().map_err(truncated)?;
+        let sec_size = reader.read_u64::<LittleEndian>().map_err(truncated)?;
+        check_limit("bytes in a section", sec_size, options.max_section_bytes)?;
+        let offset = reader.stream_position()?;
+        let section_end = offset.saturating_add(sec_size);
+        if section_end > end {
+            return Err(R1CSError::SectionOutOfBounds {
+                end: section_end - start,
+                len: end - start,
+            });
+        }
+        section_table.push((sec_type, offset, sec_size));
+        reader.seek(SeekFrom::Current(sec_size as i64))?;
+    }
+
+    if options.reject_trailing_bytes {
+        let position = reader.stream_position()?;
+        if position != end {
+            return Err(R1CSError::TrailingBytes(end - position));
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    tracing::debug!(version, num_sections, "read section table");
+    Ok((version, section_table))
+}
+
+/// Reads the section table and the header, and checks the header against the limits of
+/// `options`
+fn read_prelude<R: Read + Seek, F: PrimeField>(
+    mut reader: R,
+    options: &R1CSReaderOptions,
+) -> Result<(u32, Vec<Section>, Header)> {
+    let (version, section_table) = read_section_table(&mut reader, options)?;
+    let header = read_header::<_, F>(&mut reader, &section_table)?;
+    check_limit(
+        "constraints",
+        header.n_constraints.into(),
+        options.max_constraints,
+    )?;
+    check_limit("wires", header.n_wires.into(), options.max_wires)?;
+    Ok((version, section_table, header))
+}
+
+/// Fails if `value` is above `limit`, when a limit is set
+fn check_limit<L: Into<u64>>(what: &'static str, value: u64, limit: Option<L>) -> Result<()> {
+    match limit.map(Into::into) {
+        Some(limit) if value > limit => Err(R1CSError::LimitExceeded { what, value, limit }),
+        _ => Ok(()),
+    }
+}
+
+/// Offset and size of the last section of type `section_type`
+fn find_section(section_table: &[Section], section_type: u32) -> Option<(u64, u64)> {
+    section_table
+        .iter()
+        .rev()
+        .find(|(ty, _, _)| *ty == section_type)
+        .map(|(_, offset, size)| (*offset, *size))
+}
+
+/// Seeks to the start of a section, returning its size
+fn seek_to_section<R: Seek>(
+    mut reader: R,
+    section_table: &[Section],
+    section_type: u32,
+) -> Result<u64> {
+    let (offset, size) =
+        find_section(section_table, section_type).ok_or(R1CSError::MissingSection(section_type))?;
+    reader.seek(SeekFrom::Start(offset))?;
+    Ok(size)
+}
+
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+fn read_header<R: Read + Seek, F: PrimeField>(
+    mut reader: R,
+    section_table: &[Section],
+) -> Result<Header> {
+    let size = seek_to_section(&mut reader, section_table, HEADER_TYPE)?;
+    let header = Header::new::<_, F>(&mut reader, size)?;
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        field_size = header.field_size,
+        n_wires = header.n_wires,
+        n_constraints = header.n_constraints,
+        "read header"
+    );
+    Ok(header)
+}
+
+/// Reads the constraints of a file one at a time, holding at most one in memory
+pub struct ConstraintStream<R, F> {
+    /// Limited to the bytes left in the constraint section
+    reader: Take<R>,
+    header: Header,
+    next: u32,
+    sort_terms: bool,
+    _field: PhantomData<F>,
+}
+
+impl<R: Read + Seek, F: PrimeField> ConstraintStream<R, F> {
+    pub fn new(reader: R) -> Result<Self> {
+        Self::new_with_options(reader, R1CSReaderOptions::default())
+    }
+
+    /// Like [`Self::new`], with the limits, length checks and `sort_terms` of `options`
+    /// applied. Coefficient bytes are not retained and labels are not read.
+    pub fn new_with_options(mut reader: R, options: R1CSReaderOptions) -> Result<Self> {
+        let (_, section_table, header) = read_prelude::<_, F>(&mut reader, &options)?;
+        let size = match find_section(&section_table, CONSTRAINT_TYPE) {
+            Some((offset, size)) => {
+                reader.seek(SeekFrom::Start(offset))?;
+                size
+            }
+            // A circuit without constraints may omit the section altogether
+            None if header.n_constraints == 0 => 0,
+            None => return Err(R1CSError::MissingSection(CONSTRAINT_TYPE)),
+        };
+        Ok(ConstraintStream {
+            reader: reader.take(size),
+            header,
+            next: 0,
+            sort_terms: options.sort_terms,
+            _field: PhantomData,
         })
     }
 }
 
+impl<R, F> ConstraintStream<R, F> {
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+}
+
+impl<R: Read, F: PrimeField> Iterator for ConstraintStream<R, F> {
+    type Item = Result<Constraints<F>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next == self.header.n_constraints {
+            return None;
+        }
+        let index = self.next as usize;
+        self.next += 1;
+        let constraint = read_constraint(
+            &mut self.reader,
+            &self.header,
+            index,
+            None,
+            &F::from_le_bytes_mod_order,
+        );
+        Some(constraint.map(|mut constraint| {
+            if self.sort_terms {
+                normalize_constraint(&mut constraint);
+            }
+            constraint
+        }))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.header.n_constraints - self.next) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
 pub struct Header {
     pub field_size: u32,
     pub prime_size: Vec<u8>,
@@ -135,68 +945,1906 @@ pub struct Header {
 }
 
 impl Header {
-    fn new<R: Read>(mut reader: R, size: u64) -> IoResult<Header> {
+    /// The prime declared in the header, or `None` if it does not fit in `B`
+    pub fn prime_bigint<B: BigInteger>(&self) -> Option<B> {
+        B::try_from(BigUint::from_bytes_le(&self.prime_size)).ok()
+    }
+
+    fn new<R: Read, F: PrimeField>(mut reader: R, size: u64) -> Result<Header> {
         let field_size = reader.read_u32::<LittleEndian>()?;
-        if field_size != 4 {
-            return Err(IoError(Error::new(
-                ErrorKind::InvalidData,
-                "This parser only supports 4-byte fields",
-            )));
+        if field_size == 0 {
+            return Err(R1CSError::InvalidFieldSize(field_size));
         }
 
-        if size != 32 + field_size as u64 {
-            return Err(IoError(Error::new(
-                ErrorKind::InvalidData,
-                "Invalid header section size",
-            )));
-        }
+        // Very old files have no private input count, making the header 4 bytes shorter
+        let legacy = match size.checked_sub(field_size as u64) {
+            Some(32) => false,
+            Some(28) => true,
+            _ => {
+                return Err(R1CSError::InvalidSectionSize {
+                    section_type: 1,
+                    size,
+                })
+            }
+        };
 
         let mut prime_size = vec![0u8; field_size as usize];
         reader.read_exact(&mut prime_size)?;
 
-        if prime_size != hex::decode("ffffff7f").unwrap() {
-            return Err(IoError(Error::new(
-                ErrorKind::InvalidData,
-                "This parser only supports m31",
-            )));
+        // Compare numerically, so that zero padding in either encoding does not matter
+        let found = BigUint::from_bytes_le(&prime_size);
+        let expected: BigUint = F::MODULUS.into();
+        if found != expected {
+            // A prefix of the modulus means the prime was cut short, not that it differs
+            let needed = expected.bits().div_ceil(8) as u32;
+            if field_size < needed && found == &expected % (BigUint::from(1u8) << (8 * field_size))
+            {
+                return Err(R1CSError::FieldSizeMismatch { field_size, needed });
+            }
+            return Err(R1CSError::PrimeMismatch {
+                found: found.to_string(),
+                expected: expected.to_string(),
+            });
         }
 
-        Ok(Header {
+        let n_wires = reader.read_u32::<LittleEndian>()?;
+        let n_pub_out = reader.read_u32::<LittleEndian>()?;
+        let n_pub_in = reader.read_u32::<LittleEndian>()?;
+        let n_prv_in = if legacy {
+            // Every wire that is neither the constant nor public
+            n_wires
+                .saturating_sub(1)
+                .saturating_sub(n_pub_in)
+                .saturating_sub(n_pub_out)
+        } else {
+            reader.read_u32::<LittleEndian>()?
+        };
+        let header = Header {
             field_size,
             prime_size,
-            n_wires: reader.read_u32::<LittleEndian>()?,
-            n_pub_out: reader.read_u32::<LittleEndian>()?,
-            n_pub_in: reader.read_u32::<LittleEndian>()?,
-            n_prv_in: reader.read_u32::<LittleEndian>()?,
+            n_wires,
+            n_pub_out,
+            n_pub_in,
+            n_prv_in,
             n_labels: reader.read_u64::<LittleEndian>()?,
             n_constraints: reader.read_u32::<LittleEndian>()?,
-        })
+        };
+        header.check_wire_counts()?;
+        Ok(header)
     }
+
+    /// Fails unless there are enough wires for the constant, the public signals and the
+    /// private inputs
+    fn check_wire_counts(&self) -> Result<()> {
+        let needed = 1 + self.n_pub_out as u64 + self.n_pub_in as u64 + self.n_prv_in as u64;
+        if (self.n_wires as u64) < needed {
+            return Err(R1CSError::InconsistentWireCounts {
+                n_wires: self.n_wires,
+                n_pub_out: self.n_pub_out,
+                n_pub_in: self.n_pub_in,
+                n_prv_in: self.n_prv_in,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// One side of a constraint `A * B = C`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Matrix {
+    A,
+    B,
+    C,
 }
 
-fn read_constraint_vec<R: Read, F: PrimeField>(mut reader: R) -> IoResult<ConstraintVec<F>> {
-    let n_vec = reader.read_u32::<LittleEndian>()? as usize;
+fn read_constraint_vec<R: Read, F: PrimeField, D: Fn(&[u8]) -> F>(
+    reader: &mut Take<R>,
+    header: &Header,
+    (constraint, matrix): (usize, Matrix),
+    mut raw: Option<&mut RawConstraintVec>,
+    decode: &D,
+) -> Result<ConstraintVec<F>> {
+    let n_wires = header.n_wires as usize;
+    let n_vec = read_u32_in_section(reader, constraint)? as usize;
+    // A vector can reference each wire at most once, anything longer is corrupt
+    if n_vec > n_wires {
+        return Err(R1CSError::ConstraintVecTooLong {
+            constraint,
+            matrix,
+            len: n_vec,
+            n_wires,
+        });
+    }
+    // Nor can it extend past the end of the section
+    let term_size = 4 + header.field_size as u64;
+    if n_vec as u64 * term_size > reader.limit() {
+        return Err(R1CSError::TruncatedConstraint { constraint });
+    }
     let mut vec = Vec::with_capacity(n_vec);
+    let mut coeff = vec![0u8; header.field_size as usize];
     for _ in 0..n_vec {
         let idx = reader.read_u32::<LittleEndian>()? as usize;
-        let v = reader.read_u32::<LittleEndian>()?;
-        vec.push((idx, F::from(v)));
+        if idx >= n_wires {
+            return Err(R1CSError::WireOutOfRange {
+                constraint,
+                matrix,
+                wire: idx,
+                n_wires,
+            });
+        }
+        reader.read_exact(&mut coeff)?;
+        vec.push((idx, decode(&coeff)));
+        if let Some(raw) = raw.as_mut() {
+            raw.push((idx, coeff.clone()));
+        }
     }
     Ok(vec)
 }
 
-fn read_constraints<R: Read, F: PrimeField>(
-    mut reader: R,
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(n_constraints = header.n_constraints))
+)]
+fn read_constraints<R: Read, F: PrimeField, D: Fn(&[u8]) -> F>(
+    reader: &mut Take<R>,
     header: &Header,
-) -> IoResult<Vec<Constraints<F>>> {
-    // todo check section size
-    let mut vec = Vec::with_capacity(header.n_constraints as usize);
-    for _ in 0..header.n_constraints {
-        vec.push((
-            read_constraint_vec::<&mut R, F>(&mut reader)?,
-            read_constraint_vec::<&mut R, F>(&mut reader)?,
-            read_constraint_vec::<&mut R, F>(&mut reader)?,
-        ));
+    mut raw: Option<&mut Vec<RawConstraints>>,
+    mut progress: Option<&mut dyn FnMut(f64)>,
+    decode: &D,
+) -> Result<Vec<Constraints<F>>> {
+    // Every constraint holds at least its three vector lengths, so a section too small
+    // for the declared count is rejected before allocating for it
+    if header.n_constraints as u64 * 12 > reader.limit() {
+        return Err(R1CSError::InvalidSectionSize {
+            section_type: CONSTRAINT_TYPE,
+            size: reader.limit(),
+        });
+    }
+    let n_constraints = header.n_constraints as usize;
+    let mut vec = Vec::with_capacity(n_constraints);
+    if let Some(raw) = raw.as_mut() {
+        raw.reserve(n_constraints);
+    }
+    for index in 0..n_constraints {
+        let mut raw_constraint = raw.as_ref().map(|_| RawConstraints::default());
+        vec.push(read_constraint(
+            reader,
+            header,
+            index,
+            raw_constraint.as_mut(),
+            decode,
+        )?);
+        if let (Some(raw), Some(raw_constraint)) = (raw.as_mut(), raw_constraint) {
+            raw.push(raw_constraint);
+        }
+        if let Some(progress) = progress.as_mut() {
+            if (index + 1) % PROGRESS_INTERVAL == 0 || index + 1 == n_constraints {
+                progress((index + 1) as f64 / n_constraints as f64);
+            }
+        }
     }
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        num_terms = vec
+            .iter()
+            .map(|(a, b, c)| a.len() + b.len() + c.len())
+            .sum::<usize>(),
+        "read constraints"
+    );
     Ok(vec)
 }
+
+fn read_constraint<R: Read, F: PrimeField, D: Fn(&[u8]) -> F>(
+    reader: &mut Take<R>,
+    header: &Header,
+    index: usize,
+    mut raw: Option<&mut RawConstraints>,
+    decode: &D,
+) -> Result<Constraints<F>> {
+    Ok((
+        read_constraint_vec(
+            reader,
+            header,
+            (index, Matrix::A),
+            raw.as_mut().map(|raw| &mut raw.0),
+            decode,
+        )?,
+        read_constraint_vec(
+            reader,
+            header,
+            (index, Matrix::B),
+            raw.as_mut().map(|raw| &mut raw.1),
+            decode,
+        )?,
+        read_constraint_vec(
+            reader,
+            header,
+            (index, Matrix::C),
+            raw.as_mut().map(|raw| &mut raw.2),
+            decode,
+        )?,
+    ))
+}
+
+/// Moves past constraint `index` without decoding its coefficients
+fn skip_constraint<R: Read + Seek>(
+    reader: &mut Take<R>,
+    header: &Header,
+    index: usize,
+) -> Result<()> {
+    let term_size = 4 + header.field_size as u64;
+    for _ in 0..3 {
+        let len = read_u32_in_section(reader, index)? as u64 * term_size;
+        let limit = reader.limit();
+        if len > limit {
+            return Err(R1CSError::TruncatedConstraint { constraint: index });
+        }
+        reader.get_mut().seek(SeekFrom::Current(len as i64))?;
+        reader.set_limit(limit - len);
+    }
+    Ok(())
+}
+
+/// Reads a `u32` of constraint `constraint`, failing cleanly at the end of the section
+fn read_u32_in_section<R: Read>(reader: &mut Take<R>, constraint: usize) -> Result<u32> {
+    reader
+        .read_u32::<LittleEndian>()
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::UnexpectedEof => R1CSError::TruncatedConstraint { constraint },
+            _ => R1CSError::Io(err),
+        })
+}
+
+/// Reads the wire-to-label map if the file has one
+fn read_wire_to_label_section<R: Read + Seek>(
+    mut reader: R,
+    section_table: &[Section],
+    header: &Header,
+) -> Result<Option<Vec<u64>>> {
+    match find_section(section_table, WIRE_TO_LABEL_TYPE) {
+        Some((offset, size)) => {
+            reader.seek(SeekFrom::Start(offset))?;
+            Ok(Some(read_wire_to_label(&mut reader, header, size)?))
+        }
+        None => Ok(None),
+    }
+}
+
+fn read_wire_to_label<R: Read>(mut reader: R, header: &Header, size: u64) -> Result<Vec<u64>> {
+    if size != header.n_wires as u64 * 8 {
+        return Err(R1CSError::InvalidSectionSize {
+            section_type: 3,
+            size,
+        });
+    }
+
+    let mut labels = Vec::with_capacity(header.n_wires as usize);
+    for _ in 0..header.n_wires {
+        labels.push(reader.read_u64::<LittleEndian>()?);
+    }
+    Ok(labels)
+}
+
+fn check_labels(labels: &[u64], header: &Header) -> Result<()> {
+    match labels.iter().max() {
+        Some(max) if *max >= header.n_labels => Err(R1CSError::LabelOutOfRange {
+            label: *max,
+            n_labels: header.n_labels,
+        }),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    use crate::test_fields::{Goldilocks, Pallas, Secq256r1, Vesta};
+
+    /// A header over the BN254 scalar field
+    fn header_section(
+        n_wires: u32,
+        (n_pub_out, n_pub_in, n_prv_in): (u32, u32, u32),
+        n_labels: u64,
+        n_constraints: u32,
+    ) -> Vec<u8> {
+        let mut bytes = 32u32.to_le_bytes().to_vec();
+        bytes.extend(ark_bn254::Fr::MODULUS.to_bytes_le());
+        for count in [n_wires, n_pub_out, n_pub_in, n_prv_in] {
+            bytes.extend(count.to_le_bytes());
+        }
+        bytes.extend(n_labels.to_le_bytes());
+        bytes.extend(n_constraints.to_le_bytes());
+        bytes
+    }
+
+    /// Encodes `(wire, coefficient)` terms of A, B and C for each constraint
+    fn constraint_section(constraints: &[[&[(u32, u64)]; 3]]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for lc in constraints.iter().flatten() {
+            bytes.extend((lc.len() as u32).to_le_bytes());
+            for (wire, coeff) in lc.iter() {
+                bytes.extend(wire.to_le_bytes());
+                bytes.extend(coeff.to_le_bytes());
+                bytes.extend([0u8; 24]);
+            }
+        }
+        bytes
+    }
+
+    fn label_section(labels: &[u64]) -> Vec<u8> {
+        labels
+            .iter()
+            .flat_map(|label| label.to_le_bytes())
+            .collect()
+    }
+
+    /// Frames `sections` as a file of format `version`
+    fn file_bytes(version: u32, sections: &[(u32, Vec<u8>)]) -> Vec<u8> {
+        let mut bytes = b"r1cs".to_vec();
+        bytes.extend(version.to_le_bytes());
+        bytes.extend((sections.len() as u32).to_le_bytes());
+        for (ty, section) in sections {
+            bytes.extend(ty.to_le_bytes());
+            bytes.extend((section.len() as u64).to_le_bytes());
+            bytes.extend(section);
+        }
+        bytes
+    }
+
+    /// circom's multiplier `c <== a * b` over BN254, declaring `n_labels` labels
+    fn multiplier(n_labels: u64, labels: &[u64]) -> Vec<u8> {
+        file_bytes(
+            1,
+            &[
+                (1, header_section(4, (1, 0, 2), n_labels, 1)),
+                (2, constraint_section(&[[&[(2, 1)], &[(3, 1)], &[(1, 1)]]])),
+                (3, label_section(labels)),
+            ],
+        )
+    }
+
+    fn with_labels_checked() -> R1CSReaderOptions {
+        R1CSReaderOptions {
+            check_labels: true,
+            ..Default::default()
+        }
+    }
+
+    fn prime_file(prime: &str) -> File {
+        File::open(format!("./test-vectors/primes/{}.r1cs", prime)).unwrap()
+    }
+
+    /// Every prime fixture holds circom's multiplier, `c <== a * b`, with `c` on wire 1
+    fn check_multiplier<F: PrimeField>(prime: &str, field_size: u32) {
+        let file = R1CSFile::<F>::new(prime_file(prime)).unwrap();
+        assert_eq!(file.header.field_size, field_size);
+        assert_eq!(file.header.prime_bigint::<F::BigInt>(), Some(F::MODULUS));
+
+        let r1cs = R1CS::from(file);
+        assert_eq!(
+            r1cs.constraints,
+            vec![(
+                vec![(2, -F::one())],
+                vec![(3, F::one())],
+                vec![(1, -F::one())]
+            )]
+        );
+        let witness: Vec<F> = [1u64, 33, 3, 11].iter().map(|v| F::from(*v)).collect();
+        assert_eq!(r1cs.check_witness(&witness), Ok(()));
+    }
+
+    #[test]
+    fn reads_bn128() {
+        check_multiplier::<ark_bn254::Fr>("bn128", 32);
+    }
+
+    #[test]
+    fn reads_bls12381() {
+        check_multiplier::<ark_bls12_381::Fr>("bls12381", 32);
+    }
+
+    #[test]
+    fn reads_goldilocks() {
+        check_multiplier::<Goldilocks>("goldilocks", 8);
+    }
+
+    #[test]
+    fn reads_grumpkin() {
+        check_multiplier::<ark_bn254::Fq>("grumpkin", 32);
+    }
+
+    #[test]
+    fn reads_secq256r1() {
+        check_multiplier::<Secq256r1>("secq256r1", 32);
+    }
+
+    #[test]
+    fn reads_pallas() {
+        check_multiplier::<Pallas>("pallas", 32);
+    }
+
+    #[test]
+    fn reads_vesta() {
+        check_multiplier::<Vesta>("vesta", 32);
+    }
+
+    #[test]
+    fn reads_m31() {
+        check_multiplier::<crate::field::M31>("m31", 4);
+    }
+
+    #[test]
+    fn rejects_another_prime() {
+        for prime in ["bls12381", "grumpkin", "goldilocks", "pallas"] {
+            assert!(matches!(
+                R1CSFile::<ark_bn254::Fr>::new(prime_file(prime)),
+                Err(R1CSError::PrimeMismatch { .. })
+            ));
+        }
+        assert!(matches!(
+            R1CSFile::<Vesta>::new(prime_file("pallas")),
+            Err(R1CSError::PrimeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn reads_the_label_map() {
+        let file = R1CSFile::<ark_bn254::Fr>::new_with_options(
+            Cursor::new(multiplier(4, &[0, 1, 2, 3])),
+            with_labels_checked(),
+        )
+        .unwrap();
+        assert_eq!(file.header.n_labels, 4);
+        assert_eq!(file.wire_to_label, Some(vec![0, 1, 2, 3]));
+    }
+
+    #[test]
+    fn rejects_labels_past_n_labels_when_checked() {
+        let bytes = multiplier(3, &[0, 1, 2, 3]);
+        assert!(matches!(
+            R1CSFile::<ark_bn254::Fr>::new_with_options(Cursor::new(&bytes), with_labels_checked()),
+            Err(R1CSError::LabelOutOfRange {
+                label: 3,
+                n_labels: 3
+            })
+        ));
+        // without the flag the count is not cross-checked
+        let file = R1CSFile::<ark_bn254::Fr>::new(Cursor::new(&bytes)).unwrap();
+        assert_eq!(file.wire_to_label, Some(vec![0, 1, 2, 3]));
+    }
+
+    #[test]
+    fn rejects_a_label_map_of_the_wrong_size() {
+        assert!(matches!(
+            R1CSFile::<ark_bn254::Fr>::new(Cursor::new(multiplier(4, &[0, 1, 2]))),
+            Err(R1CSError::InvalidSectionSize {
+                section_type: 3,
+                size: 24
+            })
+        ));
+    }
+
+    #[test]
+    fn keeps_the_section_table() {
+        let file =
+            R1CSFile::<ark_bn254::Fr>::new(Cursor::new(multiplier(4, &[0, 1, 2, 3]))).unwrap();
+        // 12 bytes of preamble, then each section after its 12 byte type and size
+        assert_eq!(
+            file.section_table(),
+            &[(1, 24, 64), (2, 100, 120), (3, 232, 32)]
+        );
+    }
+
+    #[test]
+    fn section_table_follows_file_order() {
+        let file =
+            R1CSFile::<ark_bn254::Fr>::new(File::open("./test-vectors/mycircuit.r1cs").unwrap())
+                .unwrap();
+        let types: Vec<u32> = file.section_table().iter().map(|(ty, _, _)| *ty).collect();
+        assert_eq!(types.len(), 3);
+        assert!(types.contains(&1) && types.contains(&2) && types.contains(&3));
+        let header = file
+            .section_table()
+            .iter()
+            .find(|(ty, _, _)| *ty == 1)
+            .unwrap();
+        assert_eq!(header.2, 64);
+        for window in file.section_table().windows(2) {
+            assert!(window[0].1 + window[0].2 <= window[1].1);
+        }
+    }
+
+    #[test]
+    fn built_files_have_no_section_table() {
+        let file =
+            R1CSFile::<ark_bn254::Fr>::new(Cursor::new(multiplier(4, &[0, 1, 2, 3]))).unwrap();
+        let built = R1CSFile::from_parts(1, file.header, file.constraints, None).unwrap();
+        assert!(built.section_table().is_empty());
+    }
+
+    #[test]
+    fn from_parts_checks_the_parts_agree() {
+        let parts = || {
+            let file =
+                R1CSFile::<ark_bn254::Fr>::new(Cursor::new(multiplier(4, &[0, 1, 2, 3]))).unwrap();
+            (file.header, file.constraints)
+        };
+
+        let (header, constraints) = parts();
+        assert!(matches!(
+            R1CSFile::from_parts(3, header, constraints, None),
+            Err(R1CSError::UnsupportedVersion(3))
+        ));
+        let (header, _) = parts();
+        assert!(matches!(
+            R1CSFile::<ark_bn254::Fr>::from_parts(1, header, Vec::new(), None),
+            Err(R1CSError::ConstraintCountMismatch {
+                expected: 1,
+                actual: 0
+            })
+        ));
+        let (header, constraints) = parts();
+        assert!(matches!(
+            R1CSFile::from_parts(1, header, constraints, Some(vec![0, 1])),
+            Err(R1CSError::InvalidSectionSize {
+                section_type: 3,
+                ..
+            })
+        ));
+        let (mut header, constraints) = parts();
+        header.n_prv_in = 3;
+        assert!(matches!(
+            R1CSFile::from_parts(1, header, constraints, None),
+            Err(R1CSError::InconsistentWireCounts { n_wires: 4, .. })
+        ));
+        let (mut header, constraints) = parts();
+        header.prime_size.pop();
+        assert!(matches!(
+            R1CSFile::from_parts(1, header, constraints, None),
+            Err(R1CSError::InvalidFieldSize(32))
+        ));
+    }
+
+    #[test]
+    fn serialization_round_trips() {
+        let file = File::open("./test-vectors/mycircuit.r1cs").unwrap();
+        let r1cs = R1CS::from(R1CSFile::<ark_bn254::Fr>::new(file).unwrap());
+
+        for compress in [Compress::Yes, Compress::No] {
+            let mut bytes = Vec::new();
+            r1cs.serialize_with_mode(&mut bytes, compress).unwrap();
+            assert_eq!(bytes.len(), r1cs.serialized_size(compress));
+
+            let back =
+                R1CS::<ark_bn254::Fr>::deserialize_with_mode(&bytes[..], compress, Validate::Yes)
+                    .unwrap();
+            assert_eq!(back.metadata(), r1cs.metadata());
+            assert_eq!(back.constraints, r1cs.constraints);
+        }
+    }
+
+    #[test]
+    fn deserialization_validates_the_counts() {
+        let file = File::open("./test-vectors/mycircuit.r1cs").unwrap();
+        let mut r1cs = R1CS::from(R1CSFile::<ark_bn254::Fr>::new(file).unwrap());
+        r1cs.num_aux += 1;
+        let mut bytes = Vec::new();
+        r1cs.serialize_compressed(&mut bytes).unwrap();
+
+        assert!(matches!(
+            R1CS::<ark_bn254::Fr>::deserialize_compressed(&bytes[..]),
+            Err(SerializationError::InvalidData)
+        ));
+        let unchecked =
+            R1CS::<ark_bn254::Fr>::deserialize_compressed_unchecked(&bytes[..]).unwrap();
+        assert_eq!(unchecked.num_aux, 3);
+    }
+
+    /// `n` constraints `w[i] * 1 = w[i + 1]`, copying the constant along a chain of wires
+    fn copies(n: usize) -> R1CS<ark_bn254::Fr> {
+        let one = ark_bn254::Fr::from(1u64);
+        R1CS::for_test(
+            0,
+            0,
+            n,
+            (0..n)
+                .map(|i| (vec![(i, one)], vec![(0, one)], vec![(i + 1, one)]))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn constraint_range_returns_the_window() {
+        let r1cs = copies(10);
+        let window = r1cs.constraint_range(3, 4).unwrap();
+        assert_eq!(window.len(), 4);
+        assert_eq!(window[0], r1cs[3]);
+        assert_eq!(window[3], r1cs[6]);
+        assert!(r1cs.constraint_range(10, 0).unwrap().is_empty());
+        assert_eq!(r1cs.constraint_range(0, 10).unwrap().len(), 10);
+    }
+
+    #[test]
+    fn constraint_range_rejects_windows_past_the_end() {
+        let r1cs = copies(10);
+        assert!(matches!(
+            r1cs.constraint_range(8, 3),
+            Err(R1CSError::ConstraintRangeOutOfBounds {
+                start: 8,
+                end: 11,
+                len: 10
+            })
+        ));
+        assert!(matches!(
+            r1cs.constraint_range(11, 0),
+            Err(R1CSError::ConstraintRangeOutOfBounds { start: 11, .. })
+        ));
+        assert!(matches!(
+            r1cs.constraint_range(1, usize::MAX),
+            Err(R1CSError::ConstraintRangeOutOfBounds {
+                end: usize::MAX,
+                ..
+            })
+        ));
+    }
+
+    /// A source that can only be read front to back, like a pipe
+    struct Pipe<'a>(&'a [u8]);
+
+    impl Read for Pipe<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            // hand out a few bytes at a time, as a socket would
+            let n = buf.len().min(self.0.len()).min(7);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn reads_from_a_source_without_seek() {
+        let bytes = multiplier(4, &[0, 1, 2, 3]);
+        let file = R1CSFile::<ark_bn254::Fr>::new_buffered(Pipe(&bytes)).unwrap();
+        let seekable = R1CSFile::<ark_bn254::Fr>::new(Cursor::new(&bytes)).unwrap();
+        assert_eq!(file.constraints, seekable.constraints);
+        assert_eq!(file.wire_to_label, seekable.wire_to_label);
+        assert_eq!(file.section_table(), seekable.section_table());
+    }
+
+    #[test]
+    fn buffered_reading_reports_a_cut_off_stream() {
+        let bytes = multiplier(4, &[0, 1, 2, 3]);
+        assert!(matches!(
+            R1CSFile::<ark_bn254::Fr>::new_buffered(Pipe(&bytes[..200])),
+            Err(R1CSError::SectionOutOfBounds { end: 220, len: 200 })
+        ));
+    }
+
+    #[test]
+    fn retained_bytes_reencode_the_constraint_section() {
+        let mut bytes = multiplier(4, &[0, 1, 2, 3]);
+        // store the first coefficient as p + 1, a non-canonical encoding of one
+        let modulus = ark_bn254::Fr::MODULUS.to_bytes_le();
+        let mut coeff = BigUint::from_bytes_le(&modulus);
+        coeff += 1u8;
+        let mut coeff = coeff.to_bytes_le();
+        coeff.resize(32, 0);
+        bytes[108..140].copy_from_slice(&coeff);
+
+        let options = R1CSReaderOptions {
+            retain_coefficient_bytes: true,
+            ..Default::default()
+        };
+        let file =
+            R1CSFile::<ark_bn254::Fr>::new_with_options(Cursor::new(&bytes), options).unwrap();
+        assert_eq!(file.constraints[0].0, vec![(2, ark_bn254::Fr::from(1u64))]);
+
+        let raw = file.raw_constraints.as_ref().unwrap();
+        assert_eq!(raw[0].0, vec![(2, coeff)]);
+        let mut reencoded = Vec::new();
+        for (a, b, c) in raw {
+            for lc in [a, b, c] {
+                reencoded.extend((lc.len() as u32).to_le_bytes());
+                for (wire, coeff) in lc {
+                    reencoded.extend((*wire as u32).to_le_bytes());
+                    reencoded.extend(coeff);
+                }
+            }
+        }
+        let (_, offset, size) = file.section_table()[1];
+        assert_eq!(
+            &reencoded[..],
+            &bytes[offset as usize..(offset + size) as usize]
+        );
+    }
+
+    #[test]
+    fn coefficient_bytes_are_dropped_by_default() {
+        let file =
+            R1CSFile::<ark_bn254::Fr>::new(Cursor::new(multiplier(4, &[0, 1, 2, 3]))).unwrap();
+        assert!(file.raw_constraints.is_none());
+    }
+
+    #[test]
+    fn reads_a_file_after_junk() {
+        let embedded = multiplier(4, &[0, 1, 2, 3]);
+        let mut bytes = vec![0xAB; 37];
+        bytes.extend(&embedded);
+        bytes.extend(b"someone else's artifact");
+
+        let file = R1CSFile::<ark_bn254::Fr>::new_at_offset(Cursor::new(&bytes), 37).unwrap();
+        assert_eq!(file.header.n_wires, 4);
+        assert_eq!(file.wire_to_label, Some(vec![0, 1, 2, 3]));
+        // section offsets are positions in the whole stream
+        assert_eq!(file.section_table()[0], (1, 37 + 24, 64));
+
+        assert!(matches!(
+            R1CSFile::<ark_bn254::Fr>::new_at_offset(Cursor::new(&bytes), 36),
+            Err(R1CSError::InvalidMagic(_))
+        ));
+    }
+
+    #[test]
+    fn reports_public_outputs_and_inputs() {
+        let multiplier = R1CS::from(
+            R1CSFile::<ark_bn254::Fr>::new(Cursor::new(multiplier(4, &[0, 1, 2, 3]))).unwrap(),
+        );
+        assert!(multiplier.has_public_outputs());
+        assert!(!multiplier.has_public_inputs());
+        assert_eq!(multiplier.num_public(), 1);
+        assert_eq!(multiplier.num_private(), 2);
+
+        let mut no_public = copies(3);
+        assert!(!no_public.has_public_outputs());
+        assert!(!no_public.has_public_inputs());
+        assert_eq!(no_public.num_public(), 0);
+
+        no_public.num_pub_in = 1;
+        no_public.num_inputs = 2;
+        no_public.num_aux = 2;
+        assert!(no_public.has_public_inputs());
+        assert!(!no_public.has_public_outputs());
+        assert_eq!((no_public.num_public(), no_public.num_private()), (1, 2));
+    }
+
+    #[test]
+    fn indexes_constraints() {
+        let r1cs = copies(3);
+        assert_eq!(r1cs[1], r1cs.constraints[1]);
+        assert_eq!(r1cs.get(2), Some(&r1cs.constraints[2]));
+        assert_eq!(r1cs.get(3), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn indexing_past_the_end_panics() {
+        let _ = &copies(3)[3];
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn reads_from_bytes() {
+        use bytes::Buf;
+
+        let data = multiplier(4, &[0, 1, 2, 3]);
+        let expected = R1CSFile::<ark_bn254::Fr>::new(Cursor::new(&data)).unwrap();
+
+        let file = R1CSFile::<ark_bn254::Fr>::from_buf(bytes::Bytes::from(data.clone())).unwrap();
+        assert_eq!(file.constraints, expected.constraints);
+        assert_eq!(file.wire_to_label, expected.wire_to_label);
+
+        // a buffer in several pieces is gathered first
+        let (head, tail) = data.split_at(50);
+        let file = R1CSFile::<ark_bn254::Fr>::from_buf(Buf::chain(head, tail)).unwrap();
+        assert_eq!(file.constraints, expected.constraints);
+        assert_eq!(file.section_table(), expected.section_table());
+    }
+
+    /// The multiplier's header with a single constraint of the given terms
+    fn one_constraint(constraint: [&[(u32, u64)]; 3]) -> Cursor<Vec<u8>> {
+        Cursor::new(file_bytes(
+            1,
+            &[
+                (1, header_section(4, (1, 0, 2), 4, 1)),
+                (2, constraint_section(&[constraint])),
+            ],
+        ))
+    }
+
+    #[test]
+    fn rejects_a_vector_longer_than_the_wires() {
+        let a: &[(u32, u64)] = &[(0, 1), (1, 1), (2, 1), (3, 1), (0, 1)];
+        assert!(matches!(
+            R1CSFile::<ark_bn254::Fr>::new(one_constraint([a, &[(3, 1)], &[(1, 1)]])),
+            Err(R1CSError::ConstraintVecTooLong {
+                constraint: 0,
+                matrix: Matrix::A,
+                len: 5,
+                n_wires: 4
+            })
+        ));
+    }
+
+    #[test]
+    fn rejects_a_wire_past_the_last() {
+        assert!(matches!(
+            R1CSFile::<ark_bn254::Fr>::new(one_constraint([&[(2, 1)], &[(3, 1)], &[(4, 1)]])),
+            Err(R1CSError::WireOutOfRange {
+                constraint: 0,
+                matrix: Matrix::C,
+                wire: 4,
+                n_wires: 4
+            })
+        ));
+    }
+
+    #[test]
+    fn a_claimed_length_past_the_section_is_truncation() {
+        let mut bytes = one_constraint([&[(2, 1)], &[(3, 1)], &[(1, 1)]]).into_inner();
+        // claim a second term in C, within the wire count but past the section
+        let c_len = bytes.len() - 40;
+        bytes[c_len..c_len + 4].copy_from_slice(&2u32.to_le_bytes());
+        assert!(matches!(
+            R1CSFile::<ark_bn254::Fr>::new(Cursor::new(bytes)),
+            Err(R1CSError::TruncatedConstraint { constraint: 0 })
+        ));
+    }
+
+    #[test]
+    fn validate_bounds_each_vector_in_memory() {
+        let mut r1cs = copies(3);
+        assert!(r1cs.validate().is_ok());
+
+        r1cs.constraints[1].1 = (0..5).map(|i| (i % 4, ark_bn254::Fr::from(1u64))).collect();
+        assert!(matches!(
+            r1cs.validate(),
+            Err(R1CSError::ConstraintVecTooLong {
+                constraint: 1,
+                matrix: Matrix::B,
+                len: 5,
+                n_wires: 4
+            })
+        ));
+
+        r1cs.constraints[1].1 = vec![(4, ark_bn254::Fr::from(1u64))];
+        assert!(matches!(
+            r1cs.validate(),
+            Err(R1CSError::WireOutOfRange {
+                constraint: 1,
+                matrix: Matrix::B,
+                wire: 4,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn counts_instance_variables_and_public_signals() {
+        let file = File::open("./test-vectors/mycircuit.r1cs").unwrap();
+        let r1cs = R1CS::from(R1CSFile::<ark_bn254::Fr>::new(file).unwrap());
+        // the constant one and the output `c`
+        assert_eq!(r1cs.num_inputs, 2);
+        assert_eq!(r1cs.num_instance_variables(), 2);
+        // what snarkjs writes to public.json
+        assert_eq!(r1cs.num_public_signals(), 1);
+        assert_eq!(r1cs.num_public_signals(), r1cs.num_public());
+
+        let mut broken = copies(1);
+        broken.num_inputs = 0;
+        assert_eq!(broken.num_public_signals(), 0);
+    }
+
+    /// Records the span names and event messages emitted while it is the default
+    #[cfg(feature = "tracing")]
+    #[derive(Clone, Default)]
+    struct Capture(std::sync::Arc<std::sync::Mutex<Vec<String>>>);
+
+    #[cfg(feature = "tracing")]
+    impl tracing::Subscriber for Capture {
+        fn register_callsite(
+            &self,
+            _: &'static tracing::Metadata<'static>,
+        ) -> tracing::subscriber::Interest {
+            tracing::subscriber::Interest::sometimes()
+        }
+
+        fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            let mut log = self.0.lock().unwrap();
+            log.push(format!("span {}", span.metadata().name()));
+            tracing::span::Id::from_u64(log.len() as u64)
+        }
+
+        fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+
+        fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+        fn event(&self, event: &tracing::Event<'_>) {
+            struct Message(String);
+            impl tracing::field::Visit for Message {
+                fn record_debug(
+                    &mut self,
+                    field: &tracing::field::Field,
+                    value: &dyn std::fmt::Debug,
+                ) {
+                    if field.name() == "message" {
+                        self.0 = format!("{:?}", value);
+                    }
+                }
+            }
+            let mut message = Message(String::new());
+            event.record(&mut message);
+            self.0.lock().unwrap().push(format!("event {}", message.0));
+        }
+
+        fn enter(&self, _: &tracing::span::Id) {}
+
+        fn exit(&self, _: &tracing::span::Id) {}
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn traces_each_stage_of_a_load() {
+        let capture = Capture::default();
+        tracing::subscriber::with_default(capture.clone(), || {
+            R1CSFile::<ark_bn254::Fr>::new(Cursor::new(multiplier(4, &[0, 1, 2, 3]))).unwrap();
+        });
+        let log = capture.0.lock().unwrap();
+        for expected in [
+            "span read_section_table",
+            "event read section table",
+            "span read_header",
+            "event read header",
+            "span read_constraints",
+            "event read constraints",
+        ] {
+            assert!(log.iter().any(|line| line == expected), "{:?}", log);
+        }
+    }
+
+    #[test]
+    fn max_wire_index_scans_every_matrix() {
+        let file =
+            R1CSFile::<ark_bn254::Fr>::new(Cursor::new(multiplier(4, &[0, 1, 2, 3]))).unwrap();
+        let r1cs = R1CS::from(file);
+        assert_eq!(r1cs.max_wire_index(), 3);
+        assert_eq!(r1cs.max_wire_index(), r1cs.num_variables - 1);
+
+        let mut r1cs = copies(5);
+        assert_eq!(r1cs.max_wire_index(), 5);
+        // a reference past the last wire only shows up in B here
+        r1cs.constraints[2].1.push((9, ark_bn254::Fr::from(1u64)));
+        assert_eq!(r1cs.max_wire_index(), 9);
+        assert!(r1cs.max_wire_index() >= r1cs.num_variables);
+
+        assert_eq!(copies(0).max_wire_index(), 0);
+    }
+
+    #[test]
+    fn a_truncated_file_is_shorter_than_its_sections() {
+        let bytes = multiplier(4, &[0, 1, 2, 3]);
+        assert_eq!(bytes.len(), 264);
+        let err = match R1CSFile::<ark_bn254::Fr>::new(Cursor::new(&bytes[..250])) {
+            Err(err) => err,
+            Ok(_) => panic!("a truncated file was accepted"),
+        };
+        assert!(matches!(
+            err,
+            R1CSError::SectionOutOfBounds { end: 264, len: 250 }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "file is 250 bytes long but its sections extend to byte 264"
+        );
+    }
+
+    #[test]
+    fn a_declared_length_bounds_the_sections() {
+        let bytes = multiplier(4, &[0, 1, 2, 3]);
+        assert!(R1CSFile::<ark_bn254::Fr>::new_with_length(Cursor::new(&bytes), 264).is_ok());
+        assert!(matches!(
+            R1CSFile::<ark_bn254::Fr>::new_with_length(Cursor::new(&bytes), 240),
+            Err(R1CSError::SectionOutOfBounds { end: 264, len: 240 })
+        ));
+    }
+
+    #[test]
+    fn a_declared_length_past_u64_max_is_rejected() {
+        let bytes = multiplier(4, &[0, 1, 2, 3]);
+        let mut reader = Cursor::new(&bytes);
+        reader.set_position(8);
+        assert!(matches!(
+            R1CSFile::<ark_bn254::Fr>::new_with_length(reader, u64::MAX - 4),
+            Err(R1CSError::LimitExceeded {
+                value,
+                limit,
+                ..
+            }) if value == u64::MAX - 4 && limit == u64::MAX - 8
+        ));
+    }
+
+    #[test]
+    fn progress_reports_fractions_of_the_constraints() {
+        let constraint: [&[(u32, u64)]; 3] = [&[(2, 1)], &[(3, 1)], &[(1, 1)]];
+        let bytes = file_bytes(
+            1,
+            &[
+                (1, header_section(4, (1, 0, 2), 0, 2048)),
+                (2, constraint_section(&vec![constraint; 2048])),
+            ],
+        );
+        let mut reported = Vec::new();
+        let file = R1CSFile::<ark_bn254::Fr>::new_with_progress(
+            Cursor::new(bytes),
+            R1CSReaderOptions::default(),
+            &mut |fraction| reported.push(fraction),
+        )
+        .unwrap();
+        assert_eq!(file.constraints.len(), 2048);
+        assert_eq!(reported, vec![0.5, 1.0]);
+    }
+
+    /// `passthrough.r1cs` has wires `[1, out, in]`, an empty constraint section and labels
+    fn passthrough() -> File {
+        File::open("./test-vectors/passthrough.r1cs").unwrap()
+    }
+
+    #[test]
+    fn reads_a_file_without_constraints() {
+        let file = R1CSFile::<ark_bn254::Fr>::new(passthrough()).unwrap();
+        assert_eq!(file.header.n_constraints, 0);
+        assert!(file.constraints.is_empty());
+        assert_eq!(file.wire_to_label, Some(vec![0, 1, 2]));
+        assert_eq!(file.section_table()[1], (2, 100, 0));
+
+        let r1cs = R1CS::from(file);
+        assert_eq!(
+            (r1cs.num_inputs, r1cs.num_aux, r1cs.num_variables),
+            (3, 0, 3)
+        );
+        assert_eq!(
+            r1cs.check_witness(&[1u64, 5, 5].map(ark_bn254::Fr::from)),
+            Ok(())
+        );
+
+        let cs = ark_relations::r1cs::ConstraintSystem::new_ref();
+        let circuit =
+            crate::CircomCircuit::new(r1cs, Some([1u64, 5, 5].map(ark_bn254::Fr::from).to_vec()));
+        ark_relations::r1cs::ConstraintSynthesizer::generate_constraints(circuit, cs.clone())
+            .unwrap();
+        assert_eq!(cs.num_constraints(), 0);
+        assert_eq!(cs.num_instance_variables(), 3);
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn streams_no_constraints() {
+        let mut stream = ConstraintStream::<_, ark_bn254::Fr>::new(passthrough()).unwrap();
+        assert!(stream.next().is_none());
+    }
+
+    #[test]
+    fn the_constraint_section_is_optional_only_without_constraints() {
+        let bytes = file_bytes(1, &[(1, header_section(3, (1, 1, 0), 0, 0))]);
+        assert!(R1CSFile::<ark_bn254::Fr>::new(Cursor::new(&bytes))
+            .unwrap()
+            .constraints
+            .is_empty());
+        let mut stream = ConstraintStream::<_, ark_bn254::Fr>::new(Cursor::new(&bytes)).unwrap();
+        assert!(stream.next().is_none());
+
+        let bytes = file_bytes(1, &[(1, header_section(3, (1, 1, 0), 0, 1))]);
+        assert!(matches!(
+            R1CSFile::<ark_bn254::Fr>::new(Cursor::new(&bytes)),
+            Err(R1CSError::MissingSection(2))
+        ));
+        assert!(matches!(
+            ConstraintStream::<_, ark_bn254::Fr>::new(Cursor::new(&bytes)),
+            Err(R1CSError::MissingSection(2))
+        ));
+    }
+
+    #[test]
+    fn validate_accepts_a_loaded_file() {
+        let file =
+            R1CSFile::<ark_bn254::Fr>::new(Cursor::new(multiplier(4, &[0, 1, 2, 3]))).unwrap();
+        assert!(R1CS::from(file).validate().is_ok());
+    }
+
+    #[test]
+    fn validate_checks_the_wire_counts() {
+        let mut r1cs = copies(3);
+        r1cs.num_variables = 5;
+        assert!(matches!(
+            r1cs.validate(),
+            Err(R1CSError::InconsistentCounts {
+                num_inputs: 1,
+                num_aux: 3,
+                num_variables: 5
+            })
+        ));
+
+        let mut r1cs = copies(3);
+        r1cs.num_inputs = 0;
+        r1cs.num_aux = 4;
+        assert!(matches!(
+            r1cs.validate(),
+            Err(R1CSError::InconsistentCounts { num_inputs: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn validate_checks_the_public_counts() {
+        let mut r1cs = copies(3);
+        r1cs.num_pub_out = 1;
+        assert!(matches!(
+            r1cs.validate(),
+            Err(R1CSError::InconsistentPublicCounts {
+                num_inputs: 1,
+                num_pub_out: 1,
+                num_pub_in: 0
+            })
+        ));
+    }
+
+    #[test]
+    fn rejects_a_header_with_more_signals_than_wires() {
+        let bytes = file_bytes(1, &[(1, header_section(4, (1, 1, 2), 0, 0))]);
+        assert!(matches!(
+            R1CSFile::<ark_bn254::Fr>::new(Cursor::new(bytes)),
+            Err(R1CSError::InconsistentWireCounts {
+                n_wires: 4,
+                n_pub_out: 1,
+                n_pub_in: 1,
+                n_prv_in: 2
+            })
+        ));
+    }
+
+    /// Header of the multiplier with the prime written as `field_size` bytes
+    fn padded_header(field_size: u32, prime: &[u8]) -> Vec<u8> {
+        let mut bytes = field_size.to_le_bytes().to_vec();
+        bytes.extend(prime);
+        bytes.resize(4 + field_size as usize, 0);
+        for count in [4u32, 1, 0, 2] {
+            bytes.extend(count.to_le_bytes());
+        }
+        bytes.extend(0u64.to_le_bytes());
+        bytes.extend(1u32.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn accepts_a_zero_padded_prime() {
+        // M31 in 32 bytes, as tooling sized for BN254 writes it
+        let bytes = file_bytes(
+            1,
+            &[
+                (1, padded_header(32, &2147483647u32.to_le_bytes())),
+                (2, constraint_section(&[[&[(2, 1)], &[(3, 5)], &[(1, 1)]]])),
+            ],
+        );
+        let file = R1CSFile::<crate::field::M31>::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(file.header.field_size, 32);
+        assert_eq!(
+            file.constraints[0].1,
+            vec![(3, crate::field::M31::from(5u64))]
+        );
+    }
+
+    #[test]
+    fn a_padded_prime_must_still_match() {
+        let bytes = file_bytes(
+            1,
+            &[
+                (1, padded_header(32, &2147483629u32.to_le_bytes())),
+                (2, constraint_section(&[[&[(2, 1)], &[(3, 1)], &[(1, 1)]]])),
+            ],
+        );
+        assert!(matches!(
+            R1CSFile::<crate::field::M31>::new(Cursor::new(bytes)),
+            Err(R1CSError::PrimeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn a_cut_off_prime_is_a_field_size_mismatch() {
+        let modulus = ark_bn254::Fr::MODULUS.to_bytes_le();
+        let bytes = file_bytes(1, &[(1, padded_header(16, &modulus[..16]))]);
+        assert!(matches!(
+            R1CSFile::<ark_bn254::Fr>::new(Cursor::new(bytes)),
+            Err(R1CSError::FieldSizeMismatch {
+                field_size: 16,
+                needed: 32
+            })
+        ));
+    }
+
+    fn unsorted() -> Vec<u8> {
+        file_bytes(
+            1,
+            &[
+                (1, header_section(4, (1, 0, 2), 0, 1)),
+                (
+                    2,
+                    constraint_section(&[[&[(3, 1), (2, 2), (3, 4)], &[(3, 1)], &[(1, 1)]]]),
+                ),
+            ],
+        )
+    }
+
+    fn sorted_terms() -> R1CSReaderOptions {
+        R1CSReaderOptions {
+            sort_terms: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn sort_terms_sorts_and_merges_on_load() {
+        let fr = ark_bn254::Fr::from;
+        let file =
+            R1CSFile::<ark_bn254::Fr>::new_with_options(Cursor::new(unsorted()), sorted_terms())
+                .unwrap();
+        assert_eq!(file.constraints[0].0, vec![(2, fr(2u64)), (3, fr(5u64))]);
+
+        let mut stream = ConstraintStream::<_, ark_bn254::Fr>::new_with_options(
+            Cursor::new(unsorted()),
+            sorted_terms(),
+        )
+        .unwrap();
+        assert_eq!(stream.next().unwrap().unwrap(), file.constraints[0]);
+    }
+
+    #[test]
+    fn terms_keep_the_file_order_by_default() {
+        let fr = ark_bn254::Fr::from;
+        let file = R1CSFile::<ark_bn254::Fr>::new(Cursor::new(unsorted())).unwrap();
+        assert_eq!(
+            file.constraints[0].0,
+            vec![(3, fr(1u64)), (2, fr(2u64)), (3, fr(4u64))]
+        );
+    }
+
+    #[test]
+    fn sorting_leaves_the_retained_bytes_in_file_order() {
+        let options = R1CSReaderOptions {
+            retain_coefficient_bytes: true,
+            ..sorted_terms()
+        };
+        let file =
+            R1CSFile::<ark_bn254::Fr>::new_with_options(Cursor::new(unsorted()), options).unwrap();
+        let wires: Vec<usize> = file.raw_constraints.unwrap()[0]
+            .0
+            .iter()
+            .map(|(wire, _)| *wire)
+            .collect();
+        assert_eq!(wires, vec![3, 2, 3]);
+        assert_eq!(file.constraints[0].0.len(), 2);
+    }
+
+    #[test]
+    fn finds_the_constraints_touching_a_wire() {
+        let r1cs = copies(3);
+        // wire 2 is the output of constraint 1 and the input of constraint 2
+        assert_eq!(r1cs.constraints_touching(2), vec![1, 2]);
+        assert_eq!(r1cs.constraints_touching(0), vec![0, 1, 2]);
+        assert_eq!(r1cs.constraints_touching(3), vec![2]);
+        assert!(r1cs.constraints_touching(9).is_empty());
+
+        let file =
+            R1CSFile::<ark_bn254::Fr>::new(Cursor::new(multiplier(4, &[0, 1, 2, 3]))).unwrap();
+        assert_eq!(R1CS::from(file).constraints_touching(3), vec![0]);
+    }
+
+    /// Like [`constraint_section`], with `field_size` byte coefficients
+    fn sized_constraint_section(field_size: usize, constraints: &[[&[(u32, u64)]; 3]]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for lc in constraints.iter().flatten() {
+            bytes.extend((lc.len() as u32).to_le_bytes());
+            for (wire, coeff) in lc.iter() {
+                bytes.extend(wire.to_le_bytes());
+                let mut coeff = coeff.to_le_bytes().to_vec();
+                coeff.resize(field_size, 0);
+                bytes.extend(coeff);
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn reads_a_48_byte_field_size() {
+        let bytes = file_bytes(
+            1,
+            &[
+                (1, padded_header(48, &ark_bn254::Fr::MODULUS.to_bytes_le())),
+                (
+                    2,
+                    sized_constraint_section(48, &[[&[(2, 3)], &[(3, 1), (0, 9)], &[(1, 1)]]]),
+                ),
+            ],
+        );
+        let file = R1CSFile::<ark_bn254::Fr>::new(Cursor::new(&bytes)).unwrap();
+        assert_eq!(file.header.field_size, 48);
+        assert_eq!(file.constraints[0].0, vec![(2, ark_bn254::Fr::from(3u64))]);
+        assert_eq!(
+            file.constraints[0].1,
+            vec![
+                (3, ark_bn254::Fr::from(1u64)),
+                (0, ark_bn254::Fr::from(9u64))
+            ]
+        );
+        assert_eq!(
+            file.write(Cursor::new(Vec::new())).unwrap().into_inner(),
+            bytes
+        );
+    }
+
+    #[test]
+    fn reads_an_odd_field_size() {
+        let bytes = file_bytes(
+            1,
+            &[
+                (1, padded_header(5, &2147483647u32.to_le_bytes())),
+                (
+                    2,
+                    sized_constraint_section(5, &[[&[(2, 2147483646)], &[(3, 1)], &[(1, 7)]]]),
+                ),
+            ],
+        );
+        let file = R1CSFile::<crate::field::M31>::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(file.header.field_size, 5);
+        assert_eq!(
+            file.constraints[0].0,
+            vec![(2, -crate::field::M31::from(1u64))]
+        );
+        assert_eq!(
+            file.constraints[0].2,
+            vec![(1, crate::field::M31::from(7u64))]
+        );
+    }
+
+    #[test]
+    fn expected_witness_len_counts_every_wire() {
+        for r1cs in [copies(0), copies(4)] {
+            assert_eq!(r1cs.expected_witness_len(), r1cs.num_inputs + r1cs.num_aux);
+        }
+        let file =
+            R1CSFile::<ark_bn254::Fr>::new(Cursor::new(multiplier(4, &[0, 1, 2, 3]))).unwrap();
+        assert_eq!(R1CS::from(file).expected_witness_len(), 4);
+    }
+
+    #[test]
+    fn prime_bigint_is_the_modulus_of_a_matching_file() {
+        let file =
+            R1CSFile::<ark_bn254::Fr>::new(Cursor::new(multiplier(4, &[0, 1, 2, 3]))).unwrap();
+        assert_eq!(
+            file.header.prime_bigint::<ark_ff::BigInt<4>>(),
+            Some(ark_bn254::Fr::MODULUS)
+        );
+
+        let m31 = R1CSFile::<crate::field::M31>::new(prime_file("m31")).unwrap();
+        assert_eq!(
+            m31.header.prime_bigint::<ark_ff::BigInt<1>>(),
+            Some(crate::field::M31::MODULUS)
+        );
+    }
+
+    #[test]
+    fn prime_bigint_is_none_when_it_does_not_fit() {
+        let file =
+            R1CSFile::<ark_bn254::Fr>::new(Cursor::new(multiplier(4, &[0, 1, 2, 3]))).unwrap();
+        assert_eq!(file.header.prime_bigint::<ark_ff::BigInt<1>>(), None);
+
+        // zero padding does not count against the width
+        let mut header = file.header;
+        header.prime_size.resize(64, 0);
+        assert_eq!(
+            header.prime_bigint::<ark_ff::BigInt<4>>(),
+            Some(ark_bn254::Fr::MODULUS)
+        );
+    }
+
+    #[test]
+    fn reports_how_far_a_truncated_section_table_got() {
+        let bytes = multiplier(4, &[0, 1, 2, 3]);
+        // inside the type and size of the header section, then of the constraint section
+        for (len, section_index) in [(14, 0), (92, 1), (99, 1), (226, 2)] {
+            match R1CSFile::<ark_bn254::Fr>::new(Cursor::new(&bytes[..len])) {
+                Err(R1CSError::TruncatedFile {
+                    section_index: found,
+                }) => {
+                    assert_eq!(found, section_index, "{}", len)
+                }
+                Err(err) => panic!("{}: {}", len, err),
+                Ok(_) => panic!("{}: a truncated file was accepted", len),
+            }
+        }
+        assert_eq!(
+            R1CSError::TruncatedFile { section_index: 1 }.to_string(),
+            "file ends inside entry 1 of the section table"
+        );
+    }
+
+    /// circom's multiplier in a version 2 file with a custom gates list and use section
+    fn custom_gates() -> File {
+        File::open("./test-vectors/custom-gates.r1cs").unwrap()
+    }
+
+    #[test]
+    fn a_version_1_file_is_pure_r1cs() {
+        let file =
+            R1CSFile::<ark_bn254::Fr>::new(Cursor::new(multiplier(4, &[0, 1, 2, 3]))).unwrap();
+        assert!(!file.has_custom_gates());
+        let r1cs = R1CS::from(file);
+        assert_eq!(r1cs.version(), 1);
+        assert!(r1cs.is_pure_r1cs());
+    }
+
+    #[test]
+    fn a_file_with_custom_gates_is_not_pure_r1cs() {
+        let file = R1CSFile::<ark_bn254::Fr>::new(custom_gates()).unwrap();
+        assert!(file.has_custom_gates());
+        assert_eq!(file.constraints.len(), 1);
+        let r1cs = R1CS::from(file);
+        assert_eq!(r1cs.version(), 2);
+        assert!(!r1cs.is_pure_r1cs());
+    }
+
+    #[test]
+    fn a_version_2_file_without_gates_is_pure_r1cs() {
+        let mut bytes = multiplier(4, &[0, 1, 2, 3]);
+        bytes[4] = 2;
+        let r1cs = R1CS::from(R1CSFile::<ark_bn254::Fr>::new(Cursor::new(bytes)).unwrap());
+        assert_eq!(r1cs.version(), 2);
+        assert!(r1cs.is_pure_r1cs());
+    }
+
+    const SHARDED: [[&[(u32, u64)]; 3]; 3] = [
+        [&[(2, 1)], &[(3, 1)], &[(1, 1)]],
+        [&[(1, 2), (0, 1)], &[(0, 1)], &[(3, 1)]],
+        [&[(3, 1)], &[(2, 5)], &[(1, 1), (0, 4)]],
+    ];
+
+    /// Three constraints over the multiplier's wires, in one file
+    fn unsharded() -> Vec<u8> {
+        file_bytes(
+            1,
+            &[
+                (1, header_section(4, (1, 0, 2), 4, 3)),
+                (2, constraint_section(&SHARDED)),
+                (3, label_section(&[0, 1, 2, 3])),
+            ],
+        )
+    }
+
+    /// The same header and labels with no constraints of its own
+    fn shard_header() -> Cursor<Vec<u8>> {
+        Cursor::new(file_bytes(
+            1,
+            &[
+                (1, header_section(4, (1, 0, 2), 4, 3)),
+                (3, label_section(&[0, 1, 2, 3])),
+            ],
+        ))
+    }
+
+    #[test]
+    fn shards_match_the_single_file() {
+        let single = R1CSFile::<ark_bn254::Fr>::new(Cursor::new(unsharded())).unwrap();
+        let shards = vec![
+            Cursor::new(constraint_section(&SHARDED[..1])),
+            Cursor::new(constraint_section(&SHARDED[1..])),
+        ];
+        let sharded = R1CSFile::<ark_bn254::Fr>::from_shards(shard_header(), shards).unwrap();
+        assert_eq!(sharded.constraints, single.constraints);
+        assert_eq!(sharded.wire_to_label, single.wire_to_label);
+        assert_eq!(sharded.header.n_constraints, 3);
+    }
+
+    #[test]
+    fn shards_must_hold_every_constraint() {
+        let shards = vec![Cursor::new(constraint_section(&SHARDED[..2]))];
+        assert!(matches!(
+            R1CSFile::<ark_bn254::Fr>::from_shards(shard_header(), shards),
+            Err(R1CSError::ConstraintCountMismatch {
+                expected: 3,
+                actual: 2
+            })
+        ));
+
+        let mut cut = constraint_section(&SHARDED[1..]);
+        cut.truncate(cut.len() - 4);
+        let shards = vec![
+            Cursor::new(constraint_section(&SHARDED[..1])),
+            Cursor::new(cut),
+        ];
+        assert!(matches!(
+            R1CSFile::<ark_bn254::Fr>::from_shards(shard_header(), shards),
+            Err(R1CSError::TruncatedConstraint { constraint: 2 })
+        ));
+    }
+
+    #[test]
+    fn each_shard_is_bounded_like_a_section() {
+        let options = R1CSReaderOptions {
+            max_section_bytes: Some(200),
+            ..Default::default()
+        };
+        let small = Cursor::new(constraint_section(&SHARDED[..1]));
+        let large = Cursor::new(constraint_section(&SHARDED[1..]));
+        assert!(matches!(
+            R1CSFile::<ark_bn254::Fr>::from_shards_with_options(
+                shard_header(),
+                vec![small, large],
+                options
+            ),
+            Err(R1CSError::LimitExceeded {
+                value: 312,
+                limit: 200,
+                ..
+            })
+        ));
+    }
+
+    fn strict() -> R1CSReaderOptions {
+        R1CSReaderOptions {
+            reject_trailing_bytes: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn strict_reading_rejects_appended_bytes() {
+        let mut bytes = multiplier(4, &[0, 1, 2, 3]);
+        assert!(R1CSFile::<ark_bn254::Fr>::new_with_options(Cursor::new(&bytes), strict()).is_ok());
+
+        bytes.extend([0u8; 5]);
+        assert!(matches!(
+            R1CSFile::<ark_bn254::Fr>::new_with_options(Cursor::new(&bytes), strict()),
+            Err(R1CSError::TrailingBytes(5))
+        ));
+        // lenient by default
+        assert!(R1CSFile::<ark_bn254::Fr>::new(Cursor::new(&bytes)).is_ok());
+    }
+
+    #[test]
+    fn strict_reading_stops_at_the_declared_length() {
+        let mut bytes = multiplier(4, &[0, 1, 2, 3]);
+        bytes.extend(multiplier(4, &[0, 1, 2, 3]));
+        let options = R1CSReaderOptions {
+            length: Some(264),
+            ..strict()
+        };
+        assert!(R1CSFile::<ark_bn254::Fr>::new_with_options(Cursor::new(&bytes), options).is_ok());
+        let options = R1CSReaderOptions {
+            length: Some(270),
+            ..strict()
+        };
+        assert!(matches!(
+            R1CSFile::<ark_bn254::Fr>::new_with_options(Cursor::new(&bytes), options),
+            Err(R1CSError::TrailingBytes(6))
+        ));
+    }
+
+    #[test]
+    fn a_custom_decoder_controls_the_coefficients() {
+        type Fr = ark_bn254::Fr;
+        let bytes = multiplier(4, &[0, 1, 2, 3]);
+        let plain = R1CSFile::<Fr>::new(Cursor::new(&bytes)).unwrap();
+        let negated = R1CSFile::<Fr>::new_with_decoder(
+            Cursor::new(&bytes),
+            Default::default(),
+            |bytes: &[u8]| -Fr::from_le_bytes_mod_order(bytes),
+        )
+        .unwrap();
+
+        let negate = |lc: &ConstraintVec<Fr>| -> ConstraintVec<Fr> {
+            lc.iter().map(|(wire, coeff)| (*wire, -*coeff)).collect()
+        };
+        let expected: Vec<_> = plain
+            .constraints
+            .iter()
+            .map(|(a, b, c)| (negate(a), negate(b), negate(c)))
+            .collect();
+        assert_eq!(negated.constraints, expected);
+        assert_eq!(negated.header.n_constraints, plain.header.n_constraints);
+    }
+
+    #[test]
+    fn a_custom_decoder_still_checks_the_prime() {
+        let bytes = multiplier(4, &[0, 1, 2, 3]);
+        let result = R1CSFile::<ark_bls12_381::Fr>::new_with_decoder(
+            Cursor::new(&bytes),
+            Default::default(),
+            |_: &[u8]| ark_bls12_381::Fr::from(0u64),
+        );
+        assert!(matches!(result, Err(R1CSError::PrimeMismatch { .. })));
+    }
+
+    /// 150 constraints over five wires whose A vectors hold one to four terms
+    fn varied_lengths() -> Vec<u8> {
+        let terms: Vec<Vec<(u32, u64)>> = (0..150u64)
+            .map(|i| (0..i % 4 + 1).map(|j| (j as u32, i + j)).collect())
+            .collect();
+        let constraints: Vec<[&[(u32, u64)]; 3]> = terms
+            .iter()
+            .map(|a| [a.as_slice(), &[(0, 1)], &[(4, 7)]])
+            .collect();
+        file_bytes(
+            1,
+            &[
+                (1, header_section(5, (1, 1, 0), 5, 150)),
+                (2, constraint_section(&constraints)),
+            ],
+        )
+    }
+
+    #[test]
+    fn reads_a_subset_of_the_constraints() {
+        let bytes = varied_lengths();
+        let full = R1CSFile::<ark_bn254::Fr>::new(Cursor::new(&bytes)).unwrap();
+        let subset =
+            R1CSFile::<ark_bn254::Fr>::read_constraints_subset(Cursor::new(&bytes), &[100, 0, 100])
+                .unwrap();
+        assert_eq!(
+            subset,
+            vec![
+                full.constraints[100].clone(),
+                full.constraints[0].clone(),
+                full.constraints[100].clone()
+            ]
+        );
+        assert!(
+            R1CSFile::<ark_bn254::Fr>::read_constraints_subset(Cursor::new(&bytes), &[])
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn a_subset_index_past_the_end_is_rejected() {
+        let bytes = varied_lengths();
+        assert!(matches!(
+            R1CSFile::<ark_bn254::Fr>::read_constraints_subset(Cursor::new(&bytes), &[3, 150]),
+            Err(R1CSError::ConstraintRangeOutOfBounds {
+                start: 150,
+                end: 151,
+                len: 150
+            })
+        ));
+    }
+
+    fn capped(max_constraints: u32, max_wires: u32, max_section_bytes: u64) -> R1CSReaderOptions {
+        R1CSReaderOptions {
+            max_constraints: Some(max_constraints),
+            max_wires: Some(max_wires),
+            max_section_bytes: Some(max_section_bytes),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn counts_at_the_caps_are_accepted() {
+        // 4 wires, 1 constraint and a 120-byte constraint section
+        let bytes = multiplier(4, &[0, 1, 2, 3]);
+        let file =
+            R1CSFile::<ark_bn254::Fr>::new_with_options(Cursor::new(&bytes), capped(1, 4, 120));
+        assert!(file.is_ok());
+    }
+
+    #[test]
+    fn rejects_counts_over_the_caps() {
+        let bytes = multiplier(4, &[0, 1, 2, 3]);
+        let read =
+            |options| R1CSFile::<ark_bn254::Fr>::new_with_options(Cursor::new(&bytes), options);
+        assert!(matches!(
+            read(capped(0, 4, 120)),
+            Err(R1CSError::LimitExceeded {
+                what: "constraints",
+                value: 1,
+                limit: 0
+            })
+        ));
+        assert!(matches!(
+            read(capped(1, 3, 120)),
+            Err(R1CSError::LimitExceeded {
+                what: "wires",
+                value: 4,
+                limit: 3
+            })
+        ));
+        assert!(matches!(
+            read(capped(1, 4, 119)),
+            Err(R1CSError::LimitExceeded {
+                what: "bytes in a section",
+                value: 120,
+                limit: 119
+            })
+        ));
+    }
+
+    #[test]
+    fn caps_apply_before_the_constraints_are_read() {
+        // the header claims far more constraints than the file holds
+        let bytes = file_bytes(
+            1,
+            &[
+                (1, header_section(4, (1, 0, 2), 4, u32::MAX)),
+                (2, constraint_section(&[[&[(2, 1)], &[(3, 1)], &[(1, 1)]]])),
+            ],
+        );
+        let options = R1CSReaderOptions {
+            max_constraints: Some(1000),
+            ..Default::default()
+        };
+        let exceeded = |result: Result<()>| {
+            matches!(
+                result,
+                Err(R1CSError::LimitExceeded {
+                    what: "constraints",
+                    ..
+                })
+            )
+        };
+        assert!(exceeded(
+            R1CSFile::<ark_bn254::Fr>::new_with_options(Cursor::new(&bytes), options).map(|_| ())
+        ));
+        assert!(exceeded(
+            ConstraintStream::<_, ark_bn254::Fr>::new_with_options(Cursor::new(&bytes), options)
+                .map(|_| ())
+        ));
+        assert!(exceeded(
+            R1CSFile::<ark_bn254::Fr>::read_constraints_subset_with_options(
+                Cursor::new(&bytes),
+                &[0],
+                options
+            )
+            .map(|_| ())
+        ));
+    }
+
+    #[test]
+    fn reads_the_legacy_header_layout() {
+        // mycircuit.r1cs with the private input count cut out of its header
+        let legacy = File::open("./test-vectors/legacy-header.r1cs").unwrap();
+        let legacy = R1CSFile::<ark_bn254::Fr>::new(legacy).unwrap();
+        let current = File::open("./test-vectors/mycircuit.r1cs").unwrap();
+        let current = R1CSFile::<ark_bn254::Fr>::new(current).unwrap();
+
+        assert_eq!(legacy.header.n_prv_in, 2);
+        let counts = |h: &Header| {
+            (
+                h.n_wires,
+                h.n_pub_out,
+                h.n_pub_in,
+                h.n_prv_in,
+                h.n_labels,
+                h.n_constraints,
+            )
+        };
+        assert_eq!(counts(&legacy.header), counts(&current.header));
+        assert_eq!(legacy.constraints, current.constraints);
+        assert_eq!(legacy.wire_to_label, current.wire_to_label);
+    }
+
+    #[test]
+    fn legacy_headers_count_every_other_wire_as_private() {
+        let mut header = header_section(7, (1, 2, 0), 7, 0);
+        // drop n_prv_in, after the field size, prime, n_wires, n_pub_out and n_pub_in
+        header.drain(48..52);
+        let bytes = file_bytes(1, &[(1, header), (2, Vec::new())]);
+        let file = R1CSFile::<ark_bn254::Fr>::new(Cursor::new(&bytes)).unwrap();
+        assert_eq!(
+            (
+                file.header.n_pub_out,
+                file.header.n_pub_in,
+                file.header.n_prv_in
+            ),
+            (1, 2, 3)
+        );
+    }
+
+    #[test]
+    fn other_header_sizes_are_rejected() {
+        let mut header = header_section(4, (1, 0, 2), 4, 0);
+        header.drain(48..50);
+        let bytes = file_bytes(1, &[(1, header), (2, Vec::new())]);
+        assert!(matches!(
+            R1CSFile::<ark_bn254::Fr>::new(Cursor::new(&bytes)),
+            Err(R1CSError::InvalidSectionSize {
+                section_type: 1,
+                size: 62
+            })
+        ));
+    }
+
+    #[test]
+    fn a_short_prime_that_is_not_a_prefix_is_a_prime_mismatch() {
+        // 16 bytes of another value are a different prime, not a truncated BN254 modulus
+        let mut prime = ark_bn254::Fr::MODULUS.to_bytes_le();
+        prime[0] ^= 1;
+        let bytes = file_bytes(1, &[(1, padded_header(16, &prime[..16]))]);
+        let err = match R1CSFile::<ark_bn254::Fr>::new(Cursor::new(bytes)) {
+            Err(err) => err,
+            Ok(_) => panic!("a different prime was accepted"),
+        };
+        assert!(matches!(err, R1CSError::PrimeMismatch { .. }));
+    }
+
+    #[test]
+    fn the_two_prime_mismatches_read_differently() {
+        let m31 = |prime: u32| {
+            let bytes = file_bytes(1, &[(1, padded_header(4, &prime.to_le_bytes()))]);
+            match R1CSFile::<crate::field::M31>::new(Cursor::new(bytes)) {
+                Err(err) => err.to_string(),
+                Ok(_) => panic!("prime {} was accepted", prime),
+            }
+        };
+        assert_eq!(
+            m31(2147483629),
+            "prime 2147483629 in header does not match the field modulus 2147483647"
+        );
+
+        let modulus = ark_bn254::Fr::MODULUS.to_bytes_le();
+        let bytes = file_bytes(1, &[(1, padded_header(8, &modulus[..8]))]);
+        let err = match R1CSFile::<ark_bn254::Fr>::new(Cursor::new(bytes)) {
+            Err(err) => err.to_string(),
+            Ok(_) => panic!("a cut-off prime was accepted"),
+        };
+        assert_eq!(
+            err,
+            "field size of 8 bytes truncates the field modulus, which needs 32"
+        );
+    }
+
+    #[test]
+    fn tells_linear_from_quadratic_constraints() {
+        let one = ark_bn254::Fr::from(1u64);
+        let mut r1cs = copies(3);
+        // w1 * w2 = w3, and (w1 + 2) * (1 + w1) = w3 with a wire next to the constant
+        r1cs.constraints
+            .push((vec![(1, one)], vec![(2, one)], vec![(3, one)]));
+        r1cs.constraints.push((
+            vec![(1, one), (0, one + one)],
+            vec![(0, one), (1, one)],
+            vec![(3, one)],
+        ));
+        // an empty B side is the constant zero
+        r1cs.constraints.push((vec![(1, one)], vec![], vec![]));
+
+        assert!(r1cs.is_linear_constraint(0));
+        assert!(!r1cs.is_linear_constraint(3));
+        assert!(!r1cs.is_linear_constraint(4));
+        assert!(r1cs.is_linear_constraint(5));
+        assert_eq!(r1cs.linear_constraint_indices(), vec![0, 1, 2, 5]);
+    }
+
+    #[test]
+    fn the_multiplier_has_no_linear_constraints() {
+        let file = File::open("./test-vectors/mycircuit.r1cs").unwrap();
+        let r1cs = R1CS::from(R1CSFile::<ark_bn254::Fr>::new(file).unwrap());
+        assert!(r1cs.linear_constraint_indices().is_empty());
+    }
+
+    #[test]
+    fn conversion_keeps_the_version() {
+        let v1 = R1CSFile::<ark_bn254::Fr>::new(Cursor::new(multiplier(4, &[0, 1, 2, 3]))).unwrap();
+        let v2 = R1CSFile::<ark_bn254::Fr>::new(custom_gates()).unwrap();
+        assert_eq!((v1.version, v2.version), (1, 2));
+        assert_eq!(R1CS::from(v1).version(), 1);
+
+        let r1cs = R1CS::from(v2);
+        assert_eq!(r1cs.version(), 2);
+        let mut bytes = Vec::new();
+        r1cs.serialize_compressed(&mut bytes).unwrap();
+        let back = R1CS::<ark_bn254::Fr>::deserialize_compressed(&bytes[..]).unwrap();
+        assert_eq!(back.version(), 2);
+        assert_eq!(r1cs.map_field::<ark_bls12_381::Fr>().unwrap().version(), 2);
+    }
+}
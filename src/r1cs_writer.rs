@@ -0,0 +1,500 @@
+//! Incremental writer for circom `.r1cs` files
+//!
+//! Constraints are streamed to the output as they are pushed. The constraint count and
+//! the size of the constraint section are not known until the end, so placeholders are
+//! written first and [`R1CSWriter::finalize`] seeks back to fill them in. The optional
+//! wire-to-label map section follows the constraints.
+use ark_ff::{BigInteger, PrimeField};
+use byteorder::{LittleEndian, WriteBytesExt};
+
+use ark_std::io::{Seek, SeekFrom, Write};
+
+use std::marker::PhantomData;
+
+use crate::error::{R1CSError, Result};
+use crate::r1cs_reader::{Header, Matrix, R1CSFile};
+use crate::ConstraintVec;
+
+/// Positions of the placeholders written by [`R1CSWriter::write_header`]
+struct Placeholders {
+    field_size: u32,
+    n_wires: u32,
+    num_sections: u64,
+    n_constraints: u64,
+    constraints_size: u64,
+    constraints_start: u64,
+}
+
+pub struct R1CSWriter<W, F> {
+    writer: W,
+    placeholders: Option<Placeholders>,
+    n_constraints: u32,
+    /// End of the constraint section, once a section has been written after it
+    constraints_end: Option<u64>,
+    n_sections: u32,
+    version: u32,
+    _field: PhantomData<F>,
+}
+
+impl<W: Write + Seek, F: PrimeField> R1CSWriter<W, F> {
+    pub fn new(writer: W) -> Self {
+        R1CSWriter {
+            writer,
+            placeholders: None,
+            n_constraints: 0,
+            constraints_end: None,
+            n_sections: 2,
+            version: 1,
+            _field: PhantomData,
+        }
+    }
+
+    /// Declares `version`, 1 or 2, in the file preamble instead of 1. The writer has no
+    /// custom gate sections, so this only matters to readers that check the version.
+    pub fn with_version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Writes the file preamble, the header section and the start of the constraint
+    /// section. `header.n_constraints` is ignored and replaced on [`Self::finalize`].
+    pub fn write_header(&mut self, header: &Header) -> Result<()> {
+        if self.placeholders.is_some() {
+            return Err(R1CSError::WriterState("header already written"));
+        }
+        if header.prime_size.len() != header.field_size as usize {
+            return Err(R1CSError::InvalidFieldSize(header.field_size));
+        }
+        if self.version != 1 && self.version != 2 {
+            return Err(R1CSError::UnsupportedVersion(self.version));
+        }
+
+        let w = &mut self.writer;
+        w.write_all(b"r1cs")?;
+        w.write_u32::<LittleEndian>(self.version)?;
+        let num_sections = w.stream_position()?;
+        w.write_u32::<LittleEndian>(2)?;
+
+        w.write_u32::<LittleEndian>(1)?;
+        w.write_u64::<LittleEndian>(32 + header.field_size as u64)?;
+        w.write_u32::<LittleEndian>(header.field_size)?;
+        w.write_all(&header.prime_size)?;
+        w.write_u32::<LittleEndian>(header.n_wires)?;
+        w.write_u32::<LittleEndian>(header.n_pub_out)?;
+        w.write_u32::<LittleEndian>(header.n_pub_in)?;
+        w.write_u32::<LittleEndian>(header.n_prv_in)?;
+        w.write_u64::<LittleEndian>(header.n_labels)?;
+        let n_constraints = w.stream_position()?;
+        w.write_u32::<LittleEndian>(0)?;
+
+        w.write_u32::<LittleEndian>(2)?;
+        let constraints_size = w.stream_position()?;
+        w.write_u64::<LittleEndian>(0)?;
+        let constraints_start = w.stream_position()?;
+
+        self.placeholders = Some(Placeholders {
+            field_size: header.field_size,
+            n_wires: header.n_wires,
+            num_sections,
+            n_constraints,
+            constraints_size,
+            constraints_start,
+        });
+        Ok(())
+    }
+
+    /// Appends the constraint `a * b = c`
+    pub fn push_constraint(
+        &mut self,
+        a: &ConstraintVec<F>,
+        b: &ConstraintVec<F>,
+        c: &ConstraintVec<F>,
+    ) -> Result<()> {
+        let (field_size, n_wires) = match &self.placeholders {
+            Some(placeholders) => (placeholders.field_size as usize, placeholders.n_wires),
+            None => return Err(R1CSError::WriterState("header not written")),
+        };
+        if self.constraints_end.is_some() {
+            return Err(R1CSError::WriterState("constraint section already closed"));
+        }
+        // Check the whole constraint before writing any of it, with the limits
+        // `read_constraint_vec` applies, so that a rejected constraint leaves the file
+        // as it was
+        let constraint = self.n_constraints as usize;
+        let n_wires = n_wires as usize;
+        for (lc, matrix) in [a, b, c].iter().zip([Matrix::A, Matrix::B, Matrix::C]) {
+            if lc.len() > n_wires {
+                return Err(R1CSError::ConstraintVecTooLong {
+                    constraint,
+                    matrix,
+                    len: lc.len(),
+                    n_wires,
+                });
+            }
+            if let Some((wire, _)) = lc.iter().find(|(index, _)| *index >= n_wires) {
+                return Err(R1CSError::WireOutOfRange {
+                    constraint,
+                    matrix,
+                    wire: *wire,
+                    n_wires,
+                });
+            }
+        }
+        for lc in [a, b, c] {
+            write_constraint_vec(&mut self.writer, lc, field_size)?;
+        }
+        self.n_constraints += 1;
+        Ok(())
+    }
+
+    /// Closes the constraint section and writes the wire-to-label map section, which
+    /// must hold one label per wire
+    pub fn write_wire_to_label(&mut self, labels: &[u64]) -> Result<()> {
+        let n_wires = match &self.placeholders {
+            Some(placeholders) => placeholders.n_wires,
+            None => return Err(R1CSError::WriterState("header not written")),
+        };
+        if self.constraints_end.is_some() {
+            return Err(R1CSError::WriterState("wire to label map already written"));
+        }
+        if labels.len() != n_wires as usize {
+            return Err(R1CSError::InvalidSectionSize {
+                section_type: 3,
+                size: labels.len() as u64 * 8,
+            });
+        }
+
+        self.constraints_end = Some(self.writer.stream_position()?);
+        self.writer.write_u32::<LittleEndian>(3)?;
+        self.writer
+            .write_u64::<LittleEndian>(labels.len() as u64 * 8)?;
+        for label in labels {
+            self.writer.write_u64::<LittleEndian>(*label)?;
+        }
+        self.n_sections += 1;
+        Ok(())
+    }
+
+    /// Fills in the section count, the constraint count and the constraint section
+    /// size, leaving the writer positioned at the end of the file
+    pub fn finalize(mut self) -> Result<W> {
+        let placeholders = self
+            .placeholders
+            .take()
+            .ok_or(R1CSError::WriterState("header not written"))?;
+        let end = self.writer.stream_position()?;
+        let constraints_end = self.constraints_end.unwrap_or(end);
+
+        self.writer
+            .seek(SeekFrom::Start(placeholders.num_sections))?;
+        self.writer.write_u32::<LittleEndian>(self.n_sections)?;
+        self.writer
+            .seek(SeekFrom::Start(placeholders.n_constraints))?;
+        self.writer.write_u32::<LittleEndian>(self.n_constraints)?;
+        self.writer
+            .seek(SeekFrom::Start(placeholders.constraints_size))?;
+        self.writer
+            .write_u64::<LittleEndian>(constraints_end - placeholders.constraints_start)?;
+        self.writer.seek(SeekFrom::Start(end))?;
+        Ok(self.writer)
+    }
+}
+
+fn write_constraint_vec<W: Write, F: PrimeField>(
+    mut writer: W,
+    lc: &ConstraintVec<F>,
+    field_size: usize,
+) -> Result<()> {
+    // Both fit in 32 bits, being bounded by the header's `n_wires`
+    writer.write_u32::<LittleEndian>(lc.len() as u32)?;
+    for (index, coeff) in lc {
+        writer.write_u32::<LittleEndian>(*index as u32)?;
+
+        let mut bytes = coeff.into_bigint().to_bytes_le();
+        if bytes.iter().skip(field_size).any(|b| *b != 0) {
+            return Err(R1CSError::InvalidFieldSize(field_size as u32));
+        }
+        bytes.resize(field_size, 0);
+        writer.write_all(&bytes)?;
+    }
+    Ok(())
+}
+
+impl<F: PrimeField> R1CSFile<F> {
+    /// Writes the header, the constraints and the wire-to-label map, if any, in circom's
+    /// binary format, with the version the file was read with. Fails with
+    /// [`R1CSError::CustomGates`] for files with custom gates, whose sections are not
+    /// kept by the reader and so cannot be written back.
+    pub fn write<W: Write + Seek>(&self, writer: W) -> Result<W> {
+        if self.has_custom_gates() {
+            return Err(R1CSError::CustomGates);
+        }
+        let mut r1cs_writer = R1CSWriter::new(writer).with_version(self.version);
+        r1cs_writer.write_header(&self.header)?;
+        for (a, b, c) in &self.constraints {
+            r1cs_writer.push_constraint(a, b, c)?;
+        }
+        if let Some(labels) = &self.wire_to_label {
+            r1cs_writer.write_wire_to_label(labels)?;
+        }
+        r1cs_writer.finalize()
+    }
+
+    /// Number of bytes [`Self::write`] produces
+    pub fn serialized_size(&self) -> u64 {
+        // Section type and size
+        let section = 4 + 8;
+        let field_size = self.header.field_size as u64;
+        let vec_size = |lc: &ConstraintVec<F>| 4 + lc.len() as u64 * (4 + field_size);
+
+        let header = section + 32 + field_size;
+        let constraints = section
+            + self
+                .constraints
+                .iter()
+                .map(|(a, b, c)| vec_size(a) + vec_size(b) + vec_size(c))
+                .sum::<u64>();
+        let labels = self
+            .wire_to_label
+            .as_ref()
+            .map_or(0, |labels| section + labels.len() as u64 * 8);
+        // Magic, version and section count
+        12 + header + constraints + labels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use ark_std::io::Cursor;
+
+    use crate::Constraints;
+
+    /// Header of a system with one public output and `n_wires - 2` private inputs
+    fn header(n_wires: u32, n_constraints: u32) -> Header {
+        Header {
+            field_size: 32,
+            prime_size: Fr::MODULUS.to_bytes_le(),
+            n_wires,
+            n_pub_out: 1,
+            n_pub_in: 0,
+            n_prv_in: n_wires - 2,
+            n_labels: n_wires as u64,
+            n_constraints,
+        }
+    }
+
+    /// `w[i] * (i * w[i + 1] - 1) = w[i + 2]` over the private wires, with a coefficient
+    /// that changes from row to row
+    fn products(n_wires: usize) -> Vec<Constraints<Fr>> {
+        (2..n_wires - 2)
+            .map(|i| {
+                (
+                    vec![(i, Fr::from(1u64))],
+                    vec![(i + 1, Fr::from(i as u64)), (0, -Fr::from(1u64))],
+                    vec![(i + 2, Fr::from(1u64))],
+                )
+            })
+            .collect()
+    }
+
+    fn streamed(n_wires: u32, labels: Option<&[u64]>) -> Vec<u8> {
+        let mut writer = R1CSWriter::<_, Fr>::new(Cursor::new(Vec::new()));
+        // the count is only known at the end
+        writer.write_header(&header(n_wires, 0)).unwrap();
+        for (a, b, c) in products(n_wires as usize) {
+            writer.push_constraint(&a, &b, &c).unwrap();
+        }
+        if let Some(labels) = labels {
+            writer.write_wire_to_label(labels).unwrap();
+        }
+        writer.finalize().unwrap().into_inner()
+    }
+
+    #[test]
+    fn streamed_write_matches_writing_the_whole_file() {
+        let labels: Vec<u64> = (0..8).collect();
+        for labels in [None, Some(labels)] {
+            let constraints = products(8);
+            let file = R1CSFile::from_parts(
+                1,
+                header(8, constraints.len() as u32),
+                constraints,
+                labels.clone(),
+            )
+            .unwrap();
+            let whole = file.write(Cursor::new(Vec::new())).unwrap().into_inner();
+            assert_eq!(whole.len() as u64, file.serialized_size());
+
+            let bytes = streamed(8, labels.as_deref());
+            assert_eq!(bytes, whole);
+            let read = R1CSFile::<Fr>::new(Cursor::new(&bytes)).unwrap();
+            assert_eq!(read.header.n_constraints, 4);
+            assert_eq!(read.constraints, file.constraints);
+            assert_eq!(read.wire_to_label, labels);
+        }
+    }
+
+    #[test]
+    fn writer_calls_must_come_in_order() {
+        let (a, b, c) = products(8).remove(0);
+        let mut writer = R1CSWriter::<_, Fr>::new(Cursor::new(Vec::new()));
+        assert!(matches!(
+            writer.push_constraint(&a, &b, &c),
+            Err(R1CSError::WriterState("header not written"))
+        ));
+        writer.write_header(&header(8, 0)).unwrap();
+        assert!(matches!(
+            writer.write_header(&header(8, 0)),
+            Err(R1CSError::WriterState("header already written"))
+        ));
+        assert!(matches!(
+            writer.write_wire_to_label(&[0, 1]),
+            Err(R1CSError::InvalidSectionSize {
+                section_type: 3,
+                ..
+            })
+        ));
+        writer.write_wire_to_label(&[0; 8]).unwrap();
+        assert!(matches!(
+            writer.push_constraint(&a, &b, &c),
+            Err(R1CSError::WriterState("constraint section already closed"))
+        ));
+        assert!(matches!(
+            R1CSWriter::<_, Fr>::new(Cursor::new(Vec::new())).finalize(),
+            Err(R1CSError::WriterState("header not written"))
+        ));
+    }
+
+    #[test]
+    fn pushed_wires_must_be_in_the_header() {
+        let one = Fr::from(1u64);
+        let mut writer = R1CSWriter::<_, Fr>::new(Cursor::new(Vec::new()));
+        writer.write_header(&header(4, 0)).unwrap();
+        writer
+            .push_constraint(&vec![(3, one)], &vec![(0, one)], &vec![])
+            .unwrap();
+        assert!(matches!(
+            writer.push_constraint(&vec![(1, one)], &vec![(4, one)], &vec![]),
+            Err(R1CSError::WireOutOfRange {
+                constraint: 1,
+                matrix: Matrix::B,
+                wire: 4,
+                n_wires: 4
+            })
+        ));
+        assert!(matches!(
+            writer.push_constraint(&vec![], &vec![], &vec![(1, one); 5]),
+            Err(R1CSError::ConstraintVecTooLong {
+                constraint: 1,
+                matrix: Matrix::C,
+                len: 5,
+                n_wires: 4
+            })
+        ));
+
+        // the rejected constraints left the file readable
+        let bytes = writer.finalize().unwrap().into_inner();
+        let file = R1CSFile::<Fr>::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(file.constraints.len(), 1);
+    }
+
+    #[test]
+    fn empty_circuit_has_an_empty_constraint_section() {
+        let mut writer = R1CSWriter::<_, Fr>::new(Cursor::new(Vec::new()));
+        writer.write_header(&header(2, 7)).unwrap();
+        let bytes = writer.finalize().unwrap().into_inner();
+        let read = R1CSFile::<Fr>::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(read.header.n_constraints, 0);
+        assert_eq!(read.section_table()[1], (2, 100, 0));
+    }
+
+    #[test]
+    fn round_trip_keeps_the_label_map() {
+        let file =
+            R1CSFile::<Fr>::new(std::fs::File::open("./test-vectors/mycircuit.r1cs").unwrap())
+                .unwrap();
+        let labels = file.wire_to_label.clone().unwrap();
+
+        let written = file.write(Cursor::new(Vec::new())).unwrap().into_inner();
+        let read = R1CSFile::<Fr>::new(Cursor::new(&written)).unwrap();
+        assert_eq!(read.wire_to_label, Some(labels.clone()));
+        assert_eq!(read.constraints, file.constraints);
+        assert_eq!(
+            read.write(Cursor::new(Vec::new())).unwrap().into_inner(),
+            written
+        );
+        let (section_type, _, size) = read.section_table()[2];
+        assert_eq!((section_type, size), (3, labels.len() as u64 * 8));
+    }
+
+    #[test]
+    fn no_label_section_without_a_label_map() {
+        let mut file =
+            R1CSFile::<Fr>::new(std::fs::File::open("./test-vectors/mycircuit.r1cs").unwrap())
+                .unwrap();
+        file.wire_to_label = None;
+        let written = file.write(Cursor::new(Vec::new())).unwrap().into_inner();
+        assert_eq!(written.len() as u64, file.serialized_size());
+
+        let read = R1CSFile::<Fr>::new(Cursor::new(written)).unwrap();
+        assert_eq!(read.wire_to_label, None);
+        assert_eq!(read.section_table().len(), 2);
+        assert_eq!(read.constraints, file.constraints);
+    }
+
+    fn written_len<F: PrimeField>(file: &R1CSFile<F>) -> u64 {
+        file.write(Cursor::new(Vec::new()))
+            .unwrap()
+            .into_inner()
+            .len() as u64
+    }
+
+    #[test]
+    fn serialized_size_matches_the_written_bytes() {
+        for path in ["mycircuit.r1cs", "passthrough.r1cs", "primes/bn128.r1cs"] {
+            let file = R1CSFile::<Fr>::new(
+                std::fs::File::open(format!("./test-vectors/{}", path)).unwrap(),
+            )
+            .unwrap();
+            assert_eq!(file.serialized_size(), written_len(&file), "{}", path);
+        }
+
+        // 4-byte coefficients
+        let m31 = R1CSFile::<crate::field::M31>::new(
+            std::fs::File::open("./test-vectors/primes/m31.r1cs").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(m31.serialized_size(), written_len(&m31));
+    }
+
+    #[test]
+    fn serialized_size_of_a_built_file() {
+        let constraints = products(8);
+        let file = R1CSFile::from_parts(1, header(8, 4), constraints, None).unwrap();
+        // 12 byte prelude, 12 + 64 byte header section and 12 + 4 * 3 * 4 bytes of
+        // vector lengths plus 4 * 4 terms of 36 bytes
+        assert_eq!(file.serialized_size(), 12 + 76 + 12 + 48 + 16 * 36);
+        assert_eq!(file.serialized_size(), written_len(&file));
+    }
+
+    #[test]
+    fn refuses_to_write_custom_gates() {
+        let file =
+            R1CSFile::<Fr>::new(std::fs::File::open("./test-vectors/custom-gates.r1cs").unwrap())
+                .unwrap();
+        assert!(matches!(
+            file.write(Cursor::new(Vec::new())),
+            Err(R1CSError::CustomGates)
+        ));
+    }
+
+    #[test]
+    fn writes_the_version_it_was_read_with() {
+        let file = R1CSFile::<Fr>::from_parts(2, header(8, 4), products(8), None).unwrap();
+        let bytes = file.write(Cursor::new(Vec::new())).unwrap().into_inner();
+        assert_eq!(bytes[4..8], 2u32.to_le_bytes());
+        assert_eq!(R1CSFile::<Fr>::new(Cursor::new(bytes)).unwrap().version, 2);
+    }
+}
@@ -0,0 +1,209 @@
+//! Import of Groth16 proofs and verifying keys in snarkjs' JSON format
+//!
+//! snarkjs writes curve points as arrays of decimal strings holding the canonical
+//! coordinates, not their Montgomery form, followed by a projective `z` that is `"1"`
+//! for affine points and `"0"` for the point at infinity. G2 coordinates are `[c0, c1]`
+//! pairs in the same order arkworks uses; only the Solidity calldata export swaps them.
+//! Only BN254, snarkjs' `bn128`, is supported.
+use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_ec::AffineRepr;
+use ark_ff::Field;
+use ark_groth16::{Proof, VerifyingKey};
+use serde_json::Value;
+
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::error::{R1CSError, Result};
+use crate::groth16::{read_public_inputs, verify};
+
+fn invalid(what: &str) -> R1CSError {
+    R1CSError::InvalidInput(format!("invalid {} in snarkjs JSON", what))
+}
+
+fn field<'a>(json: &'a Value, key: &str) -> Result<&'a Value> {
+    json.get(key)
+        .ok_or_else(|| R1CSError::InvalidInput(format!("snarkjs JSON has no {}", key)))
+}
+
+fn array<'a>(json: &'a Value, len: usize, what: &str) -> Result<&'a [Value]> {
+    match json.as_array() {
+        Some(values) if values.len() == len => Ok(values),
+        _ => Err(invalid(what)),
+    }
+}
+
+fn fq(json: &Value, what: &str) -> Result<Fq> {
+    json.as_str()
+        .and_then(|s| Fq::from_str(s).ok())
+        .ok_or_else(|| invalid(what))
+}
+
+fn fq2(json: &Value, what: &str) -> Result<Fq2> {
+    let c = array(json, 2, what)?;
+    Ok(Fq2::new(fq(&c[0], what)?, fq(&c[1], what)?))
+}
+
+/// Whether the projective `z` of a point marks it as the point at infinity
+fn is_infinity<T: Field>(z: T) -> Result<bool> {
+    match z {
+        z if z.is_zero() => Ok(true),
+        z if z.is_one() => Ok(false),
+        _ => Err(invalid("projective coordinate")),
+    }
+}
+
+fn g1(json: &Value, what: &str) -> Result<G1Affine> {
+    let p = array(json, 3, what)?;
+    if is_infinity(fq(&p[2], what)?)? {
+        return Ok(G1Affine::zero());
+    }
+    let point = G1Affine::new_unchecked(fq(&p[0], what)?, fq(&p[1], what)?);
+    if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(invalid(what));
+    }
+    Ok(point)
+}
+
+fn g2(json: &Value, what: &str) -> Result<G2Affine> {
+    let p = array(json, 3, what)?;
+    if is_infinity(fq2(&p[2], what)?)? {
+        return Ok(G2Affine::zero());
+    }
+    let point = G2Affine::new_unchecked(fq2(&p[0], what)?, fq2(&p[1], what)?);
+    if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(invalid(what));
+    }
+    Ok(point)
+}
+
+/// Parses a snarkjs `proof.json`
+pub fn read_proof<R: Read>(reader: R) -> Result<Proof<Bn254>> {
+    let json: Value = serde_json::from_reader(reader)?;
+    Ok(Proof {
+        a: g1(field(&json, "pi_a")?, "pi_a")?,
+        b: g2(field(&json, "pi_b")?, "pi_b")?,
+        c: g1(field(&json, "pi_c")?, "pi_c")?,
+    })
+}
+
+/// Parses a snarkjs `verification_key.json`. `IC` must hold one point more than
+/// `nPublic`, for the constant one.
+pub fn read_verifying_key<R: Read>(reader: R) -> Result<VerifyingKey<Bn254>> {
+    let json: Value = serde_json::from_reader(reader)?;
+    let gamma_abc_g1 = field(&json, "IC")?
+        .as_array()
+        .ok_or_else(|| invalid("IC"))?
+        .iter()
+        .map(|point| g1(point, "IC"))
+        .collect::<Result<Vec<_>>>()?;
+    // Even a circuit without public signals has the point for the constant one
+    if gamma_abc_g1.is_empty() {
+        return Err(invalid("IC"));
+    }
+    if let Some(n_public) = json.get("nPublic") {
+        if n_public.as_u64() != Some(gamma_abc_g1.len() as u64 - 1) {
+            return Err(invalid("nPublic"));
+        }
+    }
+    Ok(VerifyingKey {
+        alpha_g1: g1(field(&json, "vk_alpha_1")?, "vk_alpha_1")?,
+        beta_g2: g2(field(&json, "vk_beta_2")?, "vk_beta_2")?,
+        gamma_g2: g2(field(&json, "vk_gamma_2")?, "vk_gamma_2")?,
+        delta_g2: g2(field(&json, "vk_delta_2")?, "vk_delta_2")?,
+        gamma_abc_g1,
+    })
+}
+
+/// Verifies a snarkjs `proof.json` against a `verification_key.json` and the
+/// `public.json` written alongside the proof
+pub fn verify_files(
+    vk_path: impl AsRef<Path>,
+    proof_path: impl AsRef<Path>,
+    public_path: impl AsRef<Path>,
+) -> Result<bool> {
+    let vk = read_verifying_key(BufReader::new(File::open(vk_path)?))?;
+    let proof = read_proof(BufReader::new(File::open(proof_path)?))?;
+    let public_inputs: Vec<Fr> = read_public_inputs(BufReader::new(File::open(public_path)?))?;
+    verify(&vk, &public_inputs, &proof)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_std::io::Cursor;
+
+    const VK: &str = "./test-vectors/snarkjs/verification_key.json";
+    const PROOF: &str = "./test-vectors/snarkjs/proof.json";
+    const PUBLIC: &str = "./test-vectors/snarkjs/public.json";
+
+    #[test]
+    fn verifies_snarkjs_proof() {
+        assert!(verify_files(VK, PROOF, PUBLIC).unwrap());
+    }
+
+    #[test]
+    fn rejects_other_public_input() {
+        let vk = read_verifying_key(File::open(VK).unwrap()).unwrap();
+        let proof = read_proof(File::open(PROOF).unwrap()).unwrap();
+        assert!(!verify(&vk, &[Fr::from(34u64)], &proof).unwrap());
+    }
+
+    #[test]
+    fn rejects_empty_ic() {
+        let vk = r#"{"IC": [], "nPublic": 0}"#;
+        assert!(matches!(
+            read_verifying_key(Cursor::new(vk)),
+            Err(R1CSError::InvalidInput(_))
+        ));
+    }
+
+    fn proof_json() -> Value {
+        serde_json::from_reader(File::open(PROOF).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn reads_g2_limbs_in_arkworks_order() {
+        let proof = read_proof(File::open(PROOF).unwrap()).unwrap();
+        let json = proof_json();
+        // the first limb of each pair is c0
+        assert_eq!(proof.b.x.c0, fq(&json["pi_b"][0][0], "pi_b").unwrap());
+
+        // the calldata order, with each pair swapped, is not a point of the curve
+        let mut swapped = json;
+        for coordinate in 0..2 {
+            swapped["pi_b"][coordinate]
+                .as_array_mut()
+                .unwrap()
+                .reverse();
+        }
+        assert!(matches!(
+            read_proof(Cursor::new(swapped.to_string())),
+            Err(R1CSError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn a_zero_projective_coordinate_is_the_point_at_infinity() {
+        let infinity: Value = serde_json::json!(["0", "1", "0"]);
+        assert!(g1(&infinity, "pi_a").unwrap().is_zero());
+        let scaled: Value = serde_json::json!(["1", "2", "2"]);
+        assert!(matches!(
+            g1(&scaled, "pi_a"),
+            Err(R1CSError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn n_public_must_match_ic() {
+        let mut vk: Value = serde_json::from_reader(File::open(VK).unwrap()).unwrap();
+        assert!(read_verifying_key(Cursor::new(vk.to_string())).is_ok());
+        vk["nPublic"] = 2.into();
+        assert!(matches!(
+            read_verifying_key(Cursor::new(vk.to_string())),
+            Err(R1CSError::InvalidInput(_))
+        ));
+    }
+}
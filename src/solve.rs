@@ -0,0 +1,176 @@
+//! Experimental derivation of witness values from the constraints themselves
+//!
+//! [`R1CS::solve_linear`] fills in wires that a single constraint determines once its
+//! other wires are known, such as `x = 2 * a + b` or `a * b = x` with `a` and `b` known.
+//! It only handles this triangular subset: wires that are only pinned down by several
+//! constraints together, or by a nonlinear relation such as `x * x = a`, stay unknown
+//! and still need the witness calculator.
+use ark_ff::PrimeField;
+
+use crate::error::{R1CSError, Result};
+use crate::transform::canonicalize_constraint;
+use crate::{ConstraintVec, R1CS};
+
+/// Sum of the terms of `lc` on wires with a value, and the terms on the other wires
+fn partial_dot<F: PrimeField>(
+    lc: &ConstraintVec<F>,
+    partial: &[Option<F>],
+) -> (F, ConstraintVec<F>) {
+    let mut known = F::zero();
+    let mut unknown = Vec::new();
+    for (index, coeff) in lc {
+        match partial[*index] {
+            Some(value) => known += *coeff * value,
+            None => unknown.push((*index, *coeff)),
+        }
+    }
+    (known, unknown)
+}
+
+impl<F: PrimeField> R1CS<F> {
+    /// Experimental: propagates the values in `partial`, one entry per wire, through
+    /// every constraint that leaves a single wire unknown on a side it determines
+    /// linearly, until no more wires can be derived. Wire 0 is set to one if missing.
+    /// Returns the number of wires filled in.
+    ///
+    /// Known values are trusted: constraints that end up fully known are not checked, so
+    /// run [`Self::check_witness`] on the completed witness. See the [module
+    /// documentation](self) for what is not derived.
+    pub fn solve_linear(&self, partial: &mut [Option<F>]) -> Result<usize> {
+        if partial.len() != self.num_variables {
+            return Err(R1CSError::WitnessLengthMismatch {
+                expected: self.num_variables,
+                actual: partial.len(),
+            });
+        }
+        if let Some(one) = partial.first_mut() {
+            one.get_or_insert(F::one());
+        }
+
+        // Merging repeated wires first means an unknown wire has a nonzero coefficient
+        let constraints: Vec<_> = self
+            .constraints
+            .iter()
+            .map(canonicalize_constraint)
+            .collect();
+        let mut pending: Vec<usize> = (0..constraints.len()).collect();
+        let mut solved = 0;
+        loop {
+            let before = solved;
+            pending.retain(|i| {
+                let (a, b, c) = &constraints[*i];
+                let (a0, a) = partial_dot(a, partial);
+                let (b0, b) = partial_dot(b, partial);
+                let (c0, c) = partial_dot(c, partial);
+                let product = match (&a[..], &b[..]) {
+                    ([], []) => Some(a0 * b0),
+                    ([], _) if a0.is_zero() => Some(F::zero()),
+                    (_, []) if b0.is_zero() => Some(F::zero()),
+                    _ => None,
+                };
+
+                let solution = match (&a[..], &b[..], &c[..], product) {
+                    (_, _, [(wire, coeff)], Some(product)) => Some((*wire, (product - c0) / coeff)),
+                    ([(wire, coeff)], [], [], _) if !b0.is_zero() => {
+                        Some((*wire, (c0 / b0 - a0) / coeff))
+                    }
+                    ([], [(wire, coeff)], [], _) if !a0.is_zero() => {
+                        Some((*wire, (c0 / a0 - b0) / coeff))
+                    }
+                    _ => None,
+                };
+                match solution {
+                    Some((wire, value)) => {
+                        partial[wire] = Some(value);
+                        solved += 1;
+                        a.len() + b.len() + c.len() > 1
+                    }
+                    None => !(a.is_empty() && b.is_empty() && c.is_empty()),
+                }
+            });
+            if solved == before {
+                return Ok(solved);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+
+    fn fr(value: u64) -> Fr {
+        Fr::from(value)
+    }
+
+    /// `x = 2 * a + b`, `a * x = y` and `y + 3 = out` over wires `[1, out, a, b, x, y]`
+    fn linear_chain() -> R1CS<Fr> {
+        R1CS::for_test(
+            1,
+            2,
+            2,
+            vec![
+                (
+                    vec![(2, fr(2)), (3, fr(1))],
+                    vec![(0, fr(1))],
+                    vec![(4, fr(1))],
+                ),
+                (vec![(2, fr(1))], vec![(4, fr(1))], vec![(5, fr(1))]),
+                (
+                    vec![(5, fr(1)), (0, fr(3))],
+                    vec![(0, fr(1))],
+                    vec![(1, fr(1))],
+                ),
+            ],
+        )
+    }
+
+    #[test]
+    fn derives_wires_from_the_inputs() {
+        let r1cs = linear_chain();
+        let mut partial = vec![None, None, Some(fr(3)), Some(fr(5)), None, None];
+        assert_eq!(r1cs.solve_linear(&mut partial).unwrap(), 3);
+
+        let witness: Vec<Fr> = partial.into_iter().map(Option::unwrap).collect();
+        assert_eq!(witness, [1, 36, 3, 5, 11, 33].map(fr));
+        assert_eq!(r1cs.check_witness(&witness), Ok(()));
+    }
+
+    #[test]
+    fn divides_by_a_known_factor() {
+        // a * x = y with a and y known
+        let r1cs = R1CS {
+            constraints: vec![(vec![(2, fr(1))], vec![(4, fr(1))], vec![(5, fr(1))])],
+            ..linear_chain()
+        };
+        let mut partial = vec![None, None, Some(fr(3)), None, None, Some(fr(33))];
+        assert_eq!(r1cs.solve_linear(&mut partial).unwrap(), 1);
+        assert_eq!(partial[4], Some(fr(11)));
+    }
+
+    #[test]
+    fn leaves_nonlinear_wires_unknown() {
+        // x * x = a cannot be solved for x without a square root
+        let r1cs = R1CS {
+            constraints: vec![(vec![(4, fr(1))], vec![(4, fr(1))], vec![(2, fr(1))])],
+            ..linear_chain()
+        };
+        let mut partial = vec![None, None, Some(fr(9)), None, None, None];
+        assert_eq!(r1cs.solve_linear(&mut partial).unwrap(), 0);
+        assert_eq!(partial[0], Some(fr(1)));
+        assert_eq!(partial[4], None);
+    }
+
+    #[test]
+    fn needs_one_entry_per_wire() {
+        let mut partial = vec![None; 5];
+        assert!(matches!(
+            linear_chain().solve_linear(&mut partial),
+            Err(R1CSError::WitnessLengthMismatch {
+                expected: 6,
+                actual: 5
+            })
+        ));
+    }
+}
@@ -0,0 +1,299 @@
+//! Statistics over the constraints of an R1CS
+use ark_ff::PrimeField;
+use ark_std::io::Read;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::error::Result;
+use crate::transform::canonicalize_constraint;
+use crate::{ConstraintStream, ConstraintVec, Constraints, R1CS};
+
+/// Number of nonzero entries of the A, B and C matrices a constraint contributes
+fn nonzero_counts<F: PrimeField>(constraint: &Constraints<F>) -> (usize, usize, usize) {
+    let (a, b, c) = canonicalize_constraint(constraint);
+    (a.len(), b.len(), c.len())
+}
+
+fn add(
+    (a, b, c): (usize, usize, usize),
+    (x, y, z): (usize, usize, usize),
+) -> (usize, usize, usize) {
+    (a + x, b + y, c + z)
+}
+
+/// Coefficient distribution of one of the A, B or C matrices.
+///
+/// `min` and `max` compare coefficients by their canonical integer value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MatrixStats<F> {
+    pub num_terms: usize,
+    pub unique: usize,
+    pub ones: usize,
+    pub minus_ones: usize,
+    pub min: Option<F>,
+    pub max: Option<F>,
+    /// Most frequent coefficient with its count, the smallest value winning ties
+    pub most_common: Option<(F, usize)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CoeffStats<F> {
+    pub a: MatrixStats<F>,
+    pub b: MatrixStats<F>,
+    pub c: MatrixStats<F>,
+}
+
+fn matrix_stats<'a, F: PrimeField>(
+    rows: impl Iterator<Item = &'a ConstraintVec<F>>,
+) -> MatrixStats<F> {
+    let mut counts = HashMap::<F, usize>::new();
+    let mut num_terms = 0;
+    for row in rows {
+        for (_, coeff) in row {
+            *counts.entry(*coeff).or_insert(0) += 1;
+            num_terms += 1;
+        }
+    }
+
+    let count = |value: F| counts.get(&value).copied().unwrap_or(0);
+    let most_common = counts
+        .iter()
+        .max_by(|(x, n), (y, m)| n.cmp(m).then(y.cmp(x)))
+        .map(|(value, count)| (*value, *count));
+
+    MatrixStats {
+        num_terms,
+        unique: counts.len(),
+        ones: count(F::one()),
+        minus_ones: count(-F::one()),
+        min: counts.keys().min().copied(),
+        max: counts.keys().max().copied(),
+        most_common,
+    }
+}
+
+/// Sizes of an R1CS, as printed by `r1cs-info`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct R1CSSummary {
+    pub num_constraints: usize,
+    pub num_variables: usize,
+    pub num_pub_out: usize,
+    pub num_pub_in: usize,
+    pub num_aux: usize,
+    /// Number of terms in the A, B and C matrices
+    pub num_terms: (usize, usize, usize),
+}
+
+impl fmt::Display for R1CSSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "constraints:    {}", self.num_constraints)?;
+        writeln!(f, "wires:          {}", self.num_variables)?;
+        writeln!(f, "public outputs: {}", self.num_pub_out)?;
+        writeln!(f, "public inputs:  {}", self.num_pub_in)?;
+        writeln!(f, "private wires:  {}", self.num_aux)?;
+        write!(
+            f,
+            "terms (A/B/C):  {} / {} / {}",
+            self.num_terms.0, self.num_terms.1, self.num_terms.2
+        )
+    }
+}
+
+impl<F: PrimeField> R1CS<F> {
+    pub fn summary(&self) -> R1CSSummary {
+        let terms = |f: fn(&Constraints<F>) -> &ConstraintVec<F>| {
+            self.constraints.iter().map(|c| f(c).len()).sum()
+        };
+        R1CSSummary {
+            num_constraints: self.constraints.len(),
+            num_variables: self.num_variables,
+            num_pub_out: self.num_pub_out,
+            num_pub_in: self.num_pub_in,
+            num_aux: self.num_aux,
+            num_terms: (terms(|c| &c.0), terms(|c| &c.1), terms(|c| &c.2)),
+        }
+    }
+
+    /// Number of terms in the A, B and C vectors of constraint `i`, which must exist
+    pub fn constraint_arity(&self, i: usize) -> (usize, usize, usize) {
+        let (a, b, c) = &self.constraints[i];
+        (a.len(), b.len(), c.len())
+    }
+
+    /// Number of constraints with each total of A, B and C terms, in increasing order
+    /// of the total
+    pub fn arity_histogram(&self) -> BTreeMap<usize, usize> {
+        let mut histogram = BTreeMap::new();
+        for (a, b, c) in &self.constraints {
+            *histogram.entry(a.len() + b.len() + c.len()).or_insert(0) += 1;
+        }
+        histogram
+    }
+
+    /// Number of nonzero entries of the A, B and C matrices, the `num_non_zero` counts
+    /// of [`Self::matrices`]. Unlike the term counts of [`Self::summary`], repeated
+    /// wires count once and zero coefficients not at all.
+    pub fn num_nonzero(&self) -> (usize, usize, usize) {
+        self.constraints
+            .iter()
+            .map(nonzero_counts)
+            .fold((0, 0, 0), add)
+    }
+
+    pub fn coefficient_stats(&self) -> CoeffStats<F> {
+        let side = |f: fn(&Constraints<F>) -> &ConstraintVec<F>| {
+            matrix_stats(self.constraints.iter().map(f))
+        };
+        CoeffStats {
+            a: side(|c| &c.0),
+            b: side(|c| &c.1),
+            c: side(|c| &c.2),
+        }
+    }
+}
+
+impl<R: Read, F: PrimeField> ConstraintStream<R, F> {
+    /// Computes [`R1CS::num_nonzero`] while reading the remaining constraints, holding
+    /// only one of them in memory at a time
+    pub fn count_nonzero(self) -> Result<(usize, usize, usize)> {
+        let mut counts = (0, 0, 0);
+        for constraint in self {
+            counts = add(counts, nonzero_counts(&constraint?));
+        }
+        Ok(counts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use ark_ff::BigInteger;
+    use ark_std::io::Cursor;
+
+    use crate::r1cs_reader::Header;
+    use crate::R1CSFile;
+
+    fn lc(terms: &[(usize, i64)]) -> ConstraintVec<Fr> {
+        terms
+            .iter()
+            .map(|(wire, coeff)| (*wire, Fr::from(*coeff)))
+            .collect()
+    }
+
+    /// Mostly `±1` coefficients, with a few 2s and a 5, over five wires
+    fn mostly_unit() -> R1CS<Fr> {
+        R1CS::for_test(
+            1,
+            0,
+            3,
+            vec![
+                (lc(&[(1, 1), (2, -1)]), lc(&[(0, 1)]), lc(&[(3, 2)])),
+                (lc(&[(2, 1)]), lc(&[(3, -1)]), lc(&[(4, 1), (0, 5)])),
+                (lc(&[(3, 2)]), lc(&[(0, 1)]), lc(&[(1, -1)])),
+            ],
+        )
+    }
+
+    #[test]
+    fn counts_the_coefficient_distribution() {
+        let stats = mostly_unit().coefficient_stats();
+        assert_eq!(
+            stats.a,
+            MatrixStats {
+                num_terms: 4,
+                unique: 3,
+                ones: 2,
+                minus_ones: 1,
+                min: Some(Fr::from(1i64)),
+                // -1 is p - 1, the largest canonical value
+                max: Some(Fr::from(-1i64)),
+                most_common: Some((Fr::from(1i64), 2)),
+            }
+        );
+        assert_eq!(stats.b.num_terms, 3);
+        assert_eq!(stats.b.unique, 2);
+        assert_eq!((stats.b.ones, stats.b.minus_ones), (2, 1));
+        assert_eq!(stats.c.unique, 4);
+        assert_eq!((stats.c.ones, stats.c.minus_ones), (1, 1));
+        // every C coefficient appears once, so the smallest one wins
+        assert_eq!(stats.c.most_common, Some((Fr::from(1i64), 1)));
+    }
+
+    #[test]
+    fn empty_matrices_have_no_extremes() {
+        let r1cs = mostly_unit().with_constraints::<Fr>(Vec::new());
+        let stats = r1cs.coefficient_stats();
+        assert_eq!(stats.a.num_terms, 0);
+        assert_eq!(stats.a.unique, 0);
+        assert_eq!(
+            (stats.b.min, stats.b.max, stats.c.most_common),
+            (None, None, None)
+        );
+    }
+
+    #[test]
+    fn reports_constraint_arities() {
+        let mut r1cs = mostly_unit();
+        // a dense outlier summing all five wires
+        r1cs.constraints.push((
+            lc(&[(0, 1), (1, 1), (2, 1), (3, 1), (4, 1)]),
+            lc(&[(0, 1)]),
+            lc(&[(4, 1)]),
+        ));
+        assert_eq!(r1cs.constraint_arity(0), (2, 1, 1));
+        assert_eq!(r1cs.constraint_arity(1), (1, 1, 2));
+        assert_eq!(r1cs.constraint_arity(2), (1, 1, 1));
+        assert_eq!(r1cs.constraint_arity(3), (5, 1, 1));
+        assert_eq!(
+            r1cs.arity_histogram().into_iter().collect::<Vec<_>>(),
+            vec![(3, 1), (4, 2), (7, 1)]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn arity_of_a_missing_constraint_panics() {
+        mostly_unit().constraint_arity(3);
+    }
+
+    /// [`mostly_unit`] with a repeated wire and a zero coefficient, written to `.r1cs`
+    fn with_repeats() -> (R1CS<Fr>, Vec<u8>) {
+        let mut r1cs = mostly_unit();
+        r1cs.constraints[0].0.push((1, Fr::from(3i64)));
+        r1cs.constraints[2].2.push((4, Fr::from(0i64)));
+
+        let header = Header {
+            field_size: 32,
+            prime_size: Fr::MODULUS.to_bytes_le(),
+            n_wires: 5,
+            n_pub_out: 1,
+            n_pub_in: 0,
+            n_prv_in: 0,
+            n_labels: 5,
+            n_constraints: 3,
+        };
+        let file = R1CSFile::from_parts(1, header, r1cs.constraints.clone(), None).unwrap();
+        let bytes = file.write(Cursor::new(Vec::new())).unwrap();
+        (r1cs, bytes.into_inner())
+    }
+
+    #[test]
+    fn streamed_nonzero_counts_match_the_loaded_system() {
+        let (r1cs, bytes) = with_repeats();
+        // repeated wire 1 counts once in A, the zero on wire 4 not at all in C
+        assert_eq!(r1cs.num_nonzero(), (4, 3, 4));
+        let stream = ConstraintStream::<_, Fr>::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(stream.count_nonzero().unwrap(), r1cs.num_nonzero());
+    }
+
+    #[test]
+    fn streamed_counts_cover_only_the_remaining_constraints() {
+        let (r1cs, bytes) = with_repeats();
+        let mut stream = ConstraintStream::<_, Fr>::new(Cursor::new(bytes)).unwrap();
+        let first = stream.next().unwrap().unwrap();
+        let rest = r1cs.with_constraints::<Fr>(r1cs.constraints[1..].to_vec());
+        assert_eq!(first, r1cs.constraints[0]);
+        assert_eq!(stream.count_nonzero().unwrap(), rest.num_nonzero());
+    }
+}
@@ -0,0 +1,166 @@
+//! Circom `.sym` file reader
+//!
+//! Each line of a `.sym` file is `label_id,wire_id,component_id,name`, where `wire_id`
+//! is `-1` for signals that were optimised away.
+use ark_ff::PrimeField;
+use ark_std::io::{BufRead, BufReader, Read};
+use std::collections::HashMap;
+
+use crate::error::{R1CSError, Result};
+use crate::R1CS;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    pub label: u64,
+    /// Wire carrying the signal, `None` if it was optimised away
+    pub wire: Option<usize>,
+    pub component: u64,
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SymFile {
+    pub symbols: Vec<Symbol>,
+    wire_names: HashMap<usize, usize>,
+}
+
+impl SymFile {
+    pub fn new<R: Read>(reader: R) -> Result<SymFile> {
+        let mut symbols = Vec::new();
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            symbols.push(parse_symbol(&line)?);
+        }
+        Ok(Self::from_symbols(symbols))
+    }
+
+    pub fn from_symbols(symbols: Vec<Symbol>) -> SymFile {
+        let mut wire_names = HashMap::new();
+        for (i, symbol) in symbols.iter().enumerate() {
+            if let Some(wire) = symbol.wire {
+                // Several signals can share a wire, keep the first one declared
+                wire_names.entry(wire).or_insert(i);
+            }
+        }
+        SymFile {
+            symbols,
+            wire_names,
+        }
+    }
+
+    /// Name of the first signal assigned to `wire`
+    pub fn name(&self, wire: usize) -> Option<&str> {
+        self.wire_names
+            .get(&wire)
+            .map(|i| self.symbols[*i].name.as_str())
+    }
+
+    /// Orders named values into the public input vector of `r1cs`, excluding the
+    /// constant one, as a Groth16 verifier expects it. A public wire can be given under
+    /// the name of any signal assigned to it, and names of other signals are ignored.
+    pub fn public_inputs<F: PrimeField>(
+        &self,
+        r1cs: &R1CS<F>,
+        values: &HashMap<String, F>,
+    ) -> Result<Vec<F>> {
+        let public = 1..r1cs.num_inputs;
+        let mut by_wire = HashMap::new();
+        for symbol in &self.symbols {
+            match (symbol.wire, values.get(&symbol.name)) {
+                (Some(wire), Some(value)) if public.contains(&wire) => {
+                    by_wire.entry(wire).or_insert(*value);
+                }
+                _ => {}
+            }
+        }
+
+        public
+            .map(|wire| {
+                by_wire.get(&wire).copied().ok_or_else(|| {
+                    let name = self.name(wire).map_or(format!("w{}", wire), str::to_string);
+                    R1CSError::MissingPublicInput(name)
+                })
+            })
+            .collect()
+    }
+}
+
+fn parse_symbol(line: &str) -> Result<Symbol> {
+    let invalid = || R1CSError::InvalidSymbol(line.to_string());
+
+    let mut fields = line.trim().splitn(4, ',');
+    let mut next = || fields.next().ok_or_else(invalid);
+    let label = next()?.parse::<u64>().map_err(|_| invalid())?;
+    let wire = next()?.parse::<i64>().map_err(|_| invalid())?;
+    let component = next()?.parse::<u64>().map_err(|_| invalid())?;
+    let name = next()?.to_string();
+
+    Ok(Symbol {
+        label,
+        wire: if wire < 0 { None } else { Some(wire as usize) },
+        component,
+        name,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+
+    /// `out` and `in` are public, `secret` private and `tmp` optimised away
+    fn symbols() -> SymFile {
+        let text = "1,1,0,main.out\n2,2,0,main.in\n3,2,1,main.sub.in\n4,3,0,main.secret\n5,-1,0,main.tmp\n";
+        SymFile::new(text.as_bytes()).unwrap()
+    }
+
+    /// Wires `[1, out, in, secret]` without constraints
+    fn r1cs() -> R1CS<Fr> {
+        R1CS::for_test(1, 1, 1, Vec::new())
+    }
+
+    fn values(named: &[(&str, u64)]) -> HashMap<String, Fr> {
+        named
+            .iter()
+            .map(|(name, value)| (name.to_string(), Fr::from(*value)))
+            .collect()
+    }
+
+    #[test]
+    fn assembles_public_inputs_in_wire_order() {
+        let inputs = symbols()
+            .public_inputs(&r1cs(), &values(&[("main.in", 6), ("main.out", 42)]))
+            .unwrap();
+        assert_eq!(inputs, vec![Fr::from(42u64), Fr::from(6u64)]);
+    }
+
+    #[test]
+    fn accepts_aliases_and_ignores_private_names() {
+        let named = values(&[
+            ("main.out", 42),
+            ("main.sub.in", 6),
+            ("main.secret", 7),
+            ("main.tmp", 1),
+        ]);
+        let inputs = symbols().public_inputs(&r1cs(), &named).unwrap();
+        assert_eq!(inputs, vec![Fr::from(42u64), Fr::from(6u64)]);
+    }
+
+    #[test]
+    fn names_the_missing_public_input() {
+        assert!(matches!(
+            symbols().public_inputs(&r1cs(), &values(&[("main.out", 42)])),
+            Err(R1CSError::MissingPublicInput(name)) if name == "main.in"
+        ));
+
+        // without a symbol for the wire, the wire itself is named
+        let unnamed = SymFile::new("1,1,0,main.out\n".as_bytes()).unwrap();
+        assert!(matches!(
+            unnamed.public_inputs(&r1cs(), &values(&[("main.out", 42)])),
+            Err(R1CSError::MissingPublicInput(name)) if name == "w2"
+        ));
+    }
+}
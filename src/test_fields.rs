@@ -0,0 +1,31 @@
+//! Prime fields for tests, beyond the BN254 and BLS12-381 scalar fields the
+//! dependencies provide
+// the derive checks ark-ff's `asm` feature on the 4-limb fields, which this crate does
+// not declare
+#![allow(unexpected_cfgs)]
+use ark_ff::{Fp256, Fp64, MontBackend, MontConfig};
+use std::convert::TryInto;
+
+#[derive(MontConfig)]
+#[modulus = "18446744069414584321"]
+#[generator = "7"]
+pub struct GoldilocksConfig;
+pub type Goldilocks = Fp64<MontBackend<GoldilocksConfig, 1>>;
+
+#[derive(MontConfig)]
+#[modulus = "115792089210356248762697446949407573530086143415290314195533631308867097853951"]
+#[generator = "2"]
+pub struct Secq256r1Config;
+pub type Secq256r1 = Fp256<MontBackend<Secq256r1Config, 4>>;
+
+#[derive(MontConfig)]
+#[modulus = "28948022309329048855892746252171976963363056481941560715954676764349967630337"]
+#[generator = "5"]
+pub struct PallasConfig;
+pub type Pallas = Fp256<MontBackend<PallasConfig, 4>>;
+
+#[derive(MontConfig)]
+#[modulus = "28948022309329048855892746252171976963363056481941647379679742748393362948097"]
+#[generator = "5"]
+pub struct VestaConfig;
+pub type Vesta = Fp256<MontBackend<VestaConfig, 4>>;
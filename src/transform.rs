@@ -0,0 +1,906 @@
+//! Transformations over the constraints of an R1CS
+use ark_ff::PrimeField;
+use num_bigint::BigUint;
+use std::collections::HashSet;
+use std::ops::Range;
+
+use crate::error::{R1CSError, Result};
+use crate::{ConstraintVec, Constraints, Matrix, R1CSFile, R1CS};
+
+/// Sorts `lc` by wire, summing the coefficients of repeated wires and dropping zero
+/// coefficients, so that equal linear combinations compare equal
+pub(crate) fn normalize_vec<F: PrimeField>(lc: &ConstraintVec<F>) -> ConstraintVec<F> {
+    let mut terms = lc.clone();
+    terms.sort_by_key(|(index, _)| *index);
+
+    let mut normalized: ConstraintVec<F> = Vec::with_capacity(terms.len());
+    for (index, coeff) in terms {
+        match normalized.last_mut() {
+            Some((last, sum)) if *last == index => *sum += coeff,
+            _ => normalized.push((index, coeff)),
+        }
+    }
+    normalized.retain(|(_, coeff)| !coeff.is_zero());
+    normalized
+}
+
+/// Canonical form of a constraint: the terms of each side sorted by wire, repeated
+/// wires merged by summing their coefficients and zero coefficients dropped. Two
+/// constraints with the same terms in different orders canonicalize equal. Hashing,
+/// deduplication and diffing all compare constraints in this form.
+pub fn canonicalize_constraint<F: PrimeField>((a, b, c): &Constraints<F>) -> Constraints<F> {
+    (normalize_vec(a), normalize_vec(b), normalize_vec(c))
+}
+
+pub(crate) fn normalize_constraint<F: PrimeField>(constraint: &mut Constraints<F>) {
+    *constraint = canonicalize_constraint(constraint);
+}
+
+/// Splits `lc` into the coefficient on the constant one wire and the remaining terms
+fn split_constant<F: PrimeField>(lc: &ConstraintVec<F>) -> (F, ConstraintVec<F>) {
+    let mut constant = F::zero();
+    let mut terms = Vec::with_capacity(lc.len());
+    for (index, coeff) in lc {
+        if *index == 0 {
+            constant += coeff;
+        } else {
+            terms.push((*index, *coeff));
+        }
+    }
+    (constant, terms)
+}
+
+impl<F: PrimeField> R1CS<F> {
+    /// Moves the coefficients on the constant one wire out of every constraint.
+    ///
+    /// Returns the system without any wire 0 terms, together with the `(a, b, c)`
+    /// constants of each constraint. A witness satisfies constraint `i` of the original
+    /// system iff `(<A_i, w> + a) * (<B_i, w> + b) = <C_i, w> + c` holds for the result.
+    pub fn extract_constants(&self) -> (R1CS<F>, Vec<(F, F, F)>) {
+        let mut constants = Vec::with_capacity(self.constraints.len());
+        let constraints = self
+            .constraints
+            .iter()
+            .map(|(a, b, c)| {
+                let (a0, a) = split_constant(a);
+                let (b0, b) = split_constant(b);
+                let (c0, c) = split_constant(c);
+                constants.push((a0, b0, c0));
+                (a, b, c)
+            })
+            .collect();
+
+        (self.with_constraints(constraints), constants)
+    }
+
+    /// Replaces every coefficient with `f(wire, coeff)`, in A, B and C alike
+    pub fn map_coefficients(&mut self, f: impl Fn(usize, F) -> F) {
+        for (a, b, c) in self.constraints.iter_mut() {
+            for (index, coeff) in a.iter_mut().chain(b.iter_mut()).chain(c.iter_mut()) {
+                *coeff = f(*index, *coeff);
+            }
+        }
+    }
+
+    /// The `(a, b, c)` coefficients on the constant one wire of every constraint,
+    /// without modifying the system. See [`Self::extract_constants`].
+    pub fn constant_terms(&self) -> Vec<(F, F, F)> {
+        let constant = |lc: &ConstraintVec<F>| -> F {
+            lc.iter()
+                .filter(|(index, _)| *index == 0)
+                .map(|(_, coeff)| *coeff)
+                .sum()
+        };
+        self.constraints
+            .iter()
+            .map(|(a, b, c)| (constant(a), constant(b), constant(c)))
+            .collect()
+    }
+}
+
+impl<F: PrimeField> R1CS<F> {
+    /// Adds the constraint `a * b = c` at the end, without checking its wires
+    pub fn append_constraint(
+        &mut self,
+        a: ConstraintVec<F>,
+        b: ConstraintVec<F>,
+        c: ConstraintVec<F>,
+    ) {
+        self.constraints.push((a, b, c));
+    }
+
+    /// Like [`Self::append_constraint`], but fails without modifying `self` if the
+    /// constraint references a wire not below `num_variables`
+    pub fn try_append_constraint(
+        &mut self,
+        a: ConstraintVec<F>,
+        b: ConstraintVec<F>,
+        c: ConstraintVec<F>,
+    ) -> Result<()> {
+        for (lc, matrix) in [(&a, Matrix::A), (&b, Matrix::B), (&c, Matrix::C)] {
+            if let Some((wire, _)) = lc.iter().find(|(wire, _)| *wire >= self.num_variables) {
+                return Err(R1CSError::WireOutOfRange {
+                    constraint: self.constraints.len(),
+                    matrix,
+                    wire: *wire,
+                    n_wires: self.num_variables,
+                });
+            }
+        }
+        self.append_constraint(a, b, c);
+        Ok(())
+    }
+
+    /// Grows `num_variables` to cover every wire the constraints reference, counting the
+    /// new wires as private, after constraints were added without checking them.
+    /// Counts are never reduced.
+    pub fn recompute_variables(&mut self) {
+        let referenced = if self.constraints.is_empty() {
+            0
+        } else {
+            self.max_wire_index() + 1
+        };
+        self.num_variables = self.num_variables.max(referenced).max(self.num_inputs);
+        self.num_aux = self.num_variables - self.num_inputs;
+    }
+
+    /// Replaces every constraint with its [canonical form](canonicalize_constraint)
+    pub fn normalize(&mut self) {
+        self.constraints.iter_mut().for_each(normalize_constraint);
+    }
+
+    /// [Normalizes](Self::normalize) every constraint, then removes the constraints
+    /// equal to an earlier one. Returns the number of constraints removed.
+    pub fn dedup_constraints(&mut self) -> usize {
+        self.normalize();
+        let before = self.constraints.len();
+        let mut seen = HashSet::with_capacity(before);
+        self.constraints
+            .retain(|constraint| seen.insert(constraint.clone()));
+        before - self.constraints.len()
+    }
+
+    /// Cleans up the constraints with every structural pass, in order: zero coefficient
+    /// removal, [normalization](Self::normalize), pruning of the constraints every
+    /// witness satisfies and [deduplication](Self::dedup_constraints). Wires are left
+    /// untouched, so witnesses for the original system still satisfy the result and
+    /// vice versa. Returns what each pass removed.
+    pub fn optimize(&mut self) -> OptimizeReport {
+        optimize_constraints(&mut self.constraints)
+    }
+}
+
+impl<F: PrimeField> R1CSFile<F> {
+    /// Runs [`R1CS::optimize`] on the constraints of the file, so that
+    /// [`Self::write`] emits the optimized circuit. The header constraint count is
+    /// updated and any retained coefficient bytes, now stale, are dropped.
+    pub fn optimize(&mut self) -> OptimizeReport {
+        let report = optimize_constraints(&mut self.constraints);
+        self.header.n_constraints = self.constraints.len() as u32;
+        self.raw_constraints = None;
+        report
+    }
+}
+
+fn optimize_constraints<F: PrimeField>(constraints: &mut Vec<Constraints<F>>) -> OptimizeReport {
+    let num_terms = |constraints: &[Constraints<F>]| -> usize {
+        constraints
+            .iter()
+            .map(|(a, b, c)| a.len() + b.len() + c.len())
+            .sum()
+    };
+
+    let before = num_terms(constraints);
+    for (a, b, c) in constraints.iter_mut() {
+        for lc in [a, b, c] {
+            lc.retain(|(_, coeff)| !coeff.is_zero());
+        }
+    }
+    let zero_terms = before - num_terms(constraints);
+
+    let before = num_terms(constraints);
+    constraints.iter_mut().for_each(normalize_constraint);
+    let merged_terms = before - num_terms(constraints);
+
+    let before = constraints.len();
+    constraints.retain(|constraint| !is_trivial(constraint));
+    let trivial_constraints = before - constraints.len();
+
+    let before = constraints.len();
+    let mut seen = HashSet::with_capacity(before);
+    constraints.retain(|constraint| seen.insert(constraint.clone()));
+
+    OptimizeReport {
+        zero_terms,
+        merged_terms,
+        trivial_constraints,
+        duplicate_constraints: before - constraints.len(),
+    }
+}
+
+/// Whether the normalized `constraint` holds for every witness: one side of the product
+/// is zero along with C, or only the constant wire appears and the constants agree.
+/// Constant constraints that do not hold are kept, as they make the system unsatisfiable.
+fn is_trivial<F: PrimeField>((a, b, c): &Constraints<F>) -> bool {
+    if (a.is_empty() || b.is_empty()) && c.is_empty() {
+        return true;
+    }
+    let constant = |lc: &ConstraintVec<F>| match &lc[..] {
+        [] => Some(F::zero()),
+        [(0, coeff)] => Some(*coeff),
+        _ => None,
+    };
+    match (constant(a), constant(b), constant(c)) {
+        (Some(a), Some(b), Some(c)) => a * b == c,
+        _ => false,
+    }
+}
+
+/// What each pass of [`R1CS::optimize`] removed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OptimizeReport {
+    /// Terms with a zero coefficient
+    pub zero_terms: usize,
+    /// Terms merged into an earlier term on the same wire, or cancelled out by it
+    pub merged_terms: usize,
+    /// Constraints every witness satisfies, such as `0 * x = 0`
+    pub trivial_constraints: usize,
+    /// Constraints equal to an earlier one
+    pub duplicate_constraints: usize,
+}
+
+/// Appends the constraints as [`R1CS::append_constraint`] does, without checking their
+/// wires. Call [`R1CS::recompute_variables`] afterwards if they reference new wires.
+impl<F: PrimeField> Extend<Constraints<F>> for R1CS<F> {
+    fn extend<I: IntoIterator<Item = Constraints<F>>>(&mut self, iter: I) {
+        self.constraints.extend(iter);
+    }
+}
+
+/// Projects a witness through a wire map, where `map[new] = old`
+pub fn apply_wire_map<F: Copy>(witness: &[F], map: &[usize]) -> Vec<F> {
+    map.iter().map(|old| witness[*old]).collect()
+}
+
+fn remap_constraint<F: PrimeField>(constraint: &mut Constraints<F>, old_to_new: &[usize]) {
+    for lc in [&mut constraint.0, &mut constraint.1, &mut constraint.2].iter_mut() {
+        for (index, _) in lc.iter_mut() {
+            *index = old_to_new[*index];
+        }
+    }
+}
+
+impl<F: PrimeField> R1CS<F> {
+    /// Orders the public wires so that outputs come after the public inputs when
+    /// `outputs_last` is set, or before them as circom lays them out otherwise.
+    ///
+    /// Returns the wire map, with `map[new] = old`, to project witnesses with
+    /// [`apply_wire_map`].
+    pub fn reorder_public(&mut self, outputs_last: bool) -> Vec<usize> {
+        let mut map: Vec<usize> = (0..self.num_variables).collect();
+        if self.outputs_last == outputs_last {
+            return map;
+        }
+
+        let (first, second) = if self.outputs_last {
+            (self.num_pub_in, self.num_pub_out)
+        } else {
+            (self.num_pub_out, self.num_pub_in)
+        };
+        let public = &mut map[1..1 + first + second];
+        public.rotate_left(first);
+
+        let mut old_to_new = vec![0; map.len()];
+        for (new, old) in map.iter().enumerate() {
+            old_to_new[*old] = new;
+        }
+        for constraint in self.constraints.iter_mut() {
+            remap_constraint(constraint, &old_to_new);
+        }
+
+        self.outputs_last = outputs_last;
+        map
+    }
+}
+
+/// Lifts `lc` into `G` coefficient by coefficient, returning the offending wire if a
+/// coefficient is not below the modulus of `G`
+fn lift_vec<F: PrimeField, G: PrimeField>(
+    lc: &ConstraintVec<F>,
+) -> Result<ConstraintVec<G>, usize> {
+    let modulus: BigUint = G::MODULUS.into();
+    lc.iter()
+        .map(|(index, coeff)| {
+            let value: BigUint = coeff.into_bigint().into();
+            if value >= modulus {
+                return Err(*index);
+            }
+            Ok((*index, G::from(value)))
+        })
+        .collect()
+}
+
+impl<F: PrimeField> R1CS<F> {
+    /// Reinterprets every coefficient in `G` through its canonical integer value in
+    /// `[0, p)`.
+    ///
+    /// Coefficients are lifted as-is, so a coefficient of `-1` in `F` becomes `p - 1` in
+    /// `G` rather than `-1`. Fails if any coefficient does not fit below the modulus of
+    /// `G`.
+    pub fn map_field<G: PrimeField>(&self) -> Result<R1CS<G>> {
+        let constraints = self
+            .constraints
+            .iter()
+            .enumerate()
+            .map(|(i, (a, b, c))| {
+                let lift = |lc, matrix| {
+                    lift_vec(lc).map_err(|wire| R1CSError::CoefficientOutOfRange {
+                        constraint: i,
+                        matrix,
+                        wire,
+                    })
+                };
+                Ok((
+                    lift(a, Matrix::A)?,
+                    lift(b, Matrix::B)?,
+                    lift(c, Matrix::C)?,
+                ))
+            })
+            .collect::<Result<_>>()?;
+        Ok(self.with_constraints(constraints))
+    }
+}
+
+impl<F: PrimeField> R1CS<F> {
+    /// Extracts the constraints in `range` into their own system.
+    ///
+    /// The instance wires keep their indices, and the auxiliary wires referenced by the
+    /// slice are renumbered contiguously after them in their original order. Returns the
+    /// wire map, with `map[new] = old`, to project witnesses with [`apply_wire_map`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds for the constraints.
+    pub fn slice_constraints(&self, range: Range<usize>) -> (R1CS<F>, Vec<usize>) {
+        let mut constraints = self.constraints[range].to_vec();
+
+        let mut used = vec![false; self.num_variables];
+        for (a, b, c) in &constraints {
+            for (index, _) in a.iter().chain(b).chain(c) {
+                used[*index] = true;
+            }
+        }
+
+        let map: Vec<usize> = (0..self.num_inputs)
+            .chain((self.num_inputs..self.num_variables).filter(|wire| used[*wire]))
+            .collect();
+        let mut old_to_new = vec![0; self.num_variables];
+        for (new, old) in map.iter().enumerate() {
+            old_to_new[*old] = new;
+        }
+        for constraint in constraints.iter_mut() {
+            remap_constraint(constraint, &old_to_new);
+        }
+
+        let mut r1cs = self.with_constraints(constraints);
+        r1cs.num_aux = map.len() - self.num_inputs;
+        r1cs.num_variables = map.len();
+        (r1cs, map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+
+    use crate::eval::dot;
+    use crate::field::M31;
+
+    fn fr(value: i64) -> Fr {
+        if value < 0 {
+            -Fr::from(value.unsigned_abs())
+        } else {
+            Fr::from(value as u64)
+        }
+    }
+
+    /// `(x + 3) * 2 = y`, `y * (x - 1) = z + 5` and `z * 1 = out`, over wires
+    /// `[1, out, x, y, z]`
+    fn affine() -> R1CS<Fr> {
+        R1CS::for_test(
+            1,
+            0,
+            3,
+            vec![
+                (
+                    vec![(2, fr(1)), (0, fr(3))],
+                    vec![(0, fr(2))],
+                    vec![(3, fr(1))],
+                ),
+                (
+                    vec![(3, fr(1))],
+                    vec![(2, fr(1)), (0, fr(-1))],
+                    vec![(4, fr(1)), (0, fr(5))],
+                ),
+                (vec![(4, fr(1))], vec![(0, fr(1))], vec![(1, fr(1))]),
+            ],
+        )
+    }
+
+    /// The witness of [`affine`] for `x`
+    fn affine_witness(x: i64) -> Vec<Fr> {
+        let y = (x + 3) * 2;
+        let z = y * (x - 1) - 5;
+        [1, z, x, y, z].iter().map(|v| fr(*v)).collect()
+    }
+
+    /// Whether constraint `i` of `r1cs` holds for `witness` once `constants` are added
+    fn holds_with_constants(r1cs: &R1CS<Fr>, constants: &[(Fr, Fr, Fr)], witness: &[Fr]) -> bool {
+        r1cs.constraints
+            .iter()
+            .zip(constants)
+            .all(|((a, b, c), (a0, b0, c0))| {
+                (dot(a, witness) + a0) * (dot(b, witness) + b0) == dot(c, witness) + c0
+            })
+    }
+
+    #[test]
+    fn extract_constants_moves_wire_zero_out() {
+        let (stripped, constants) = affine().extract_constants();
+        assert_eq!(
+            constants,
+            vec![
+                (fr(3), fr(2), fr(0)),
+                (fr(0), fr(-1), fr(5)),
+                (fr(0), fr(1), fr(0)),
+            ]
+        );
+        assert!(stripped.constraints.iter().all(|(a, b, c)| a
+            .iter()
+            .chain(b)
+            .chain(c)
+            .all(|(wire, _)| *wire != 0)));
+        assert_eq!(stripped.metadata(), affine().metadata());
+    }
+
+    #[test]
+    fn extract_constants_preserves_satisfiability() {
+        let r1cs = affine();
+        let (stripped, constants) = r1cs.extract_constants();
+        for x in [-2, 0, 4, 9] {
+            let witness = affine_witness(x);
+            assert_eq!(r1cs.check_witness(&witness), Ok(()));
+            assert!(holds_with_constants(&stripped, &constants, &witness));
+        }
+
+        let mut wrong = affine_witness(4);
+        wrong[3] += fr(1);
+        assert!(r1cs.check_witness(&wrong).is_err());
+        assert!(!holds_with_constants(&stripped, &constants, &wrong));
+    }
+
+    #[test]
+    fn repeated_constant_terms_are_summed() {
+        let mut r1cs = affine();
+        r1cs.constraints[0].0.push((0, fr(4)));
+        let (stripped, constants) = r1cs.extract_constants();
+        assert_eq!(constants[0].0, fr(7));
+        assert_eq!(stripped.constraints[0].0, vec![(2, fr(1))]);
+    }
+
+    /// `a * b = p` and `p * 1 = q - a`, over wires `[1, p, q, a, b, s]` with outputs
+    /// `p, q` and public inputs `a, b`, plus an unused private wire
+    fn two_outputs() -> R1CS<Fr> {
+        R1CS::for_test(
+            2,
+            2,
+            1,
+            vec![
+                (vec![(3, fr(1))], vec![(4, fr(1))], vec![(1, fr(1))]),
+                (
+                    vec![(1, fr(1))],
+                    vec![(0, fr(1))],
+                    vec![(2, fr(1)), (3, fr(-1))],
+                ),
+            ],
+        )
+    }
+
+    #[test]
+    fn reorder_public_moves_outputs_after_inputs() {
+        let mut r1cs = two_outputs();
+        let map = r1cs.reorder_public(true);
+        assert_eq!(map, vec![0, 3, 4, 1, 2, 5]);
+        assert!(r1cs.outputs_last);
+        assert_eq!(r1cs.constraints[0].0, vec![(1, fr(1))]);
+        assert_eq!(r1cs.constraints[0].2, vec![(3, fr(1))]);
+
+        // a, b = 3, 5
+        let witness: Vec<Fr> = [1, 15, 18, 3, 5, 9].iter().map(|v| fr(*v)).collect();
+        assert_eq!(two_outputs().check_witness(&witness), Ok(()));
+        assert_eq!(r1cs.check_witness(&apply_wire_map(&witness, &map)), Ok(()));
+    }
+
+    #[test]
+    fn reordering_twice_restores_the_original() {
+        let mut r1cs = two_outputs();
+        r1cs.reorder_public(true);
+        let map = r1cs.reorder_public(false);
+        assert_eq!(map, vec![0, 3, 4, 1, 2, 5]);
+        assert_eq!(r1cs.constraints, two_outputs().constraints);
+        assert!(!r1cs.outputs_last);
+    }
+
+    #[test]
+    fn circom_order_is_kept_unless_requested() {
+        let mut r1cs = two_outputs();
+        let map = r1cs.reorder_public(false);
+        assert_eq!(map, (0..6).collect::<Vec<_>>());
+        assert_eq!(r1cs.constraints, two_outputs().constraints);
+    }
+
+    use crate::test_fields::Goldilocks;
+
+    /// `x * x = y` and `(y - 1) * 2 = out` over M31 wires `[1, out, x, y]`
+    fn m31_circuit() -> R1CS<M31> {
+        R1CS::for_test(
+            1,
+            0,
+            2,
+            vec![
+                (
+                    vec![(2, M31::from(1u64))],
+                    vec![(2, M31::from(1u64))],
+                    vec![(3, M31::from(1u64))],
+                ),
+                (
+                    vec![(3, M31::from(1u64)), (0, -M31::from(1u64))],
+                    vec![(0, M31::from(2u64))],
+                    vec![(1, M31::from(1u64))],
+                ),
+            ],
+        )
+    }
+
+    #[test]
+    fn map_field_lifts_m31_into_goldilocks() {
+        let lifted = m31_circuit().map_field::<Goldilocks>().unwrap();
+        assert_eq!(lifted.num_variables, 4);
+        assert_eq!(lifted.num_pub_out, 1);
+
+        let (a, b, c) = &lifted.constraints[1];
+        assert_eq!(a[1], (0, Goldilocks::from(2147483646u64)));
+        assert_eq!(b, &vec![(0, Goldilocks::from(2u64))]);
+        assert_eq!(c, &vec![(1, Goldilocks::from(1u64))]);
+
+        // -1 lifts to p - 1 rather than -1, so only the first constraint keeps its meaning
+        let witness: Vec<Goldilocks> = [1u64, 16, 3, 9].iter().map(|&v| v.into()).collect();
+        let (a, b, c) = &lifted.constraints[0];
+        assert_eq!(dot(a, &witness) * dot(b, &witness), dot(c, &witness));
+        let (a, b, c) = &lifted.constraints[1];
+        assert_ne!(dot(a, &witness) * dot(b, &witness), dot(c, &witness));
+    }
+
+    #[test]
+    fn map_field_rejects_coefficients_above_the_target_modulus() {
+        let mut r1cs = affine();
+        assert!(matches!(
+            r1cs.map_field::<M31>(),
+            Err(R1CSError::CoefficientOutOfRange {
+                constraint: 1,
+                matrix: Matrix::B,
+                wire: 0
+            })
+        ));
+
+        r1cs.constraints[1].1[1].1 = fr(1);
+        assert!(r1cs.map_field::<M31>().is_ok());
+    }
+
+    #[test]
+    fn slice_keeps_the_instance_and_renumbers_used_aux_wires() {
+        let (sliced, map) = affine().slice_constraints(2..3);
+        assert_eq!(map, vec![0, 1, 4]);
+        assert_eq!(
+            (sliced.num_inputs, sliced.num_aux, sliced.num_variables),
+            (2, 1, 3)
+        );
+        assert_eq!(
+            sliced.constraints,
+            vec![(vec![(2, fr(1))], vec![(0, fr(1))], vec![(1, fr(1))])]
+        );
+    }
+
+    #[test]
+    fn projected_witness_satisfies_the_slice() {
+        let witness = affine_witness(4);
+        for range in [0..1, 1..3, 0..3, 2..2] {
+            let (sliced, map) = affine().slice_constraints(range.clone());
+            assert_eq!(sliced.constraints.len(), range.len());
+            let projected = apply_wire_map(&witness, &map);
+            assert_eq!(sliced.check_witness(&projected), Ok(()));
+        }
+
+        let mut wrong = witness;
+        wrong[3] += fr(1);
+        let (sliced, map) = affine().slice_constraints(0..1);
+        assert!(sliced.check_witness(&apply_wire_map(&wrong, &map)).is_err());
+    }
+
+    #[test]
+    fn dedup_collapses_identical_constraints() {
+        let mut r1cs = affine();
+        r1cs.constraints.push(r1cs.constraints[1].clone());
+        assert_eq!(r1cs.dedup_constraints(), 1);
+        assert_eq!(r1cs.constraints.len(), 3);
+        assert_eq!(r1cs.check_witness(&affine_witness(4)), Ok(()));
+        assert_eq!(r1cs.dedup_constraints(), 0);
+    }
+
+    #[test]
+    fn dedup_compares_normalized_constraints() {
+        let mut r1cs = affine();
+        // (3 + x) * (1 + 1) = y, the first constraint with its terms reordered and split
+        r1cs.constraints.push((
+            vec![(0, fr(3)), (2, fr(1))],
+            vec![(0, fr(1)), (0, fr(1))],
+            vec![(3, fr(1))],
+        ));
+        // (x + 3) * 2 = 2 * y is a different row
+        r1cs.constraints.push((
+            vec![(2, fr(1)), (0, fr(3))],
+            vec![(0, fr(2))],
+            vec![(3, fr(2))],
+        ));
+        assert_eq!(r1cs.dedup_constraints(), 1);
+        assert_eq!(r1cs.constraints.len(), 4);
+        assert_eq!(r1cs.constraints[0].0, vec![(0, fr(3)), (2, fr(1))]);
+    }
+
+    #[test]
+    fn constant_terms_read_wire_zero_of_each_side() {
+        let r1cs = affine();
+        assert_eq!(
+            r1cs.constant_terms(),
+            vec![
+                (fr(3), fr(2), fr(0)),
+                (fr(0), fr(-1), fr(5)),
+                (fr(0), fr(1), fr(0))
+            ]
+        );
+        assert_eq!(r1cs.constraints, affine().constraints);
+        assert_eq!(r1cs.constant_terms(), affine().extract_constants().1);
+    }
+
+    #[test]
+    fn constant_terms_sum_repeated_constants() {
+        let mut r1cs = affine();
+        // (x + 2) * (y + 3) = z + 1 + 3, with a constant on every side
+        r1cs.constraints = vec![(
+            vec![(0, fr(2)), (2, fr(1))],
+            vec![(3, fr(1)), (0, fr(3))],
+            vec![(0, fr(1)), (4, fr(1)), (0, fr(3))],
+        )];
+        assert_eq!(r1cs.constant_terms(), vec![(fr(2), fr(3), fr(4))]);
+    }
+
+    #[test]
+    fn map_coefficients_doubles_every_coefficient() {
+        let mut r1cs = affine();
+        r1cs.map_coefficients(|_, coeff| coeff + coeff);
+        assert_eq!(
+            r1cs.constraints[1],
+            (
+                vec![(3, fr(2))],
+                vec![(2, fr(2)), (0, fr(-2))],
+                vec![(4, fr(2)), (0, fr(10))]
+            )
+        );
+        assert_eq!(r1cs.constraints.len(), 3);
+    }
+
+    #[test]
+    fn map_coefficients_sees_the_wire() {
+        let mut r1cs = affine();
+        // flip the sign of the constant terms only
+        r1cs.map_coefficients(|wire, coeff| if wire == 0 { -coeff } else { coeff });
+        let negated: Vec<_> = affine()
+            .constant_terms()
+            .into_iter()
+            .map(|(a, b, c)| (-a, -b, -c))
+            .collect();
+        assert_eq!(r1cs.constant_terms(), negated);
+        assert_eq!(r1cs.constraints[0].0[0], (2, fr(1)));
+    }
+
+    #[test]
+    fn appended_constraints_synthesize() {
+        use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+
+        // x * x = y, y * x = z and (z + 1) * 1 = out, over the wires of `affine`
+        let mut r1cs = affine();
+        r1cs.constraints.clear();
+        r1cs.append_constraint(vec![(2, fr(1))], vec![(2, fr(1))], vec![(3, fr(1))]);
+        r1cs.append_constraint(vec![(3, fr(1))], vec![(2, fr(1))], vec![(4, fr(1))]);
+        r1cs.append_constraint(
+            vec![(4, fr(1)), (0, fr(1))],
+            vec![(0, fr(1))],
+            vec![(1, fr(1))],
+        );
+        assert_eq!(r1cs.constraints.len(), 3);
+
+        let witness: Vec<Fr> = [1, 9, 2, 4, 8].iter().map(|v| fr(*v)).collect();
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        crate::CircomCircuit::new(r1cs, Some(witness))
+            .generate_constraints(cs.clone())
+            .unwrap();
+        assert_eq!(cs.num_constraints(), 3);
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn try_append_checks_the_wires() {
+        let mut r1cs = affine();
+        assert!(matches!(
+            r1cs.try_append_constraint(vec![(2, fr(1))], vec![(0, fr(1))], vec![(5, fr(1))]),
+            Err(R1CSError::WireOutOfRange {
+                constraint: 3,
+                matrix: Matrix::C,
+                wire: 5,
+                n_wires: 5
+            })
+        ));
+        assert_eq!(r1cs.constraints.len(), 3);
+
+        r1cs.try_append_constraint(vec![(2, fr(1))], vec![(0, fr(1))], vec![(4, fr(1))])
+            .unwrap();
+        assert_eq!(r1cs.constraints.len(), 4);
+        assert!(r1cs.validate().is_ok());
+    }
+
+    #[test]
+    fn reordered_constraints_canonicalize_equal() {
+        let constraint = &affine().constraints[1];
+        // the same row with its terms reversed, `x` split in two and a zero term added
+        let reordered = (
+            vec![(3, fr(1)), (4, fr(0))],
+            vec![(0, fr(-1)), (2, fr(3)), (2, fr(-2))],
+            vec![(0, fr(5)), (4, fr(1))],
+        );
+        let canonical = canonicalize_constraint(constraint);
+        assert_eq!(canonicalize_constraint(&reordered), canonical);
+        assert_eq!(
+            canonical,
+            (
+                vec![(3, fr(1))],
+                vec![(0, fr(-1)), (2, fr(1))],
+                vec![(0, fr(5)), (4, fr(1))],
+            )
+        );
+        assert_eq!(canonicalize_constraint(&canonical), canonical);
+    }
+
+    #[test]
+    fn cancelling_terms_leave_an_empty_side() {
+        let constraint = (vec![(2, fr(4)), (2, fr(-4))], vec![(0, fr(1))], vec![]);
+        assert_eq!(
+            canonicalize_constraint(&constraint),
+            (vec![], vec![(0, fr(1))], vec![])
+        );
+    }
+
+    #[test]
+    fn hashing_and_diffing_see_the_canonical_form() {
+        let mut reordered = affine();
+        reordered.constraints[1].1.reverse();
+        reordered.constraints[1].2.push((3, fr(0)));
+        assert_eq!(reordered.structural_hash(), affine().structural_hash());
+        assert!(reordered.diff(&affine()).is_empty());
+    }
+
+    #[test]
+    fn extends_from_an_iterator() {
+        let mut r1cs = affine();
+        // w[i] * 1 = w[i + 1] for i in 4..7, reaching three wires past the last
+        r1cs.extend((4..7).map(|i| (vec![(i, fr(1))], vec![(0, fr(1))], vec![(i + 1, fr(1))])));
+        assert_eq!(r1cs.constraints.len(), 6);
+        assert_eq!(r1cs.constraints[5].2, vec![(7, fr(1))]);
+        // the counts are left for the caller to finalize
+        assert_eq!((r1cs.num_variables, r1cs.num_aux), (5, 3));
+        assert!(r1cs.validate().is_err());
+
+        r1cs.recompute_variables();
+        assert_eq!((r1cs.num_variables, r1cs.num_aux), (8, 6));
+        assert_eq!(r1cs.num_inputs, 2);
+        assert!(r1cs.validate().is_ok());
+    }
+
+    #[test]
+    fn recompute_variables_never_shrinks_the_counts() {
+        let mut r1cs = affine();
+        r1cs.constraints.truncate(1);
+        r1cs.recompute_variables();
+        assert_eq!((r1cs.num_variables, r1cs.num_aux), (5, 3));
+
+        r1cs.constraints.clear();
+        r1cs.recompute_variables();
+        assert_eq!((r1cs.num_variables, r1cs.num_aux), (5, 3));
+    }
+
+    /// [`affine`] with a zero term, a split term, two trivial constraints and a reordered
+    /// copy of its first constraint
+    fn redundant() -> R1CS<Fr> {
+        let mut r1cs = affine();
+        r1cs.constraints[2].0.push((3, fr(0)));
+        r1cs.constraints[1].0 = vec![(3, fr(2)), (3, fr(-1))];
+        // 0 * x = 0 and 2 * 3 = 6
+        r1cs.constraints.push((vec![], vec![(2, fr(1))], vec![]));
+        r1cs.constraints
+            .push((vec![(0, fr(2))], vec![(0, fr(3))], vec![(0, fr(6))]));
+        r1cs.constraints.push((
+            vec![(0, fr(3)), (2, fr(1))],
+            vec![(0, fr(2))],
+            vec![(3, fr(1))],
+        ));
+        r1cs
+    }
+
+    #[test]
+    fn optimize_reports_what_each_pass_removed() {
+        let mut r1cs = redundant();
+        assert_eq!(
+            r1cs.optimize(),
+            OptimizeReport {
+                zero_terms: 1,
+                merged_terms: 1,
+                trivial_constraints: 2,
+                duplicate_constraints: 1,
+            }
+        );
+        assert_eq!(r1cs.constraints.len(), 3);
+        assert_eq!(r1cs.constraints[1].0, vec![(3, fr(1))]);
+        assert_eq!(r1cs.optimize(), OptimizeReport::default());
+    }
+
+    #[test]
+    fn optimize_preserves_satisfiability() {
+        let original = redundant();
+        let mut optimized = original.clone();
+        optimized.optimize();
+        for x in [0, 4, 9] {
+            assert_eq!(original.check_witness(&affine_witness(x)), Ok(()));
+            assert_eq!(optimized.check_witness(&affine_witness(x)), Ok(()));
+        }
+        let mut wrong = affine_witness(4);
+        wrong[3] += fr(1);
+        assert!(original.check_witness(&wrong).is_err());
+        assert!(optimized.check_witness(&wrong).is_err());
+    }
+
+    #[test]
+    fn false_constant_constraints_are_kept() {
+        let mut r1cs = affine();
+        r1cs.constraints
+            .push((vec![(0, fr(2))], vec![(0, fr(3))], vec![(0, fr(7))]));
+        assert_eq!(r1cs.optimize().trivial_constraints, 0);
+        assert_eq!(r1cs.constraints.len(), 4);
+    }
+
+    #[test]
+    fn optimized_files_write_the_reduced_circuit() {
+        let file = std::fs::File::open("./test-vectors/mycircuit.r1cs").unwrap();
+        let mut file = R1CSFile::<Fr>::new(file).unwrap();
+        file.constraints.push(file.constraints[0].clone());
+        file.header.n_constraints += 1;
+
+        assert_eq!(file.optimize().duplicate_constraints, 1);
+        assert_eq!(file.header.n_constraints, 1);
+        let bytes = file
+            .write(ark_std::io::Cursor::new(Vec::new()))
+            .unwrap()
+            .into_inner();
+        let written = R1CSFile::<Fr>::new(ark_std::io::Cursor::new(bytes)).unwrap();
+        assert_eq!(written.constraints, file.constraints);
+    }
+}
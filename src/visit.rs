@@ -0,0 +1,129 @@
+//! Visitor over the constraints of an R1CS
+//!
+//! Analyses that only need to see each constraint once, such as counters, validators
+//! or exporters, implement [`ConstraintVisitor`] and run on an R1CS in memory with
+//! [`R1CS::accept`], or straight from disk with [`ConstraintStream::accept`].
+use ark_ff::PrimeField;
+use ark_std::io::Read;
+
+use crate::error::Result;
+use crate::{ConstraintStream, ConstraintVec, R1CS};
+
+pub trait ConstraintVisitor<F> {
+    /// Called on constraint `index`, stating `a * b = c`, in order of the constraints
+    fn visit(
+        &mut self,
+        index: usize,
+        a: &ConstraintVec<F>,
+        b: &ConstraintVec<F>,
+        c: &ConstraintVec<F>,
+    );
+}
+
+impl<F: PrimeField> R1CS<F> {
+    /// Passes every constraint to `visitor`, without copying them
+    pub fn accept<V: ConstraintVisitor<F>>(&self, visitor: &mut V) {
+        for (index, (a, b, c)) in self.constraints.iter().enumerate() {
+            visitor.visit(index, a, b, c);
+        }
+    }
+}
+
+impl<R: Read, F: PrimeField> ConstraintStream<R, F> {
+    /// Reads the remaining constraints and passes each one to `visitor`, holding only
+    /// one of them in memory at a time. Stops at the first read error.
+    pub fn accept<V: ConstraintVisitor<F>>(self, visitor: &mut V) -> Result<()> {
+        let start = self.header().n_constraints as usize - self.size_hint().0;
+        for (offset, constraint) in self.enumerate() {
+            let (a, b, c) = constraint?;
+            visitor.visit(start + offset, &a, &b, &c);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use std::fs::File;
+
+    use crate::R1CSFile;
+
+    /// Records the index of each visited constraint and its number of A, B and C terms
+    #[derive(Default)]
+    struct Counter {
+        visited: Vec<(usize, (usize, usize, usize))>,
+    }
+
+    impl<F> ConstraintVisitor<F> for Counter {
+        fn visit(
+            &mut self,
+            index: usize,
+            a: &ConstraintVec<F>,
+            b: &ConstraintVec<F>,
+            c: &ConstraintVec<F>,
+        ) {
+            self.visited.push((index, (a.len(), b.len(), c.len())));
+        }
+    }
+
+    fn multiplier() -> File {
+        File::open("./test-vectors/mycircuit.r1cs").unwrap()
+    }
+
+    #[test]
+    fn visits_every_constraint_in_order() {
+        let one = Fr::from(1u64);
+        // x * x = y, (x + y) * 1 = z and y * z = out over wires [1, out, x, y, z]
+        let r1cs = R1CS::for_test(
+            1,
+            0,
+            3,
+            vec![
+                (vec![(2, one)], vec![(2, one)], vec![(3, one)]),
+                (vec![(2, one), (3, one)], vec![(0, one)], vec![(4, one)]),
+                (vec![(3, one)], vec![(4, one)], vec![(1, one)]),
+            ],
+        );
+        let mut counter = Counter::default();
+        r1cs.accept(&mut counter);
+        assert_eq!(
+            counter.visited,
+            vec![(0, (1, 1, 1)), (1, (2, 1, 1)), (2, (1, 1, 1))]
+        );
+    }
+
+    #[test]
+    fn streams_the_same_visits_as_memory() {
+        let r1cs = R1CS::from(R1CSFile::<Fr>::new(multiplier()).unwrap());
+        let mut in_memory = Counter::default();
+        r1cs.accept(&mut in_memory);
+
+        let mut streamed = Counter::default();
+        ConstraintStream::<_, Fr>::new(multiplier())
+            .unwrap()
+            .accept(&mut streamed)
+            .unwrap();
+        assert_eq!(streamed.visited, in_memory.visited);
+        assert_eq!(streamed.visited.len(), 1);
+    }
+
+    #[test]
+    fn a_partly_read_stream_visits_the_rest_with_their_indices() {
+        // the multiplier followed by a copy with an extra A term
+        let mut file = R1CSFile::<Fr>::new(multiplier()).unwrap();
+        let mut second = file.constraints[0].clone();
+        second.0.push((0, Fr::from(1u64)));
+        file.constraints.push(second);
+        file.header.n_constraints = 2;
+        let bytes = file.write(ark_std::io::Cursor::new(Vec::new())).unwrap();
+
+        let mut stream =
+            ConstraintStream::<_, Fr>::new(ark_std::io::Cursor::new(bytes.into_inner())).unwrap();
+        stream.next().unwrap().unwrap();
+        let mut counter = Counter::default();
+        stream.accept(&mut counter).unwrap();
+        assert_eq!(counter.visited, vec![(1, (2, 1, 1))]);
+    }
+}
@@ -0,0 +1,758 @@
+//! Circom `.wtns` witness file reader, for both the legacy version 1 layout and the
+//! sectioned version 2 layout
+//! Format: <https://github.com/iden3/snarkjs/blob/master/src/wtns_utils.js>
+use ark_ff::PrimeField;
+use byteorder::{LittleEndian, ReadBytesExt};
+
+use ark_std::io::{Read, Seek, SeekFrom};
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use crate::error::{R1CSError, Result};
+use crate::R1CS;
+
+pub struct WitnessHeader {
+    pub field_size: u32,
+    pub prime: Vec<u8>,
+    pub n_witness: u32,
+}
+
+pub struct WitnessFile<F: PrimeField> {
+    pub version: u32,
+    pub header: WitnessHeader,
+    pub witness: Vec<F>,
+}
+
+/// How witness values are decoded
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WitnessReaderOptions {
+    /// Read values as big-endian, as some third-party generators write them. Circom
+    /// uses little-endian. The header, including the prime, is always little-endian.
+    pub big_endian: bool,
+}
+
+impl WitnessReaderOptions {
+    fn decode<F: PrimeField>(&self, bytes: &[u8]) -> F {
+        if self.big_endian {
+            F::from_be_bytes_mod_order(bytes)
+        } else {
+            F::from_le_bytes_mod_order(bytes)
+        }
+    }
+}
+
+impl<F: PrimeField> WitnessFile<F> {
+    pub fn new<R: Read + Seek>(reader: R) -> Result<WitnessFile<F>> {
+        Self::new_with_options(reader, WitnessReaderOptions::default())
+    }
+
+    pub fn new_with_options<R: Read + Seek>(
+        mut reader: R,
+        options: WitnessReaderOptions,
+    ) -> Result<WitnessFile<F>> {
+        let (version, header, witness_offset) = read_layout(&mut reader)?;
+        reader.seek(SeekFrom::Start(witness_offset))?;
+        let witness = read_witness(&mut reader, &header, options)?;
+
+        Ok(WitnessFile {
+            version,
+            header,
+            witness,
+        })
+    }
+}
+
+/// Default number of values kept in memory by a [`WitnessReader`]
+pub const DEFAULT_WITNESS_CACHE: usize = 4096;
+
+/// Reads witness values on demand from a seekable `.wtns` source.
+///
+/// Only the header is parsed up front, each value is fetched with a seek and a
+/// `field_size` byte read the first time it is needed and kept in a small LRU cache.
+/// This keeps memory use bounded for witnesses that do not fit in RAM, at the cost of
+/// one read per cache miss: constraints touching wires far apart in the file can be
+/// orders of magnitude slower to check than with an in-memory [`WitnessFile`].
+pub struct WitnessReader<R, F> {
+    reader: R,
+    header: WitnessHeader,
+    offset: u64,
+    options: WitnessReaderOptions,
+    cache: LruCache<F>,
+}
+
+impl<R: Read + Seek, F: PrimeField> WitnessReader<R, F> {
+    pub fn new(reader: R) -> Result<Self> {
+        Self::with_cache_capacity(reader, DEFAULT_WITNESS_CACHE)
+    }
+
+    pub fn with_cache_capacity(reader: R, capacity: usize) -> Result<Self> {
+        Self::with_options(reader, capacity, WitnessReaderOptions::default())
+    }
+
+    pub fn with_options(
+        mut reader: R,
+        capacity: usize,
+        options: WitnessReaderOptions,
+    ) -> Result<Self> {
+        let (_, header, offset) = read_layout(&mut reader)?;
+        Ok(WitnessReader {
+            reader,
+            header,
+            offset,
+            options,
+            cache: LruCache::new(capacity.max(1)),
+        })
+    }
+
+    pub fn header(&self) -> &WitnessHeader {
+        &self.header
+    }
+
+    pub fn len(&self) -> usize {
+        self.header.n_witness as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns witness value `index`, reading it from the source on a cache miss
+    pub fn get(&mut self, index: usize) -> Result<F> {
+        if let Some(value) = self.cache.get(index) {
+            return Ok(value);
+        }
+
+        if index >= self.len() {
+            return Err(R1CSError::WitnessIndexOutOfRange {
+                index,
+                len: self.len(),
+            });
+        }
+
+        let field_size = self.header.field_size as u64;
+        self.reader
+            .seek(SeekFrom::Start(self.offset + index as u64 * field_size))?;
+        let mut bytes = vec![0u8; field_size as usize];
+        self.reader.read_exact(&mut bytes)?;
+        let value = self.options.decode(&bytes);
+
+        self.cache.insert(index, value);
+        Ok(value)
+    }
+}
+
+const NIL: usize = usize::MAX;
+
+struct CacheEntry<F> {
+    index: usize,
+    value: F,
+    prev: usize,
+    next: usize,
+}
+
+/// Least recently used cache of witness values. Entries live in a fixed set of slots
+/// linked from most to least recently used, so lookups, insertions and evictions take
+/// constant time.
+struct LruCache<F> {
+    capacity: usize,
+    slots: Vec<CacheEntry<F>>,
+    // witness index -> slot
+    map: HashMap<usize, usize>,
+    head: usize,
+    tail: usize,
+}
+
+impl<F: Copy> LruCache<F> {
+    fn new(capacity: usize) -> Self {
+        LruCache {
+            capacity,
+            slots: Vec::new(),
+            map: HashMap::new(),
+            head: NIL,
+            tail: NIL,
+        }
+    }
+
+    fn get(&mut self, index: usize) -> Option<F> {
+        let slot = *self.map.get(&index)?;
+        self.unlink(slot);
+        self.push_front(slot);
+        Some(self.slots[slot].value)
+    }
+
+    /// Inserts a value that is not cached yet, evicting the least recently used one
+    /// when the cache is full
+    fn insert(&mut self, index: usize, value: F) {
+        let slot = if self.slots.len() < self.capacity {
+            self.slots.push(CacheEntry {
+                index,
+                value,
+                prev: NIL,
+                next: NIL,
+            });
+            self.slots.len() - 1
+        } else {
+            let slot = self.tail;
+            self.unlink(slot);
+            self.map.remove(&self.slots[slot].index);
+            self.slots[slot].index = index;
+            self.slots[slot].value = value;
+            slot
+        };
+        self.map.insert(index, slot);
+        self.push_front(slot);
+    }
+
+    fn unlink(&mut self, slot: usize) {
+        let (prev, next) = (self.slots[slot].prev, self.slots[slot].next);
+        match prev {
+            NIL => self.head = next,
+            prev => self.slots[prev].next = next,
+        }
+        match next {
+            NIL => self.tail = prev,
+            next => self.slots[next].prev = prev,
+        }
+    }
+
+    fn push_front(&mut self, slot: usize) {
+        self.slots[slot].prev = NIL;
+        self.slots[slot].next = self.head;
+        match self.head {
+            NIL => self.tail = slot,
+            head => self.slots[head].prev = slot,
+        }
+        self.head = slot;
+    }
+}
+
+/// Parses the section table and header, returning the version, the header and the
+/// file offset of the first witness value
+fn read_layout<R: Read + Seek>(mut reader: R) -> Result<(u32, WitnessHeader, u64)> {
+    let start = reader.stream_position()?;
+    let end = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(start))?;
+
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != *b"wtns" {
+        return Err(R1CSError::InvalidMagic(magic));
+    }
+
+    let version = reader.read_u32::<LittleEndian>()?;
+    match version {
+        1 => return read_legacy_layout(reader, start, end),
+        2 => {}
+        _ => return Err(R1CSError::UnsupportedVersion(version)),
+    }
+
+    let num_sections = reader.read_u32::<LittleEndian>()?;
+
+    // section type -> (file offset, size)
+    let mut sections = HashMap::<u32, (u64, u64)>::new();
+    for _ in 0..num_sections {
+        let sec_type = reader.read_u32::<LittleEndian>()?;
+        let sec_size = reader.read_u64::<LittleEndian>()?;
+        let offset = reader.stream_position()?;
+        let section_end = offset.saturating_add(sec_size);
+        if section_end > end {
+            return Err(R1CSError::SectionOutOfBounds {
+                end: section_end - start,
+                len: end - start,
+            });
+        }
+        sections.insert(sec_type, (offset, sec_size));
+        reader.seek(SeekFrom::Current(sec_size as i64))?;
+    }
+
+    let header_type = 1;
+    let witness_type = 2;
+
+    let (header_offset, header_size) = *sections
+        .get(&header_type)
+        .ok_or(R1CSError::MissingSection(header_type))?;
+    reader.seek(SeekFrom::Start(header_offset))?;
+    let header = WitnessHeader::new(&mut reader, header_size)?;
+
+    let (witness_offset, witness_size) = *sections
+        .get(&witness_type)
+        .ok_or(R1CSError::MissingSection(witness_type))?;
+    if witness_size != header.n_witness as u64 * header.field_size as u64 {
+        return Err(R1CSError::InvalidSectionSize {
+            section_type: witness_type,
+            size: witness_size,
+        });
+    }
+
+    Ok((version, header, witness_offset))
+}
+
+/// Version 1 files, written by older circom releases, have no section table: the header
+/// follows the version directly and the values follow the header, up to `end`
+fn read_legacy_layout<R: Read + Seek>(
+    mut reader: R,
+    start: u64,
+    end: u64,
+) -> Result<(u32, WitnessHeader, u64)> {
+    let field_size = reader.read_u32::<LittleEndian>()?;
+    reader.seek(SeekFrom::Current(-4))?;
+    let header = WitnessHeader::new(&mut reader, 8 + field_size as u64)?;
+    let offset = reader.stream_position()?;
+    let values_end = offset.saturating_add(header.n_witness as u64 * header.field_size as u64);
+    if values_end > end {
+        return Err(R1CSError::SectionOutOfBounds {
+            end: values_end - start,
+            len: end - start,
+        });
+    }
+    Ok((1, header, offset))
+}
+
+impl<F: PrimeField> TryFrom<WitnessFile<F>> for Vec<F> {
+    type Error = R1CSError;
+
+    /// Checks that the first value is the constant one and that the declared count
+    /// matches the values
+    fn try_from(file: WitnessFile<F>) -> Result<Self> {
+        if file.witness.len() != file.header.n_witness as usize {
+            return Err(R1CSError::WitnessLengthMismatch {
+                expected: file.header.n_witness as usize,
+                actual: file.witness.len(),
+            });
+        }
+        if file.witness.first() != Some(&F::one()) {
+            return Err(R1CSError::InvalidConstantWire);
+        }
+        Ok(file.witness)
+    }
+}
+
+impl WitnessHeader {
+    fn new<R: Read>(mut reader: R, size: u64) -> Result<WitnessHeader> {
+        let field_size = reader.read_u32::<LittleEndian>()?;
+        if field_size == 0 {
+            return Err(R1CSError::InvalidFieldSize(field_size));
+        }
+
+        if size != 8 + field_size as u64 {
+            return Err(R1CSError::InvalidSectionSize {
+                section_type: 1,
+                size,
+            });
+        }
+
+        let mut prime = vec![0u8; field_size as usize];
+        reader.read_exact(&mut prime)?;
+
+        Ok(WitnessHeader {
+            field_size,
+            prime,
+            n_witness: reader.read_u32::<LittleEndian>()?,
+        })
+    }
+}
+
+fn read_witness<R: Read, F: PrimeField>(
+    mut reader: R,
+    header: &WitnessHeader,
+    options: WitnessReaderOptions,
+) -> Result<Vec<F>> {
+    let mut witness = Vec::with_capacity(header.n_witness as usize);
+    let mut value = vec![0u8; header.field_size as usize];
+    for _ in 0..header.n_witness {
+        reader.read_exact(&mut value)?;
+        witness.push(options.decode(&value));
+    }
+    Ok(witness)
+}
+
+/// Compares two little-endian primes, ignoring zero padding
+pub(crate) fn same_prime(a: &[u8], b: &[u8]) -> bool {
+    let trim = |bytes: &[u8]| {
+        let len = bytes.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+        bytes[..len].to_vec()
+    };
+    trim(a) == trim(b)
+}
+
+/// Whether two witnesses for `r1cs` agree on the public signals, wires
+/// `1..num_inputs`. Witnesses too short to hold them never agree.
+pub fn same_public_signals<F: PrimeField>(r1cs: &R1CS<F>, w1: &[F], w2: &[F]) -> bool {
+    let public = 1..r1cs.num_inputs;
+    match (w1.get(public.clone()), w2.get(public)) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Packs a witness into `limbs_per_element` little-endian `u64` limbs per value, stored
+/// column-major: limb `j` of element `i` is at `j * witness.len() + i`
+pub fn witness_to_limbs<F: PrimeField>(
+    witness: &[F],
+    limbs_per_element: usize,
+) -> Result<Vec<u64>> {
+    let n = witness.len();
+    let mut limbs = vec![0u64; n * limbs_per_element];
+    for (i, value) in witness.iter().enumerate() {
+        let bigint = value.into_bigint();
+        for (j, limb) in bigint.as_ref().iter().enumerate() {
+            if j < limbs_per_element {
+                limbs[j * n + i] = *limb;
+            } else if *limb != 0 {
+                return Err(R1CSError::InvalidLimbs(i));
+            }
+        }
+    }
+    Ok(limbs)
+}
+
+impl<F: PrimeField> R1CS<F> {
+    /// Rebuilds a witness packed by [`witness_to_limbs`], checking that it holds
+    /// exactly [`R1CS::expected_witness_len`] canonical field elements
+    pub fn witness_from_limbs(&self, limbs: &[u64], limbs_per_element: usize) -> Result<Vec<F>> {
+        let n = self.expected_witness_len();
+        if limbs_per_element == 0 || limbs.len() != n * limbs_per_element {
+            return Err(R1CSError::WitnessLengthMismatch {
+                expected: n * limbs_per_element.max(1),
+                actual: limbs.len(),
+            });
+        }
+
+        (0..n)
+            .map(|i| {
+                let mut bigint = F::BigInt::default();
+                let dst = bigint.as_mut();
+                for j in 0..limbs_per_element {
+                    let limb = limbs[j * n + i];
+                    match dst.get_mut(j) {
+                        Some(d) => *d = limb,
+                        None if limb == 0 => {}
+                        None => return Err(R1CSError::InvalidLimbs(i)),
+                    }
+                }
+                F::from_bigint(bigint).ok_or(R1CSError::InvalidLimbs(i))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use ark_ff::BigInteger;
+    use ark_std::io::Cursor;
+
+    /// Encodes small values in the sectioned version 2 layout, 32 bytes per value
+    fn wtns(values: &[u64]) -> Vec<u8> {
+        let mut bytes = b"wtns".to_vec();
+        bytes.extend(2u32.to_le_bytes());
+        bytes.extend(2u32.to_le_bytes());
+        bytes.extend(1u32.to_le_bytes());
+        bytes.extend(40u64.to_le_bytes());
+        bytes.extend(32u32.to_le_bytes());
+        bytes.extend(Fr::MODULUS.to_bytes_le());
+        bytes.extend((values.len() as u32).to_le_bytes());
+        bytes.extend(2u32.to_le_bytes());
+        bytes.extend((values.len() as u64 * 32).to_le_bytes());
+        for value in values {
+            bytes.extend(value.to_le_bytes());
+            bytes.extend([0u8; 24]);
+        }
+        bytes
+    }
+
+    fn frs(values: &[u64]) -> Vec<Fr> {
+        values.iter().map(|v| Fr::from(*v)).collect()
+    }
+
+    #[test]
+    fn converts_a_valid_witness() {
+        let file = WitnessFile::<Fr>::new(Cursor::new(wtns(&[1, 33, 3, 11]))).unwrap();
+        assert_eq!(file.version, 2);
+        assert_eq!(file.header.n_witness, 4);
+        assert_eq!(Vec::try_from(file).unwrap(), frs(&[1, 33, 3, 11]));
+    }
+
+    #[test]
+    fn rejects_a_bad_constant_wire() {
+        let file = WitnessFile::<Fr>::new(Cursor::new(wtns(&[2, 33, 3, 11]))).unwrap();
+        assert!(matches!(
+            Vec::try_from(file),
+            Err(R1CSError::InvalidConstantWire)
+        ));
+        let file = WitnessFile::<Fr>::new(Cursor::new(wtns(&[]))).unwrap();
+        assert!(matches!(
+            Vec::try_from(file),
+            Err(R1CSError::InvalidConstantWire)
+        ));
+    }
+
+    #[test]
+    fn rejects_a_count_that_disagrees_with_the_values() {
+        let mut file = WitnessFile::<Fr>::new(Cursor::new(wtns(&[1, 33, 3, 11]))).unwrap();
+        file.witness.pop();
+        assert!(matches!(
+            Vec::try_from(file),
+            Err(R1CSError::WitnessLengthMismatch {
+                expected: 4,
+                actual: 3
+            })
+        ));
+    }
+
+    /// Counts of a system with four wires, the only part the limb packing looks at
+    fn four_wires() -> R1CS<Fr> {
+        R1CS::for_test(1, 0, 2, Vec::new())
+    }
+
+    #[test]
+    fn limbs_round_trip() {
+        let witness = vec![
+            Fr::from(1u64),
+            -Fr::from(1u64),
+            Fr::from(u64::MAX),
+            Fr::from(7u64),
+        ];
+        let limbs = witness_to_limbs(&witness, 4).unwrap();
+        assert_eq!(limbs.len(), 16);
+        // column-major: the low limbs of every element come first
+        assert_eq!(
+            &limbs[..4],
+            &[1, (-Fr::from(1u64)).into_bigint().0[0], u64::MAX, 7]
+        );
+        assert_eq!(four_wires().witness_from_limbs(&limbs, 4).unwrap(), witness);
+
+        // zero limbs past the width of the field are accepted
+        let limbs = witness_to_limbs(&witness, 5).unwrap();
+        assert_eq!(four_wires().witness_from_limbs(&limbs, 5).unwrap(), witness);
+    }
+
+    #[test]
+    fn small_values_fit_in_fewer_limbs() {
+        let witness = frs(&[1, 33, 3, 11]);
+        let limbs = witness_to_limbs(&witness, 1).unwrap();
+        assert_eq!(limbs, vec![1, 33, 3, 11]);
+        assert_eq!(four_wires().witness_from_limbs(&limbs, 1).unwrap(), witness);
+
+        let wide = vec![Fr::from(1u64), -Fr::from(1u64)];
+        assert!(matches!(
+            witness_to_limbs(&wide, 2),
+            Err(R1CSError::InvalidLimbs(1))
+        ));
+    }
+
+    #[test]
+    fn rejects_limbs_of_the_wrong_length() {
+        let limbs = witness_to_limbs(&frs(&[1, 33, 3]), 4).unwrap();
+        assert!(matches!(
+            four_wires().witness_from_limbs(&limbs, 4),
+            Err(R1CSError::WitnessLengthMismatch {
+                expected: 16,
+                actual: 12
+            })
+        ));
+        assert!(matches!(
+            four_wires().witness_from_limbs(&[], 0),
+            Err(R1CSError::WitnessLengthMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_limbs_past_the_modulus() {
+        let mut limbs = witness_to_limbs(&frs(&[1, 33, 3, 11]), 4).unwrap();
+        for (j, limb) in Fr::MODULUS.0.iter().enumerate() {
+            limbs[j * 4 + 2] = *limb;
+        }
+        assert!(matches!(
+            four_wires().witness_from_limbs(&limbs, 4),
+            Err(R1CSError::InvalidLimbs(2))
+        ));
+    }
+
+    #[test]
+    fn lru_evicts_the_least_recently_used() {
+        let mut cache = LruCache::new(2);
+        cache.insert(10, 'a');
+        cache.insert(20, 'b');
+        assert_eq!(cache.get(10), Some('a'));
+        // 20 is now the least recently used
+        cache.insert(30, 'c');
+        assert_eq!(cache.get(20), None);
+        assert_eq!(cache.get(10), Some('a'));
+        assert_eq!(cache.get(30), Some('c'));
+        cache.insert(40, 'd');
+        assert_eq!(cache.get(10), None);
+        assert_eq!((cache.get(30), cache.get(40)), (Some('c'), Some('d')));
+        assert_eq!(cache.slots.len(), 2);
+    }
+
+    #[test]
+    fn lru_of_one_keeps_the_latest() {
+        let mut cache = LruCache::new(1);
+        for index in 0..5 {
+            cache.insert(index, index * 2);
+            assert_eq!(cache.get(index), Some(index * 2));
+        }
+        assert_eq!(cache.get(3), None);
+        assert_eq!((cache.head, cache.tail), (0, 0));
+    }
+
+    /// Counts the reads made through it
+    struct CountingReader<R> {
+        inner: R,
+        reads: usize,
+    }
+
+    impl<R: Read> Read for CountingReader<R> {
+        fn read(&mut self, buf: &mut [u8]) -> ark_std::io::Result<usize> {
+            self.reads += 1;
+            self.inner.read(buf)
+        }
+    }
+
+    impl<R: Seek> Seek for CountingReader<R> {
+        fn seek(&mut self, pos: SeekFrom) -> ark_std::io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn witness_reader_fetches_values_on_demand() {
+        let source = CountingReader {
+            inner: Cursor::new(wtns(&[1, 33, 3, 11])),
+            reads: 0,
+        };
+        let mut reader = WitnessReader::<_, Fr>::with_cache_capacity(source, 2).unwrap();
+        assert_eq!(reader.len(), 4);
+        let after_header = reader.reader.reads;
+
+        assert_eq!(reader.get(3).unwrap(), Fr::from(11u64));
+        assert_eq!(reader.get(1).unwrap(), Fr::from(33u64));
+        let after_misses = reader.reader.reads;
+        assert!(after_misses > after_header);
+        // both are cached now
+        assert_eq!(reader.get(3).unwrap(), Fr::from(11u64));
+        assert_eq!(reader.get(1).unwrap(), Fr::from(33u64));
+        assert_eq!(reader.reader.reads, after_misses);
+        // evicting 3 makes it a miss again, with the right value
+        assert_eq!(reader.get(2).unwrap(), Fr::from(3u64));
+        assert_eq!(reader.get(3).unwrap(), Fr::from(11u64));
+        assert!(reader.reader.reads > after_misses);
+    }
+
+    #[test]
+    fn witness_reader_rejects_an_index_past_the_end() {
+        let mut reader = WitnessReader::<_, Fr>::new(Cursor::new(wtns(&[1, 33]))).unwrap();
+        assert!(matches!(
+            reader.get(2),
+            Err(R1CSError::WitnessIndexOutOfRange { index: 2, len: 2 })
+        ));
+    }
+
+    fn fixture(name: &str) -> Vec<u8> {
+        std::fs::read(format!("./test-vectors/{}", name)).unwrap()
+    }
+
+    #[test]
+    fn both_layouts_decode_to_the_same_witness() {
+        let v1 = WitnessFile::<Fr>::new(Cursor::new(fixture("mycircuit-v1.wtns"))).unwrap();
+        let v2 = WitnessFile::<Fr>::new(Cursor::new(fixture("mycircuit.wtns"))).unwrap();
+        assert_eq!((v1.version, v2.version), (1, 2));
+        assert_eq!(v1.header.prime, v2.header.prime);
+        assert_eq!(Vec::try_from(v1).unwrap(), frs(&[1, 33, 3, 11]));
+        assert_eq!(Vec::try_from(v2).unwrap(), frs(&[1, 33, 3, 11]));
+
+        let mut v1 =
+            WitnessReader::<_, Fr>::new(Cursor::new(fixture("mycircuit-v1.wtns"))).unwrap();
+        assert_eq!(v1.get(2).unwrap(), Fr::from(3u64));
+    }
+
+    #[test]
+    fn rejects_values_past_the_end_of_a_v1_file() {
+        let mut bytes = fixture("mycircuit-v1.wtns");
+        bytes.truncate(bytes.len() - 1);
+        assert!(matches!(
+            WitnessFile::<Fr>::new(Cursor::new(bytes)),
+            Err(R1CSError::SectionOutOfBounds { end: 176, len: 175 })
+        ));
+    }
+
+    #[test]
+    fn rejects_a_v2_section_past_the_end() {
+        let mut bytes = fixture("mycircuit.wtns");
+        bytes.truncate(bytes.len() - 32);
+        assert!(matches!(
+            WitnessFile::<Fr>::new(Cursor::new(bytes)),
+            Err(R1CSError::SectionOutOfBounds { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_an_unknown_version() {
+        let mut bytes = fixture("mycircuit.wtns");
+        bytes[4] = 3;
+        assert!(matches!(
+            WitnessFile::<Fr>::new(Cursor::new(bytes)),
+            Err(R1CSError::UnsupportedVersion(3))
+        ));
+    }
+
+    #[test]
+    fn public_signals_agree_despite_private_differences() {
+        let r1cs = four_wires();
+        assert!(same_public_signals(
+            &r1cs,
+            &frs(&[1, 9, 3, 4]),
+            &frs(&[1, 9, 7, 8])
+        ));
+        assert!(!same_public_signals(
+            &r1cs,
+            &frs(&[1, 9, 3, 4]),
+            &frs(&[1, 8, 3, 4])
+        ));
+        // the private part may even be missing, but the public signals may not
+        assert!(same_public_signals(
+            &r1cs,
+            &frs(&[1, 9]),
+            &frs(&[1, 9, 7, 8])
+        ));
+        assert!(!same_public_signals(&r1cs, &frs(&[1]), &frs(&[1])));
+    }
+
+    /// `wtns(values)` with each value written big-endian
+    fn wtns_big_endian(values: &[u64]) -> Vec<u8> {
+        let mut bytes = wtns(values);
+        let start = bytes.len() - values.len() * 32;
+        bytes[start..]
+            .chunks_mut(32)
+            .for_each(|value| value.reverse());
+        bytes
+    }
+
+    fn big_endian() -> WitnessReaderOptions {
+        WitnessReaderOptions { big_endian: true }
+    }
+
+    #[test]
+    fn decodes_a_big_endian_witness_under_the_option() {
+        let bytes = wtns_big_endian(&[1, 33, 3, 11]);
+        let file = WitnessFile::<Fr>::new_with_options(Cursor::new(&bytes), big_endian()).unwrap();
+        assert_eq!(file.header.prime, Fr::MODULUS.to_bytes_le());
+        assert_eq!(Vec::try_from(file).unwrap(), frs(&[1, 33, 3, 11]));
+
+        let mut reader =
+            WitnessReader::<_, Fr>::with_options(Cursor::new(&bytes), 0, big_endian()).unwrap();
+        assert_eq!(reader.get(1).unwrap(), Fr::from(33u64));
+    }
+
+    #[test]
+    fn values_are_little_endian_by_default() {
+        let file = WitnessFile::<Fr>::new(Cursor::new(wtns_big_endian(&[1]))).unwrap();
+        let mut value = [0u8; 32];
+        value[31] = 1;
+        assert_eq!(file.witness[0], Fr::from_le_bytes_mod_order(&value));
+        assert_ne!(file.witness[0], Fr::from(1u64));
+    }
+}
@@ -0,0 +1,135 @@
+//! Writer for circom `.wtns` files, in the sectioned version 2 layout
+//!
+//! The value count is known up front, so unlike [`crate::R1CSWriter`] the output does
+//! not need to be seekable.
+use ark_ff::{BigInteger, PrimeField};
+use byteorder::{LittleEndian, WriteBytesExt};
+
+use ark_std::io::Write;
+
+use std::convert::TryFrom;
+
+use crate::error::{R1CSError, Result};
+use crate::WitnessFile;
+
+const HEADER_TYPE: u32 = 1;
+const WITNESS_TYPE: u32 = 2;
+
+/// Writes `witness` as a `.wtns` file for the prime of `F`. Values take the size of the
+/// modulus rounded up to a multiple of 8 bytes, as circom writes them.
+pub fn write_witness<W: Write, F: PrimeField>(writer: W, witness: &[F]) -> Result<W> {
+    let field_size = (F::MODULUS_BIT_SIZE as usize).div_ceil(64) * 8;
+    let mut prime = F::MODULUS.to_bytes_le();
+    prime.resize(field_size, 0);
+    write_sections(writer, &prime, witness)
+}
+
+impl<F: PrimeField> WitnessFile<F> {
+    /// Writes the values with the prime and field size of the header. Version 1 files
+    /// are written in the version 2 layout.
+    pub fn write<W: Write>(&self, writer: W) -> Result<W> {
+        write_sections(writer, &self.header.prime, &self.witness)
+    }
+}
+
+fn write_sections<W: Write, F: PrimeField>(
+    mut writer: W,
+    prime: &[u8],
+    witness: &[F],
+) -> Result<W> {
+    let field_size = prime.len();
+    let n_witness = u32::try_from(witness.len())
+        .map_err(|_| R1CSError::InvalidInput("too many witness values".to_string()))?;
+
+    writer.write_all(b"wtns")?;
+    writer.write_u32::<LittleEndian>(2)?;
+    writer.write_u32::<LittleEndian>(2)?;
+
+    writer.write_u32::<LittleEndian>(HEADER_TYPE)?;
+    writer.write_u64::<LittleEndian>(8 + field_size as u64)?;
+    writer.write_u32::<LittleEndian>(field_size as u32)?;
+    writer.write_all(prime)?;
+    writer.write_u32::<LittleEndian>(n_witness)?;
+
+    writer.write_u32::<LittleEndian>(WITNESS_TYPE)?;
+    writer.write_u64::<LittleEndian>(witness.len() as u64 * field_size as u64)?;
+    for value in witness {
+        let mut bytes = value.into_bigint().to_bytes_le();
+        if bytes.iter().skip(field_size).any(|b| *b != 0) {
+            return Err(R1CSError::InvalidFieldSize(field_size as u32));
+        }
+        bytes.resize(field_size, 0);
+        writer.write_all(&bytes)?;
+    }
+    Ok(writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use ark_std::io::Cursor;
+    use std::convert::TryInto;
+
+    use crate::field::M31;
+    use crate::witness::WitnessHeader;
+
+    fn read<F: PrimeField>(bytes: Vec<u8>) -> Vec<F> {
+        WitnessFile::<F>::new(Cursor::new(bytes))
+            .unwrap()
+            .try_into()
+            .unwrap()
+    }
+
+    #[test]
+    fn round_trips_a_witness() {
+        let witness = vec![
+            Fr::from(1u64),
+            -Fr::from(1u64),
+            Fr::from(u64::MAX),
+            Fr::from(0u64),
+        ];
+        let bytes = write_witness(Vec::new(), &witness).unwrap();
+        assert_eq!(&bytes[..8], b"wtns\x02\x00\x00\x00");
+        assert_eq!(bytes.len(), 12 + 12 + 40 + 12 + 4 * 32);
+        assert_eq!(read::<Fr>(bytes), witness);
+    }
+
+    #[test]
+    fn small_fields_take_eight_bytes() {
+        let witness = vec![M31::from(1u64), -M31::from(1u64)];
+        let bytes = write_witness(Vec::new(), &witness).unwrap();
+        let file = WitnessFile::<M31>::new(Cursor::new(&bytes)).unwrap();
+        assert_eq!(file.header.field_size, 8);
+        assert_eq!(file.witness, witness);
+    }
+
+    #[test]
+    fn read_write_read_reproduces_the_fixture() {
+        let fixture = std::fs::read("./test-vectors/mycircuit.wtns").unwrap();
+        let file = WitnessFile::<Fr>::new(Cursor::new(&fixture)).unwrap();
+        assert_eq!(file.write(Vec::new()).unwrap(), fixture);
+
+        // version 1 files come back in the version 2 layout
+        let v1 = std::fs::File::open("./test-vectors/mycircuit-v1.wtns").unwrap();
+        let file = WitnessFile::<Fr>::new(v1).unwrap();
+        assert_eq!(file.write(Vec::new()).unwrap(), fixture);
+    }
+
+    #[test]
+    fn rejects_values_wider_than_the_field_size() {
+        let file = WitnessFile {
+            version: 2,
+            header: WitnessHeader {
+                field_size: 4,
+                prime: vec![0xff; 4],
+                n_witness: 1,
+            },
+            witness: vec![Fr::from(u64::MAX)],
+        };
+        assert!(matches!(
+            file.write(Vec::new()),
+            Err(R1CSError::InvalidFieldSize(4))
+        ));
+    }
+}
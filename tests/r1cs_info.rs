@@ -0,0 +1,61 @@
+//! Runs the `r1cs-info` binary on the test vectors
+use std::process::Command;
+
+fn r1cs_info(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_r1cs-info"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn prints_summary_and_named_constraints() {
+    let output = r1cs_info(&[
+        "test-vectors/mycircuit.r1cs",
+        "--field",
+        "bn254",
+        "--sym",
+        "test-vectors/mycircuit.sym",
+        "--constraints",
+        "1",
+    ]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("constraints:    1"));
+    assert!(stdout.contains("wires:          4"));
+    assert!(stdout.contains("public outputs: 1"));
+    assert!(stdout.contains("0: (-main.a) * (main.b) = (-main.c)"));
+}
+
+#[test]
+fn raw_prints_canonical_coefficients() {
+    let output = r1cs_info(&[
+        "test-vectors/mycircuit.r1cs",
+        "--field",
+        "bn254",
+        "--constraints",
+        "1",
+        "--raw",
+    ]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!stdout.contains("(-"));
+}
+
+#[test]
+fn wrong_field_fails() {
+    let output = r1cs_info(&["test-vectors/mycircuit.r1cs", "--field", "m31"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .starts_with("error: "));
+}
+
+#[test]
+fn usage_on_bad_arguments() {
+    let output = r1cs_info(&["--constraints", "many"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .starts_with("usage: r1cs-info"));
+}